@@ -1,20 +1,24 @@
 use shaderc;
 use std::path::{Path, PathBuf};
 
+/// Relative to the client crate, which build scripts run in.
+const SHADER_DIR: &str = "../resources/shaders";
+
 fn main() {
-    println!("cargo:rerun-if-changed=resources/shaders");
+    println!("cargo:rerun-if-changed={}", SHADER_DIR);
 
-    for entry in std::fs::read_dir("resources/shaders").expect("Shaders directory should exist") {
+    for entry in std::fs::read_dir(SHADER_DIR).expect("Shaders directory should exist") {
         let entry = entry.unwrap();
         let path = entry.path();
 
         if let Some(extension) = path.extension().and_then(|os_str| os_str.to_str()) {
             match extension.to_ascii_lowercase().as_str() {
-                ext @ "vert" | ext @ "frag" => {
+                ext @ "vert" | ext @ "frag" | ext @ "comp" => {
                     println!("cargo:rerun-if-changed={}", path.to_string_lossy());
                     let shader_kind = match ext {
                         "vert" => shaderc::ShaderKind::Vertex,
                         "frag" => shaderc::ShaderKind::Fragment,
+                        "comp" => shaderc::ShaderKind::Compute,
                         _ => panic!("Unexpected shader type"),
                     };
 
@@ -0,0 +1,130 @@
+use crate::{
+    graphics::{FrameEncoder, GraphicsDevice, GraphicsDeviceConfig},
+    input::InputState,
+};
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+/// A game or tool driven by `run_app`, which owns the window and event loop.
+pub trait App: 'static {
+    /// Called once per frame before `render`, with the input for the frame
+    /// and the duration of the previous frame in seconds.
+    fn update(&mut self, input: &InputState, dt: f32);
+
+    /// Draws the frame. `run_app` finishes the frame afterwards. The app stays
+    /// borrowed for the whole frame, so its drawables can be `submit`ted to layers.
+    fn render<'a>(&'a mut self, frame: &mut FrameEncoder<'a>);
+
+    /// Called with every winit event before it's handled, for input which
+    /// `InputState` doesn't cover, like typing into a `TextInput`.
+    fn handle_event(&mut self, _event: &Event<()>) {}
+
+    /// Checked after each `update`, return true to close the window and exit.
+    fn should_exit(&self) -> bool {
+        false
+    }
+}
+
+/// Opens a window titled `title`, creates the graphics device and then the app
+/// with `create_app`, and runs the event loop until the window is closed.
+/// Resizing, scale factor changes, suspending and input are handled before
+/// the app sees them.
+///
+/// This never returns, like `EventLoop::run`. On the web the device is created
+/// asynchronously, so this returns right away there and the app starts later.
+/// The window's canvas is appended to the document body here, so callers don't
+/// have to.
+pub fn run_app<A, F>(title: &'static str, config: GraphicsDeviceConfig, create_app: F)
+where
+    A: App,
+    F: FnOnce(&mut GraphicsDevice, &Window) -> A + 'static,
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    futures::executor::block_on(run_app_async(title, config, create_app));
+
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(run_app_async(title, config, create_app));
+}
+
+async fn run_app_async<A, F>(title: &'static str, config: GraphicsDeviceConfig, create_app: F)
+where
+    A: App,
+    F: FnOnce(&mut GraphicsDevice, &Window) -> A,
+{
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(title)
+        .with_transparent(config.transparent)
+        .build(&event_loop)
+        .unwrap();
+
+    // wgpu looks for the window's canvas in the document when creating the surface.
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
+
+        web_sys::window()
+            .and_then(|web_window| web_window.document())
+            .and_then(|document| document.body())
+            .and_then(|body| body.append_child(&web_sys::Element::from(window.canvas())).ok())
+            .expect("Couldn't append the canvas to the document body");
+    }
+
+    let mut graphics_device = GraphicsDevice::new(&window, config).await;
+    let mut app = create_app(&mut graphics_device, &window);
+    let mut input = InputState::new(window.scale_factor());
+
+    event_loop.run(move |event, _, control_flow| {
+        input.update(&event);
+        app.handle_event(&event);
+
+        match event {
+            Event::MainEventsCleared => {
+                // The frame clock ticks in `begin_frame`, so this is the last frame's duration.
+                let dt = graphics_device.frame_clock().delta_seconds();
+                app.update(&input, dt);
+                input.end_frame();
+
+                if app.should_exit() {
+                    *control_flow = ControlFlow::Exit;
+                } else if graphics_device.is_suspended() {
+                    // Nothing can be drawn until `Resumed`, so sleep until the next event.
+                    *control_flow = ControlFlow::Wait;
+                } else {
+                    *control_flow = ControlFlow::Poll;
+                    window.request_redraw();
+                }
+            },
+            Event::RedrawRequested(_window_id) => {
+                if let Some(mut frame_encoder) = graphics_device.begin_frame() {
+                    app.render(&mut frame_encoder);
+                    frame_encoder.finish();
+                }
+            },
+            Event::Suspended => graphics_device.suspend(),
+            Event::Resumed => graphics_device.resume(&window),
+            Event::WindowEvent { event: WindowEvent::Resized(new_size), .. } => {
+                graphics_device.resize(new_size);
+                if !graphics_device.is_suspended() {
+                    window.request_redraw();
+                }
+            },
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
+                ..
+            } => {
+                graphics_device.set_scale_factor(scale_factor, *new_inner_size);
+                if !graphics_device.is_suspended() {
+                    window.request_redraw();
+                }
+            },
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                *control_flow = ControlFlow::Exit;
+            },
+            _ => {},
+        }
+    });
+}
@@ -0,0 +1,221 @@
+use crate::graphics::{FrameClock, Rect, TextureAtlas};
+use std::time::Duration;
+
+/// What an `Animation` does after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackMode {
+    /// Start over from the first frame.
+    Loop,
+
+    /// Stay on the last frame and mark the animation as complete.
+    Once,
+}
+
+/// A flipbook animation over regions of a `TextureAtlas`. Every frame
+/// is shown for the same duration. Call `update` once per frame and
+/// draw the region from `current_uv_rect`.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<String>,
+    frame_duration: Duration,
+    mode: PlaybackMode,
+    speed: f32,
+    paused: bool,
+
+    /// Time into the animation, at normal speed.
+    elapsed: Duration,
+    complete: bool,
+}
+
+impl Animation {
+    /// Creates an animation showing the atlas regions called `frames` in order.
+    pub fn new<S: Into<String>>(
+        frames: impl IntoIterator<Item = S>,
+        frame_duration: Duration,
+        mode: PlaybackMode,
+    ) -> Self {
+        Self {
+            frames: frames.into_iter().map(Into::into).collect(),
+            frame_duration,
+            mode,
+            speed: 1.0,
+            paused: false,
+            elapsed: Duration::from_secs(0),
+            complete: false,
+        }
+    }
+
+    /// Advances the animation by the time since the last frame.
+    pub fn update(&mut self, frame_clock: &FrameClock) {
+        self.advance(frame_clock.delta());
+    }
+
+    /// Advances the animation by `delta`, scaled by the speed.
+    pub fn advance(&mut self, delta: Duration) {
+        if self.paused || self.complete || self.frames.is_empty() {
+            return;
+        }
+
+        let total = self.frame_duration * self.frames.len() as u32;
+        if total == Duration::from_secs(0) {
+            return;
+        }
+
+        // In seconds, as scaling the `Duration` panics when it overflows at high speeds.
+        let elapsed = self.elapsed.as_secs_f64() + delta.as_secs_f64() * f64::from(self.speed);
+        if elapsed < total.as_secs_f64() {
+            self.elapsed = Duration::from_secs_f64(elapsed);
+            return;
+        }
+
+        match self.mode {
+            PlaybackMode::Loop => {
+                self.elapsed = Duration::from_secs_f64(elapsed % total.as_secs_f64());
+            },
+            PlaybackMode::Once => {
+                self.elapsed = total;
+                self.complete = true;
+            },
+        }
+    }
+
+    /// The index of the frame being shown.
+    pub fn current_frame(&self) -> usize {
+        if self.frames.is_empty() || self.frame_duration == Duration::from_secs(0) {
+            return 0;
+        }
+
+        let frame = (self.elapsed.as_secs_f64() / self.frame_duration.as_secs_f64()) as usize;
+        frame.min(self.frames.len() - 1)
+    }
+
+    /// The atlas region name of the frame being shown.
+    pub fn current_frame_name(&self) -> Option<&str> {
+        self.frames.get(self.current_frame()).map(String::as_str)
+    }
+
+    /// The UV rect of the frame being shown, see `TextureAtlas::uv_rect`.
+    pub fn current_uv_rect(&self, atlas: &TextureAtlas) -> Option<Rect> {
+        self.current_frame_name().and_then(|name| atlas.uv_rect(name))
+    }
+
+    /// Sets the playback speed, 1.0 is normal speed and 2.0 twice as fast.
+    /// Negative speeds are treated as 0.0, and infinite ones as `f32::MAX`.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0).min(f32::MAX);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Goes back to the first frame and clears the completed state. Doesn't unpause.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+        self.complete = false;
+    }
+
+    /// Whether a `PlaybackMode::Once` animation has reached its end.
+    /// Looping animations never complete.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(mode: PlaybackMode) -> Animation {
+        Animation::new(vec!["a", "b", "c"], Duration::from_millis(100), mode)
+    }
+
+    #[test]
+    fn frames_step_with_elapsed_time() {
+        let mut animation = animation(PlaybackMode::Loop);
+        assert_eq!(animation.current_frame_name(), Some("a"));
+
+        animation.advance(Duration::from_millis(50));
+        assert_eq!(animation.current_frame(), 0);
+
+        animation.advance(Duration::from_millis(60));
+        assert_eq!(animation.current_frame(), 1);
+
+        animation.advance(Duration::from_millis(150));
+        assert_eq!(animation.current_frame_name(), Some("c"));
+    }
+
+    #[test]
+    fn looping_animation_wraps_around() {
+        let mut animation = animation(PlaybackMode::Loop);
+
+        animation.advance(Duration::from_millis(350));
+
+        assert_eq!(animation.current_frame(), 0);
+        assert!(!animation.is_complete());
+    }
+
+    #[test]
+    fn one_shot_animation_stops_on_last_frame() {
+        let mut animation = animation(PlaybackMode::Once);
+
+        animation.advance(Duration::from_millis(350));
+        assert_eq!(animation.current_frame(), 2);
+        assert!(animation.is_complete());
+
+        animation.reset();
+        assert_eq!(animation.current_frame(), 0);
+        assert!(!animation.is_complete());
+    }
+
+    #[test]
+    fn paused_animation_does_not_advance() {
+        let mut animation = animation(PlaybackMode::Loop);
+
+        animation.pause();
+        animation.advance(Duration::from_millis(150));
+        assert_eq!(animation.current_frame(), 0);
+
+        animation.resume();
+        animation.advance(Duration::from_millis(150));
+        assert_eq!(animation.current_frame(), 1);
+    }
+
+    #[test]
+    fn speed_scales_elapsed_time() {
+        let mut animation = animation(PlaybackMode::Loop);
+
+        animation.set_speed(2.0);
+        animation.advance(Duration::from_millis(100));
+        assert_eq!(animation.current_frame(), 2);
+
+        animation.set_speed(-1.0);
+        animation.advance(Duration::from_millis(100));
+        assert_eq!(animation.current_frame(), 2);
+    }
+
+    #[test]
+    fn huge_speeds_do_not_overflow() {
+        let mut looping = animation(PlaybackMode::Loop);
+        looping.set_speed(f32::INFINITY);
+        looping.advance(Duration::from_secs(60));
+        assert!(looping.current_frame() < 3);
+
+        let mut once = animation(PlaybackMode::Once);
+        once.set_speed(f32::MAX);
+        once.advance(Duration::from_secs(60));
+        assert!(once.is_complete());
+    }
+}
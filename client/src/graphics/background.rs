@@ -0,0 +1,135 @@
+use crate::graphics::{color_target, Color, FrameEncoder, FullscreenPass, GraphicsDevice};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer};
+
+const VERTICAL_GRADIENT: u32 = 0;
+const RADIAL: u32 = 1;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct BackgroundUniforms {
+    /// The top or center color.
+    inner_color: [f32; 4],
+
+    /// The bottom or edge color.
+    outer_color: [f32; 4],
+
+    mode: u32,
+    _padding: [u32; 3],
+}
+
+/// A solid color or gradient filling the frame, drawn with a `FullscreenPass`
+/// in place of clearing it. Draw it before everything else, or submit it to
+/// the `RenderLayer::BACKGROUND` layer. When letterboxed it fills the viewport.
+pub struct Background {
+    pass: FullscreenPass,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    uniforms: BackgroundUniforms,
+    uniforms_dirty: bool,
+}
+
+impl Background {
+    pub fn solid(graphics_device: &GraphicsDevice, color: Color) -> Self {
+        Self::new(graphics_device, color, color, VERTICAL_GRADIENT)
+    }
+
+    /// Blends from `top` at the top of the frame to `bottom` at the bottom.
+    pub fn vertical_gradient(graphics_device: &GraphicsDevice, top: Color, bottom: Color) -> Self {
+        Self::new(graphics_device, top, bottom, VERTICAL_GRADIENT)
+    }
+
+    /// Blends from `center` in the middle of the frame to `edge` at the corners.
+    pub fn radial(graphics_device: &GraphicsDevice, center: Color, edge: Color) -> Self {
+        Self::new(graphics_device, center, edge, RADIAL)
+    }
+
+    fn new(graphics_device: &GraphicsDevice, inner: Color, outer: Color, mode: u32) -> Self {
+        let device = graphics_device.device();
+        let uniform_size = std::mem::size_of::<BackgroundUniforms>() as u64;
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Background Uniform Buffer"),
+            size: uniform_size,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("background"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(uniform_size),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(uniform_size),
+                },
+            }],
+            label: Some("background"),
+        });
+
+        let format = graphics_device.color_format();
+        let pass = FullscreenPass::new(
+            graphics_device,
+            "background",
+            &[&bind_group_layout],
+            &include_shader!("background.frag"),
+            color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING)),
+        );
+
+        let uniforms = BackgroundUniforms {
+            inner_color: inner.to_f32_array(),
+            outer_color: outer.to_f32_array(),
+            mode,
+            _padding: [0; 3],
+        };
+
+        Self { pass, uniform_buffer, bind_group, uniforms, uniforms_dirty: true }
+    }
+
+    /// Changes the colors, e.g. for a day and night cycle. `inner` is the top
+    /// or center color and `outer` the bottom or edge color, for a solid
+    /// background pass the same color twice. They're uploaded with the next `render`.
+    pub fn set_colors(&mut self, inner: Color, outer: Color) {
+        let (inner, outer) = (inner.to_f32_array(), outer.to_f32_array());
+
+        if (inner, outer) != (self.uniforms.inner_color, self.uniforms.outer_color) {
+            self.uniforms.inner_color = inner;
+            self.uniforms.outer_color = outer;
+            self.uniforms_dirty = true;
+        }
+    }
+
+    /// Fills the frame target, or the letterbox viewport when there is one.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder) {
+        if self.uniforms_dirty {
+            frame_encoder.queue().write_buffer(
+                &self.uniform_buffer,
+                0,
+                bytemuck::bytes_of(&self.uniforms),
+            );
+            self.uniforms_dirty = false;
+        }
+
+        match frame_encoder.viewport() {
+            Some(viewport) => {
+                self.pass.render_in_viewport(frame_encoder, &[&self.bind_group], viewport)
+            },
+            None => self.pass.render(frame_encoder, &[&self.bind_group], None),
+        }
+    }
+}
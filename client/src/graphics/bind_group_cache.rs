@@ -0,0 +1,74 @@
+use crate::graphics::resource_tracker::{ResourceKind, Tracked};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use wgpu::BindGroup;
+
+static NEXT_RESOURCE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A unique id for a GPU resource, as wgpu resources can't be compared or
+/// hashed. `Texture` and `RenderTarget` have one, and other resources used
+/// in cached bind groups (like uniform buffers) can be given one with `new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId(u64);
+
+impl ResourceId {
+    /// A new id, different from every other id.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        ResourceId(NEXT_RESOURCE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Bind groups for one bind group layout, keyed by the resources bound in
+/// them, so switching between a handful of textures doesn't create a new bind
+/// group every frame. Use one cache per layout, as the key doesn't include it.
+#[derive(Default)]
+pub struct BindGroupCache {
+    bind_groups: HashMap<Vec<ResourceId>, (Arc<BindGroup>, Tracked)>,
+}
+
+impl BindGroupCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the bind group cached for `resources`, or creates it with `create`
+    /// and caches it. List the resources in the same order every time.
+    pub fn get_or_create<F: FnOnce() -> BindGroup>(
+        &mut self,
+        resources: &[ResourceId],
+        create: F,
+    ) -> Arc<BindGroup> {
+        if let Some((bind_group, _)) = self.bind_groups.get(resources) {
+            return bind_group.clone();
+        }
+
+        let bind_group = Arc::new(create());
+        let tracked = Tracked::new(ResourceKind::BindGroup);
+        self.bind_groups.insert(resources.to_vec(), (bind_group.clone(), tracked));
+        bind_group
+    }
+
+    /// Drops the cached bind groups which use `resource`, call this when
+    /// it's destroyed or replaced so they don't keep it alive.
+    pub fn remove_resource(&mut self, resource: ResourceId) {
+        self.bind_groups.retain(|resources, _| !resources.contains(&resource));
+    }
+
+    pub fn len(&self) -> usize {
+        self.bind_groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bind_groups.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.bind_groups.clear();
+    }
+}
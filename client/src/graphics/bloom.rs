@@ -0,0 +1,394 @@
+use crate::graphics::{
+    color_target,
+    resource_tracker::{ResourceKind, Tracked},
+    FrameEncoder, FrameTarget, FullscreenPass, GraphicsDevice, RenderTarget,
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Sampler, Texture, TextureView,
+};
+
+/// Adds the blurred glow on top of what's in the frame target, keeping its alpha.
+const COMPOSITE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct BloomUniforms {
+    /// The blur direction in texels of the sampled texture, zero when not blurring.
+    direction: [f32; 2],
+    threshold: f32,
+    intensity: f32,
+}
+
+/// One level of the blur chain, `blurred` holds the result and
+/// `scratch` the horizontally blurred pass in between.
+struct BloomLevel {
+    blurred: BloomTexture,
+    scratch: BloomTexture,
+
+    /// For the horizontal blur, which samples the previous level's
+    /// `blurred` (or the thresholded scene for the first level).
+    horizontal: BindGroup,
+    vertical: BindGroup,
+    composite: BindGroup,
+}
+
+struct BloomTexture {
+    texture: Texture,
+    view: TextureView,
+    _tracked: Tracked,
+}
+
+impl BloomTexture {
+    fn new(device: &Device, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Bloom texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self { texture, view, _tracked: Tracked::new(ResourceKind::Texture) }
+    }
+
+    fn frame_target(&self) -> FrameTarget {
+        FrameTarget::View(self.texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+}
+
+/// A glow around bright parts of the scene. Pixels brighter than the threshold
+/// are extracted from a `RenderTarget`, blurred at half, quarter, etc. resolution,
+/// one level per iteration, and the levels are added back onto the frame target.
+/// It's meant for HDR scenes (see `GraphicsDeviceConfig::hdr`), drawn onto the
+/// scene render target before tone mapping, but works on LDR targets as well:
+///
+/// ```ignore
+/// let swap_chain_frame = frame_encoder.set_target(scene.frame_target());
+/// // ... draw the scene ...
+/// bloom.render(&mut frame_encoder);
+/// frame_encoder.set_target(swap_chain_frame);
+/// tone_map.render(&mut frame_encoder);
+/// ```
+///
+/// The source is bound up front, so call `resize` after resizing it.
+pub struct Bloom {
+    threshold_pass: FullscreenPass,
+    blur_pass: FullscreenPass,
+    composite_pass: FullscreenPass,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+
+    /// Threshold and intensity, for the threshold and composite passes.
+    params_buffer: Buffer,
+    horizontal_buffer: Buffer,
+    vertical_buffer: Buffer,
+
+    /// The thresholded scene at half resolution, which the first level blurs.
+    bright: BloomTexture,
+    source_bind_group: BindGroup,
+    levels: Vec<BloomLevel>,
+    format: wgpu::TextureFormat,
+    size: [u32; 2],
+
+    threshold: f32,
+    intensity: f32,
+    iterations: usize,
+    params_dirty: bool,
+}
+
+impl Bloom {
+    pub fn new(graphics_device: &GraphicsDevice, source: &RenderTarget) -> Self {
+        let device = graphics_device.device();
+        let uniform_size = std::mem::size_of::<BloomUniforms>() as u64;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { filtering: true, comparison: false },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(uniform_size),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let create_uniform_buffer = |label, uniforms: BloomUniforms| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::bytes_of(&uniforms),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            })
+        };
+        let blur = |direction| BloomUniforms { direction, threshold: 0.0, intensity: 0.0 };
+        let params_buffer = create_uniform_buffer("Bloom Params Buffer", blur([0.0, 0.0]));
+        let horizontal_buffer = create_uniform_buffer("Bloom Horizontal Buffer", blur([1.0, 0.0]));
+        let vertical_buffer = create_uniform_buffer("Bloom Vertical Buffer", blur([0.0, 1.0]));
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // The intermediate textures are in the scene's format, so
+        // they keep the HDR range when the scene has it.
+        let format = graphics_device.color_format();
+        let create_pass = |label, shader, blend| {
+            FullscreenPass::new(
+                graphics_device,
+                label,
+                &[&bind_group_layout],
+                shader,
+                color_target(format, Some(blend)),
+            )
+        };
+        let threshold_pass = create_pass(
+            "bloom threshold",
+            &include_shader!("bloom_threshold.frag"),
+            wgpu::BlendState::REPLACE,
+        );
+        let blur_pass = create_pass(
+            "bloom blur",
+            &include_shader!("bloom_blur.frag"),
+            wgpu::BlendState::REPLACE,
+        );
+        let composite_pass = create_pass(
+            "bloom composite",
+            &include_shader!("bloom_composite.frag"),
+            COMPOSITE_BLENDING,
+        );
+
+        let size = Self::base_size(source);
+        let bright = BloomTexture::new(device, format, size[0], size[1]);
+        let source_bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            source.view(),
+            &sampler,
+            &params_buffer,
+        );
+
+        let mut bloom = Self {
+            threshold_pass,
+            blur_pass,
+            composite_pass,
+            bind_group_layout,
+            sampler,
+            params_buffer,
+            horizontal_buffer,
+            vertical_buffer,
+            bright,
+            source_bind_group,
+            levels: Vec::new(),
+            format,
+            size,
+            threshold: 1.0,
+            intensity: 1.0,
+            iterations: 4,
+            params_dirty: true,
+        };
+        bloom.create_levels(device);
+
+        bloom
+    }
+
+    /// Half the size of the source, where the first blur level starts.
+    fn base_size(source: &RenderTarget) -> [u32; 2] {
+        let (width, height) = source.size();
+        [(width / 2).max(1), (height / 2).max(1)]
+    }
+
+    fn create_bind_group(
+        device: &Device,
+        layout: &BindGroupLayout,
+        view: &TextureView,
+        sampler: &Sampler,
+        uniform_buffer: &Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: uniform_buffer,
+                        offset: 0,
+                        size: None,
+                    },
+                },
+            ],
+            label: Some("bloom"),
+        })
+    }
+
+    fn create_levels(&mut self, device: &Device) {
+        let mut levels: Vec<BloomLevel> = Vec::with_capacity(self.iterations);
+
+        for index in 0..self.iterations {
+            let width = (self.size[0] >> index).max(1);
+            let height = (self.size[1] >> index).max(1);
+            let blurred = BloomTexture::new(device, self.format, width, height);
+            let scratch = BloomTexture::new(device, self.format, width, height);
+
+            // Each level blurs a downsampled copy of the level above it.
+            let input = levels.last().map_or(&self.bright.view, |level| &level.blurred.view);
+            let bind_group = |view, buffer| {
+                Self::create_bind_group(
+                    device,
+                    &self.bind_group_layout,
+                    view,
+                    &self.sampler,
+                    buffer,
+                )
+            };
+            let horizontal = bind_group(input, &self.horizontal_buffer);
+            let vertical = bind_group(&scratch.view, &self.vertical_buffer);
+            let composite = bind_group(&blurred.view, &self.params_buffer);
+
+            levels.push(BloomLevel { blurred, scratch, horizontal, vertical, composite });
+        }
+
+        self.levels = levels;
+    }
+
+    /// Only pixels brighter than this contribute, 1.0 by default so
+    /// only HDR colors glow. Lower it for LDR scenes.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        if threshold != self.threshold {
+            self.threshold = threshold;
+            self.params_dirty = true;
+        }
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Scales the glow added to the frame target, 1.0 by default.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        if intensity != self.intensity {
+            self.intensity = intensity;
+            self.params_dirty = true;
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// How many blur levels there are, each at half the resolution of the one
+    /// before. More levels spread the glow further. 4 by default, at least 1.
+    pub fn set_iterations(&mut self, graphics_device: &GraphicsDevice, iterations: usize) {
+        let iterations = iterations.max(1);
+        if iterations != self.iterations {
+            self.iterations = iterations;
+            self.create_levels(graphics_device.device());
+        }
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Switches to `source` and recreates the intermediate textures if its size
+    /// changed. Call this after `RenderTarget::resize`.
+    pub fn resize(&mut self, graphics_device: &GraphicsDevice, source: &RenderTarget) {
+        let device = graphics_device.device();
+        self.source_bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            source.view(),
+            &self.sampler,
+            &self.params_buffer,
+        );
+
+        let size = Self::base_size(source);
+        if size != self.size {
+            self.size = size;
+            self.bright = BloomTexture::new(device, self.format, size[0], size[1]);
+            self.create_levels(device);
+        }
+    }
+
+    /// Blurs the bright parts of the source and adds them onto the frame target of
+    /// `frame_encoder`, which has to be in `GraphicsDevice::color_format`.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder) {
+        if self.params_dirty {
+            let uniforms = BloomUniforms {
+                direction: [0.0, 0.0],
+                threshold: self.threshold,
+                intensity: self.intensity,
+            };
+            frame_encoder.queue().write_buffer(
+                &self.params_buffer,
+                0,
+                bytemuck::bytes_of(&uniforms),
+            );
+            self.params_dirty = false;
+        }
+
+        let frame_target = frame_encoder.set_target(self.bright.frame_target());
+        self.threshold_pass.render(frame_encoder, &[&self.source_bind_group], None);
+
+        for level in &self.levels {
+            frame_encoder.set_target(level.scratch.frame_target());
+            self.blur_pass.render(frame_encoder, &[&level.horizontal], None);
+
+            frame_encoder.set_target(level.blurred.frame_target());
+            self.blur_pass.render(frame_encoder, &[&level.vertical], None);
+        }
+
+        frame_encoder.set_target(frame_target);
+        for level in &self.levels {
+            self.composite_pass.render(frame_encoder, &[&level.composite], None);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::graphics::resource_tracker::{ResourceKind, Tracked};
+use std::ops::Range;
+use wgpu::{Buffer, BufferAddress, BufferSlice, BufferUsage, Device, Queue};
+
+struct PooledBuffer {
+    buffer: Buffer,
+    capacity: BufferAddress,
+    _tracked: Tracked,
+}
+
+/// Where `BufferPool::write` put the data, look the buffer up with `BufferPool::buffer`.
+/// Only valid until the pool moves on to the next frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PooledSlice {
+    index: usize,
+    size: BufferAddress,
+}
+
+impl PooledSlice {
+    /// The bytes of the buffer holding the data.
+    pub fn range(&self) -> Range<BufferAddress> {
+        0..self.size
+    }
+}
+
+/// Reusable buffers for data which is uploaded again every frame, like the
+/// vertices of dynamic drawables. Buffers are kept in `ring_size` sets, one
+/// set per frame, so a buffer is only written again `ring_size` frames after
+/// it was last used, and a buffer is only reallocated when the data no longer
+/// fits. Buffers grow to the next power of two, and never shrink.
+///
+/// Call `next_frame` at the start of each frame, then `write` once per
+/// buffer worth of data that frame. The `GraphicsDevice` has a pool for
+/// vertex and index data which it moves on itself, see `FrameEncoder::write_dynamic`.
+pub struct BufferPool {
+    label: &'static str,
+    usage: BufferUsage,
+    rings: Vec<Vec<PooledBuffer>>,
+    ring_index: usize,
+
+    /// How many buffers of the current ring were handed out this frame.
+    used: usize,
+}
+
+impl BufferPool {
+    /// `COPY_DST` is added to `usage`, as the buffers are written with the queue.
+    pub fn new(label: &'static str, usage: BufferUsage, ring_size: usize) -> Self {
+        Self {
+            label,
+            usage: usage | BufferUsage::COPY_DST,
+            rings: (0..ring_size.max(1)).map(|_| Vec::new()).collect(),
+            ring_index: 0,
+            used: 0,
+        }
+    }
+
+    /// Moves on to the buffers of the next frame.
+    pub fn next_frame(&mut self) {
+        self.ring_index = (self.ring_index + 1) % self.rings.len();
+        self.used = 0;
+    }
+
+    /// Uploads `data` into the next free buffer of this frame and returns where
+    /// it is. Each write gets its own buffer, so e.g. vertices and indices can be
+    /// written and then bound together. `data` must not be empty, as wgpu doesn't
+    /// allow empty buffer slices.
+    pub fn write(&mut self, device: &Device, queue: &Queue, data: &[u8]) -> PooledSlice {
+        let size = data.len() as BufferAddress;
+
+        // Queue writes have to be a multiple of 4 bytes.
+        let align = wgpu::COPY_BUFFER_ALIGNMENT;
+        let padded_size = ((size + align - 1) / align * align).max(align);
+
+        let ring = &mut self.rings[self.ring_index];
+
+        if self.used == ring.len() {
+            ring.push(Self::create_buffer(device, self.label, self.usage, padded_size));
+        } else if ring[self.used].capacity < padded_size {
+            ring[self.used] = Self::create_buffer(device, self.label, self.usage, padded_size);
+        }
+
+        let index = self.used;
+        let pooled = &ring[index];
+        self.used += 1;
+
+        if padded_size == size {
+            queue.write_buffer(&pooled.buffer, 0, data);
+        } else {
+            let mut padded = data.to_vec();
+            padded.resize(padded_size as usize, 0);
+            queue.write_buffer(&pooled.buffer, 0, &padded);
+        }
+
+        PooledSlice { index, size }
+    }
+
+    pub fn buffer(&self, slice: PooledSlice) -> &Buffer {
+        &self.rings[self.ring_index][slice.index].buffer
+    }
+
+    /// The part of `buffer` holding the data, e.g. for `set_vertex_buffer`.
+    pub fn slice(&self, slice: PooledSlice) -> BufferSlice {
+        self.buffer(slice).slice(slice.range())
+    }
+
+    fn create_buffer(
+        device: &Device,
+        label: &str,
+        usage: BufferUsage,
+        size: BufferAddress,
+    ) -> PooledBuffer {
+        let capacity = size.next_power_of_two();
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: capacity,
+            usage,
+            mapped_at_creation: false,
+        });
+
+        PooledBuffer { buffer, capacity, _tracked: Tracked::new(ResourceKind::Buffer) }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::graphics::{Rect, SamplerConfig};
+
+/// A 2D camera over a world measured in pixels, with the Y axis
+/// pointing down. At a zoom of 1.0 one world unit is one screen pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    /// The world position at the center of the screen.
+    pub position: [f32; 2],
+
+    /// Scale from world units to screen pixels.
+    pub zoom: f32,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self { position: [0.0, 0.0], zoom: 1.0 }
+    }
+}
+
+impl Camera2D {
+    pub fn new(position: [f32; 2], zoom: f32) -> Self {
+        Self { position, zoom }
+    }
+
+    /// The area of the world visible in a framebuffer of the given size.
+    pub fn visible_bounds(&self, width: f32, height: f32) -> Rect {
+        let visible_width = width / self.zoom;
+        let visible_height = height / self.zoom;
+
+        Rect::new(
+            self.position[0] - visible_width / 2.0,
+            self.position[1] - visible_height / 2.0,
+            visible_width,
+            visible_height,
+        )
+    }
+
+    /// Whether world pixels map to a whole number of screen pixels, or a whole
+    /// number of world pixels to one screen pixel, so pixel art stays even.
+    pub fn is_integer_zoom(&self) -> bool {
+        let scale = if self.zoom >= 1.0 { self.zoom } else { 1.0 / self.zoom };
+        (scale - scale.round()).abs() < 0.001
+    }
+
+    /// The mip level matching the zoom, for sprites drawn at one world unit per
+    /// texel. Zoomed in it's always 0, zoomed out it's the level whose texels are
+    /// closest to screen pixels, rounded towards the sharper level.
+    pub fn pixel_perfect_mip_level(&self) -> f32 {
+        if self.zoom >= 1.0 || self.zoom <= 0.0 {
+            return 0.0;
+        }
+
+        // Tolerate float error at exact powers of two, like a zoom of 0.25.
+        ((1.0 / self.zoom).log2() + 0.001).floor()
+    }
+
+    /// `config` forced to `pixel_perfect_mip_level`, so sprites don't blur between
+    /// mip levels while zooming out. Set it with `Texture::set_sampler_config`
+    /// when the zoom changes.
+    pub fn pixel_perfect_sampler(&self, config: SamplerConfig) -> SamplerConfig {
+        let level = self.pixel_perfect_mip_level();
+        config.with_lod_clamp(level, level)
+    }
+
+    /// A matrix which maps world coordinates to clip space
+    /// for a framebuffer of the given size.
+    pub fn view_projection_matrix(&self, width: f32, height: f32) -> [[f32; 4]; 4] {
+        let bounds = self.visible_bounds(width, height);
+
+        ortho_projection_matrix(
+            bounds.x,
+            bounds.x + bounds.w,
+            bounds.y + bounds.h,
+            bounds.y,
+            -1.0,
+            1.0,
+        )
+    }
+}
+
+// Creates a matrix that projects screen coordinates defined by width and
+// height orthographically onto the OpenGL vertex coordinates.
+pub(crate) fn screen_projection_matrix(width: f32, height: f32) -> [[f32; 4]; 4] {
+    ortho_projection_matrix(0.0, width, height, 0.0, -1.0, 1.0)
+}
+
+// Creates a matrix that projects a cube defined by the arguments
+// orthographically onto the OpenGL vertex coordinates.
+// TODO(bschwind) - Double check this works outside of OpenGL/Metal
+fn ortho_projection_matrix(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> [[f32; 4]; 4] {
+    let lr = 1.0 / (left - right);
+    let bt = 1.0 / (bottom - top);
+    let nf = 1.0 / (near - far);
+
+    [
+        [-2.0 * lr, 0.0, 0.0, 0.0],
+        [0.0, -2.0 * bt, 0.0, 0.0],
+        [0.0, 0.0, 2.0 * nf, 0.0],
+        [(left + right) * lr, (top + bottom) * bt, (far + near) * nf, 1.0],
+    ]
+}
@@ -0,0 +1,115 @@
+use crate::graphics::{GraphicsDevice, RenderTarget};
+use image::RgbaImage;
+use std::num::NonZeroU32;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use wgpu::TextureFormat;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The render target's format isn't 8 bit RGBA or BGRA, e.g. an `HDR_FORMAT` target.
+    UnsupportedFormat(TextureFormat),
+    MapFailed(wgpu::BufferAsyncError),
+    SaveFailed(image::ImageError),
+}
+
+impl From<image::ImageError> for CaptureError {
+    fn from(err: image::ImageError) -> Self {
+        CaptureError::SaveFailed(err)
+    }
+}
+
+impl GraphicsDevice {
+    /// Reads back the contents of `target` as an RGBA image, e.g. for screenshots.
+    /// The swap chain texture can't be read back, so to capture what's on screen
+    /// render the frame into a `RenderTarget` and present it with a `PostProcess`.
+    /// Only 8 bit RGBA and BGRA formats are supported, others are an error.
+    ///
+    /// This blocks on the GPU while waiting for the copy to finish, so
+    /// it's meant for occasional captures rather than every frame. On the
+    /// web it doesn't block, the returned future resolves once the browser
+    /// has mapped the buffer.
+    pub async fn capture_frame(&self, target: &RenderTarget) -> Result<RgbaImage, CaptureError> {
+        let (width, height) = target.size();
+        let swap_red_blue = match target.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => return Err(CaptureError::UnsupportedFormat(format)),
+        };
+
+        // Rows in the buffer have to be padded to a multiple of 256 bytes.
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame capture buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("capture") });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: target.texture(),
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let map = slice.map_async(wgpu::MapMode::Read);
+
+        // Mapping only completes while the device is polled.
+        self.device.poll(wgpu::Maintain::Wait);
+        map.await.map_err(CaptureError::MapFailed)?;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+
+        {
+            let data = slice.get_mapped_range();
+
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+
+        buffer.unmap();
+
+        if swap_red_blue {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(RgbaImage::from_raw(width, height, pixels)
+            .expect("Captured pixel data should match the render target size"))
+    }
+
+    /// Captures `target` with `capture_frame` and saves it as a PNG at `path`.
+    /// There's no file system on the web, so this is only available natively.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn capture_frame_to_png<P: AsRef<Path>>(
+        &self,
+        target: &RenderTarget,
+        path: P,
+    ) -> Result<(), CaptureError> {
+        let image = self.capture_frame(target).await?;
+        Ok(image.save_with_format(path, image::ImageFormat::Png)?)
+    }
+}
@@ -0,0 +1,95 @@
+use crate::graphics::{FrameEncoder, GraphicsDevice, PostProcess, RenderTarget};
+use bytemuck::{Pod, Zeroable};
+
+/// Color adjustments applied by `ColorGrade`, in this order: brightness,
+/// contrast, saturation and then gamma. The defaults change nothing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorGradeParams {
+    /// Added to every channel, 0.0 for no change.
+    pub brightness: f32,
+
+    /// Scales the distance of every channel from 0.5, 1.0 for no change.
+    pub contrast: f32,
+
+    /// 0.0 is grayscale, 1.0 is no change and higher values are more saturated.
+    pub saturation: f32,
+
+    /// Channels are raised to `1.0 / gamma`, values above 1.0 brighten the midtones.
+    pub gamma: f32,
+}
+
+impl Default for ColorGradeParams {
+    fn default() -> Self {
+        Self { brightness: 0.0, contrast: 1.0, saturation: 1.0, gamma: 1.0 }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct ColorGradeUniforms {
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    gamma: f32,
+}
+
+impl From<ColorGradeParams> for ColorGradeUniforms {
+    fn from(params: ColorGradeParams) -> Self {
+        Self {
+            brightness: params.brightness,
+            contrast: params.contrast,
+            saturation: params.saturation,
+            // Avoid dividing by zero in the shader.
+            gamma: params.gamma.max(0.001),
+        }
+    }
+}
+
+/// A `PostProcess` adjusting brightness, contrast, saturation and gamma,
+/// e.g. for a brightness setting or to compensate for a linear swap chain format.
+pub struct ColorGrade {
+    post_process: PostProcess,
+    params: ColorGradeParams,
+    params_dirty: bool,
+}
+
+impl ColorGrade {
+    pub fn new(graphics_device: &GraphicsDevice, source: &RenderTarget) -> Self {
+        let post_process = PostProcess::with_uniforms(
+            graphics_device,
+            source,
+            &include_shader!("color_grade.frag"),
+            std::mem::size_of::<ColorGradeUniforms>() as u64,
+        );
+
+        Self { post_process, params: ColorGradeParams::default(), params_dirty: true }
+    }
+
+    pub fn params(&self) -> ColorGradeParams {
+        self.params
+    }
+
+    /// Changes the adjustments, they're uploaded with the next `render`.
+    pub fn set_params(&mut self, params: ColorGradeParams) {
+        if params != self.params {
+            self.params = params;
+            self.params_dirty = true;
+        }
+    }
+
+    /// See `PostProcess::set_source`.
+    pub fn set_source(&mut self, graphics_device: &GraphicsDevice, source: &RenderTarget) {
+        self.post_process.set_source(graphics_device, source);
+    }
+
+    /// Draws the color graded source render target over the whole frame target.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder) {
+        if self.params_dirty {
+            let uniforms = ColorGradeUniforms::from(self.params);
+            self.post_process.write_uniforms(frame_encoder.queue(), bytemuck::bytes_of(&uniforms));
+            self.params_dirty = false;
+        }
+
+        self.post_process.render(frame_encoder);
+    }
+}
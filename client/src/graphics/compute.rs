@@ -0,0 +1,191 @@
+use crate::graphics::{
+    resource_tracker::{ResourceKind, Tracked},
+    FrameEncoder, GraphicsDevice,
+};
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, BufferAddress, BufferUsage,
+    ComputePipeline, Queue, ShaderModuleDescriptor,
+};
+
+/// A buffer compute shaders read and write. It can be written from the CPU
+/// and copied out of, and `extra_usage` can add e.g. `VERTEX` to draw
+/// straight from the results.
+pub struct StorageBuffer {
+    buffer: Buffer,
+    size: BufferAddress,
+    _tracked: Tracked,
+}
+
+impl StorageBuffer {
+    /// A zeroed buffer of `size` bytes.
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        label: &str,
+        size: BufferAddress,
+        extra_usage: BufferUsage,
+    ) -> Self {
+        let buffer = graphics_device.device().create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: Self::usage(extra_usage),
+            mapped_at_creation: false,
+        });
+
+        Self { buffer, size, _tracked: Tracked::new(ResourceKind::Buffer) }
+    }
+
+    /// A buffer holding `contents`.
+    pub fn with_contents(
+        graphics_device: &GraphicsDevice,
+        label: &str,
+        contents: &[u8],
+        extra_usage: BufferUsage,
+    ) -> Self {
+        let buffer =
+            graphics_device.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: Self::usage(extra_usage),
+            });
+
+        Self {
+            buffer,
+            size: contents.len() as BufferAddress,
+            _tracked: Tracked::new(ResourceKind::Buffer),
+        }
+    }
+
+    fn usage(extra_usage: BufferUsage) -> BufferUsage {
+        BufferUsage::STORAGE | BufferUsage::COPY_SRC | BufferUsage::COPY_DST | extra_usage
+    }
+
+    /// Writes `data` at `offset` bytes into the buffer, before the next submitted frame.
+    pub fn write(&self, queue: &Queue, offset: BufferAddress, data: &[u8]) {
+        queue.write_buffer(&self.buffer, offset, data);
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Size in bytes.
+    pub fn size(&self) -> BufferAddress {
+        self.size
+    }
+}
+
+/// How a compute shader accesses a storage buffer binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// A compute shader with its storage buffer bindings. Binding `i` of set 0 is
+/// the buffer declared with `bindings[i]`, e.g. for `[ReadOnly, ReadWrite]`:
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) readonly buffer Input { ... };
+/// layout(set = 0, binding = 1) buffer Output { ... };
+/// ```
+///
+/// The shader's entry point is `main`. Dispatches recorded with `dispatch`
+/// run in order with the frame's render passes, so dispatching before
+/// drawing lets the draws use the results in the same frame.
+pub struct ComputePass {
+    bind_group_layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl ComputePass {
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        label: &str,
+        shader: &ShaderModuleDescriptor,
+        bindings: &[StorageAccess],
+    ) -> Self {
+        let device = graphics_device.device();
+
+        let entries: Vec<_> = bindings
+            .iter()
+            .enumerate()
+            .map(|(binding, access)| wgpu::BindGroupLayoutEntry {
+                binding: binding as u32,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: *access == StorageAccess::ReadOnly,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect();
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let module = device.create_shader_module(shader);
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "main",
+        });
+
+        Self { bind_group_layout, pipeline }
+    }
+
+    /// Binds `buffers` in the order of the `bindings` the pass was created with.
+    pub fn create_bind_group(
+        &self,
+        graphics_device: &GraphicsDevice,
+        buffers: &[&StorageBuffer],
+    ) -> BindGroup {
+        let entries: Vec<_> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: buffer.buffer(),
+                    offset: 0,
+                    size: None,
+                },
+            })
+            .collect();
+
+        graphics_device.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &entries,
+        })
+    }
+
+    /// Runs the shader over `workgroups` (x, y, z) workgroups with `bind_group`.
+    pub fn dispatch(
+        &self,
+        frame_encoder: &mut FrameEncoder,
+        bind_group: &BindGroup,
+        workgroups: [u32; 3],
+    ) {
+        let mut cpass =
+            frame_encoder.encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, bind_group, &[]);
+
+        let [x, y, z] = workgroups;
+        cpass.dispatch(x, y, z);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::graphics::{
+    resource_tracker::{ResourceKind, Tracked},
+    GraphicsDevice,
+};
+use std::num::NonZeroU32;
+use wgpu::{Sampler, Texture, TextureView};
+
+/// The six faces of a cubemap, in the order of their array layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    fn array_layer(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A cube texture which can be rendered into one face at a time with
+/// `GraphicsDevice::begin_frame_to_cube_face`, and then sampled in a
+/// shader as a `textureCube` (e.g. for simple reflection probes).
+/// It uses `GraphicsDevice::color_format` so any drawable can render into it.
+pub struct Cubemap {
+    texture: Texture,
+    cube_view: TextureView,
+    sampler: Sampler,
+    size: u32,
+    _tracked: Tracked,
+}
+
+impl Cubemap {
+    pub fn new(graphics_device: &GraphicsDevice, size: u32) -> Self {
+        let device = graphics_device.device();
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cubemap texture"),
+            size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 6 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: graphics_device.color_format(),
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+
+        let cube_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cubemap view"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            array_layer_count: NonZeroU32::new(6),
+            ..Default::default()
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self { texture, cube_view, sampler, size, _tracked: Tracked::new(ResourceKind::Texture) }
+    }
+
+    /// The width and height of each face, in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// A 2D view of a single face, usable as a render attachment.
+    pub fn face_view(&self, face: CubeFace) -> TextureView {
+        self.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Cubemap face view"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: face.array_layer(),
+            array_layer_count: NonZeroU32::new(1),
+            ..Default::default()
+        })
+    }
+
+    /// A view of the whole cube, for sampling in shaders.
+    pub fn view(&self) -> &TextureView {
+        &self.cube_view
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// The bind group layout entry for binding `view()`, declared in
+    /// GLSL as `layout(set = 0, binding = N) uniform textureCube name;`.
+    pub fn texture_layout_entry(
+        binding: u32,
+        visibility: wgpu::ShaderStage,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{tests::headless_device, Color};
+    use futures::executor::block_on;
+
+    /// Reads back the RGBA pixels of one face of `cube`.
+    fn read_face(graphics_device: &GraphicsDevice, cube: &Cubemap, face: CubeFace) -> Vec<[u8; 4]> {
+        let size = cube.size();
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (size * 4 + align - 1) / align * align;
+
+        let device = graphics_device.device();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cubemap face readback"),
+            size: (padded_bytes_per_row * size) as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Cubemap face readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &cube.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: face.array_layer() },
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+        );
+        graphics_device.queue().submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let map = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        block_on(map).unwrap();
+
+        let swap_red_blue = matches!(
+            graphics_device.color_format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let data = slice.get_mapped_range();
+        data.chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| row[..(size * 4) as usize].chunks(4))
+            .map(|pixel| {
+                let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                if swap_red_blue {
+                    [b, g, r, a]
+                } else {
+                    [r, g, b, a]
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_face_is_rendered_separately() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+        let cube = Cubemap::new(&graphics_device, 4);
+
+        let colors = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 0, 255],
+            [0, 255, 255, 255],
+            [255, 0, 255, 255],
+        ];
+
+        for (face, color) in CubeFace::ALL.iter().zip(&colors) {
+            let [r, g, b, a] = *color;
+            let mut frame_encoder = graphics_device.begin_frame_to_cube_face(&cube, *face);
+            frame_encoder.clear(Color::rgba8(r, g, b, a));
+            frame_encoder.finish();
+        }
+
+        for (face, color) in CubeFace::ALL.iter().zip(&colors) {
+            let pixels = read_face(&graphics_device, &cube, *face);
+            assert_eq!(pixels.len(), 16);
+            assert!(pixels.iter().all(|pixel| pixel == color), "{:?} is {:?}", face, pixels[0]);
+        }
+    }
+}
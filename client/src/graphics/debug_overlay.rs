@@ -0,0 +1,58 @@
+use crate::graphics::{
+    text::{Font, StyledText, TextAlignment, TextStyle, TextSystem},
+    Color, FrameEncoder, GraphicsDevice,
+};
+use winit::dpi::PhysicalSize;
+
+const DEBUG_FONT: Font = Font::SpaceMono400(16);
+
+struct DebugText {
+    x: i32,
+    y: i32,
+    text: String,
+    color: Color,
+}
+
+/// Immediate mode text for FPS counters and other debug output. Queue
+/// strings with `debug_text` from anywhere during the frame and draw them
+/// all over everything else with `render`, right before `FrameEncoder::finish`.
+/// Positions are in screen pixels from the top left of the window, ignoring
+/// any camera, and the text is always the built in monospace font.
+pub struct DebugOverlay {
+    text_system: TextSystem,
+    queued: Vec<DebugText>,
+}
+
+impl DebugOverlay {
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        Self { text_system: TextSystem::new(graphics_device), queued: Vec::new() }
+    }
+
+    /// Queues white `text` with its top left corner at `(x, y)`.
+    pub fn debug_text(&mut self, x: i32, y: i32, text: &str) {
+        self.debug_text_colored(x, y, text, Color::WHITE);
+    }
+
+    pub fn debug_text_colored(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        self.queued.push(DebugText { x, y, text: text.to_string(), color });
+    }
+
+    /// Draws all queued text with a single draw call, and clears the queue.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder, window_size: PhysicalSize<u32>) {
+        for debug_text in self.queued.drain(..) {
+            self.text_system.queue_text(
+                TextAlignment::left_top(debug_text.x, debug_text.y),
+                &[StyledText {
+                    text: &debug_text.text,
+                    font: DEBUG_FONT,
+                    color: debug_text.color,
+                    style: TextStyle::default(),
+                }],
+                frame_encoder,
+                window_size,
+            );
+        }
+
+        self.text_system.flush(frame_encoder);
+    }
+}
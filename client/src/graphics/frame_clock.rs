@@ -0,0 +1,176 @@
+// std's Instant panics on wasm32-unknown-unknown, this one
+// uses performance.now() there and is std's Instant elsewhere.
+use instant::Instant;
+use std::time::Duration;
+
+/// Frame deltas are capped to this, so animations don't jump after
+/// the process was paused by a breakpoint or a window drag.
+const MAX_DELTA: Duration = Duration::from_millis(100);
+
+/// How much of the previous FPS estimate is kept each frame.
+const FPS_SMOOTHING: f32 = 0.95;
+
+/// OS sleeps can overshoot by a millisecond or more, so
+/// the last stretch before a deadline is spent spinning.
+const SPIN_DURATION: Duration = Duration::from_millis(2);
+
+/// Measures the time between frames. `GraphicsDevice::begin_frame` ticks
+/// it once per frame, giving animation and physics code a consistent delta.
+#[derive(Debug)]
+pub struct FrameClock {
+    last_frame_instant: Option<Instant>,
+    delta: Duration,
+    smoothed_fps: f32,
+    frame_count: u64,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self {
+            last_frame_instant: None,
+            delta: Duration::from_secs(0),
+            smoothed_fps: 0.0,
+            frame_count: 0,
+        }
+    }
+
+    /// Marks the start of a new frame.
+    pub fn tick(&mut self) {
+        self.tick_at(Instant::now());
+    }
+
+    fn tick_at(&mut self, now: Instant) {
+        if let Some(last_frame_instant) = self.last_frame_instant {
+            self.delta = now.duration_since(last_frame_instant).min(MAX_DELTA);
+
+            let delta_seconds = self.delta.as_secs_f32();
+            if delta_seconds > 0.0 {
+                let fps = 1.0 / delta_seconds;
+
+                self.smoothed_fps = if self.smoothed_fps == 0.0 {
+                    fps
+                } else {
+                    self.smoothed_fps * FPS_SMOOTHING + fps * (1.0 - FPS_SMOOTHING)
+                };
+            }
+        }
+
+        self.last_frame_instant = Some(now);
+        self.frame_count += 1;
+    }
+
+    /// Seconds between the start of the previous frame and this one.
+    /// This is 0.0 on the first frame.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Frames per second, smoothed over roughly the last second of frames.
+    pub fn fps(&self) -> f32 {
+        self.smoothed_fps
+    }
+
+    /// The number of frames started so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// When the current frame started.
+    pub fn last_frame_instant(&self) -> Option<Instant> {
+        self.last_frame_instant
+    }
+}
+
+/// Blocks until `deadline`, sleeping for most of the wait and
+/// spinning for the rest to avoid oversleeping.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if now >= deadline {
+        return;
+    }
+
+    let remaining = deadline - now;
+    if remaining > SPIN_DURATION {
+        std::thread::sleep(remaining - SPIN_DURATION);
+    }
+
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// The browser's main thread can't block, and requestAnimationFrame
+/// already paces frames on the web, so this doesn't wait there.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn sleep_until(_deadline: Instant) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clock ticked once per `frame_time`, `frames` times.
+    fn ticked(frame_time: Duration, frames: u32) -> FrameClock {
+        let mut frame_clock = FrameClock::new();
+        let start = Instant::now();
+
+        for frame in 0..frames {
+            frame_clock.tick_at(start + frame_time * frame);
+        }
+
+        frame_clock
+    }
+
+    #[test]
+    fn first_frame_has_no_delta() {
+        let frame_clock = ticked(Duration::from_millis(16), 1);
+
+        assert_eq!(frame_clock.delta(), Duration::from_secs(0));
+        assert_eq!(frame_clock.fps(), 0.0);
+        assert_eq!(frame_clock.frame_count(), 1);
+    }
+
+    #[test]
+    fn delta_is_time_since_last_tick() {
+        let frame_clock = ticked(Duration::from_millis(20), 3);
+
+        assert_eq!(frame_clock.delta(), Duration::from_millis(20));
+        assert!((frame_clock.delta_seconds() - 0.02).abs() < 1e-6);
+        assert!((frame_clock.fps() - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn long_pauses_are_capped() {
+        let frame_clock = ticked(Duration::from_secs(5), 2);
+
+        assert_eq!(frame_clock.delta(), MAX_DELTA);
+    }
+
+    #[test]
+    fn fps_is_smoothed_towards_the_frame_rate() {
+        let mut frame_clock = ticked(Duration::from_millis(10), 2);
+        let start = frame_clock.last_frame_instant().unwrap();
+        assert!((frame_clock.fps() - 100.0).abs() < 1e-3);
+
+        // One slow frame only moves the estimate a little.
+        frame_clock.tick_at(start + Duration::from_millis(50));
+        let fps = frame_clock.fps();
+        assert!(fps < 100.0 && fps > 90.0, "{}", fps);
+
+        // Keeping the slower frame rate makes the estimate converge to it.
+        for frame in 1..200 {
+            frame_clock.tick_at(start + Duration::from_millis(50 + 50 * frame));
+        }
+        assert!((frame_clock.fps() - 20.0).abs() < 0.1, "{}", frame_clock.fps());
+    }
+}
@@ -0,0 +1,84 @@
+use futures::executor::block_on;
+use std::{collections::VecDeque, future::Future, pin::Pin};
+use wgpu::{Buffer, BufferAsyncError, CommandEncoder, Device};
+
+/// Copies are done in multiples of 4 bytes.
+const FENCE_SIZE: u64 = 4;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), BufferAsyncError>> + Send>>;
+
+/// Limits how many frames the CPU can submit before the GPU finishes them.
+/// wgpu has no fences, so each frame ends by copying into a small buffer, which
+/// is then mapped. The mapping completes once the GPU has finished everything
+/// submitted before it, i.e. the frame.
+pub(crate) struct FrameFences {
+    max_frames_in_flight: usize,
+    source: Buffer,
+    free: Vec<Buffer>,
+    in_flight: VecDeque<(Buffer, MapFuture)>,
+
+    /// The fence written by the frame currently being recorded.
+    recording: Option<Buffer>,
+}
+
+impl FrameFences {
+    pub fn new(device: &Device, max_frames_in_flight: u32) -> Self {
+        let source = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame fence source"),
+            size: FENCE_SIZE,
+            usage: wgpu::BufferUsage::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            max_frames_in_flight: max_frames_in_flight.max(1) as usize,
+            source,
+            free: Vec::new(),
+            in_flight: VecDeque::new(),
+            recording: None,
+        }
+    }
+
+    /// Blocks until fewer than `max_frames_in_flight` frames are still running on the GPU.
+    /// wgpu can only wait for everything submitted, so once the limit is reached
+    /// this waits for all frames in flight, not just the oldest one.
+    pub fn wait(&mut self, device: &Device) {
+        while self.in_flight.len() >= self.max_frames_in_flight {
+            let (fence, pending_map) = self.in_flight.pop_front().unwrap();
+
+            // The map completes during the wait, so this doesn't block any further.
+            device.poll(wgpu::Maintain::Wait);
+            let result = block_on(pending_map);
+
+            if let Err(e) = result {
+                println!("Failed to wait for a frame fence: {:?}", e);
+            }
+
+            fence.unmap();
+            self.free.push(fence);
+        }
+    }
+
+    /// Records the copy into this frame's fence, at the end of the frame.
+    pub fn end(&mut self, device: &Device, encoder: &mut CommandEncoder) {
+        let fence = self.free.pop().unwrap_or_else(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Frame fence"),
+                size: FENCE_SIZE,
+                usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+                mapped_at_creation: false,
+            })
+        });
+
+        encoder.copy_buffer_to_buffer(&self.source, 0, &fence, 0, FENCE_SIZE);
+        self.recording = Some(fence);
+    }
+
+    /// Starts mapping the fence once the frame has been submitted.
+    pub fn after_submit(&mut self) {
+        if let Some(fence) = self.recording.take() {
+            let pending_map = Box::pin(fence.slice(..).map_async(wgpu::MapMode::Read));
+            self.in_flight.push_back((fence, pending_map));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::graphics::{debug_marker, Color, FrameEncoder, GraphicsDevice, PipelineBuilder, Rect};
+use wgpu::{BindGroup, BindGroupLayout, ColorTargetState, RenderPipeline, ShaderModuleDescriptor};
+
+/// Runs a fragment shader over the whole frame target, for screen space effects.
+/// It draws a single triangle covering the screen, generated from the vertex
+/// index in fullscreen.vert, so there are no vertex or index buffers. The
+/// fragment shader gets the UV coordinates, with (0, 0) at the top left:
+///
+/// ```glsl
+/// layout(location = 0) in vec2 vert_uv;
+/// ```
+///
+/// and whatever is in the bind groups passed to `render`.
+pub struct FullscreenPass {
+    pipeline: RenderPipeline,
+
+    /// Names the render pass in GPU captures.
+    label: String,
+}
+
+impl FullscreenPass {
+    /// `bind_group_layouts` are the layouts of the bind groups passed to `render`,
+    /// for sets 0, 1, etc. `target` is the format and blending of the frame target.
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        label: &str,
+        bind_group_layouts: &[&BindGroupLayout],
+        fragment_shader: &ShaderModuleDescriptor,
+        target: ColorTargetState,
+    ) -> Self {
+        let device = graphics_device.device();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let vs_module = device.create_shader_module(&include_shader!("fullscreen.vert"));
+        let fs_module = device.create_shader_module(fragment_shader);
+
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label(label)
+            .color_target(target)
+            .build(device);
+
+        Self { pipeline, label: label.to_owned() }
+    }
+
+    /// Draws over the whole frame target of `frame_encoder`, first clearing it
+    /// to `clear` if it's set. `bind_groups` are bound to sets 0, 1, etc.
+    pub fn render(
+        &self,
+        frame_encoder: &mut FrameEncoder,
+        bind_groups: &[&BindGroup],
+        clear: Option<Color>,
+    ) {
+        self.draw(frame_encoder, bind_groups, clear, None);
+    }
+
+    /// Draws over `viewport` only, in physical pixels, keeping the rest of the frame.
+    pub fn render_in_viewport(
+        &self,
+        frame_encoder: &mut FrameEncoder,
+        bind_groups: &[&BindGroup],
+        viewport: Rect,
+    ) {
+        self.draw(frame_encoder, bind_groups, None, Some(viewport));
+    }
+
+    fn draw(
+        &self,
+        frame_encoder: &mut FrameEncoder,
+        bind_groups: &[&BindGroup],
+        clear: Option<Color>,
+        viewport: Option<Rect>,
+    ) {
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        frame_encoder.stats.record_draw(1);
+
+        let load = match clear {
+            Some(color) => wgpu::LoadOp::Clear(color.into()),
+            None => wgpu::LoadOp::Load,
+        };
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.label.as_str()),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+
+        if let Some(Rect { x, y, w, h }) = viewport {
+            rpass.set_viewport(x, y, w, h, 0.0, 1.0);
+        }
+
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            rpass.set_bind_group(index as u32, bind_group, &[]);
+        }
+
+        debug_marker(&mut rpass, &self.label);
+        rpass.draw(0..3, 0..1);
+    }
+}
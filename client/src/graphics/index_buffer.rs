@@ -0,0 +1,224 @@
+use crate::graphics::{
+    resource_tracker::{ResourceKind, Tracked},
+    GraphicsDevice,
+};
+use wgpu::{util::DeviceExt, Buffer, IndexFormat, RenderPass};
+
+/// Index data in the smallest format which can address all vertices of a mesh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Uses `u16` indices when all `vertex_count` vertices can be addressed
+    /// with them, `u32` otherwise.
+    pub fn for_vertex_count(vertex_count: usize, indices: &[u32]) -> Self {
+        if vertex_count <= u16::MAX as usize + 1 {
+            Indices::U16(indices.iter().map(|&index| index as u16).collect())
+        } else {
+            Indices::U32(indices.to_vec())
+        }
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Indices::U16(_) => IndexFormat::Uint16,
+            Indices::U32(_) => IndexFormat::Uint32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
+
+/// An index buffer which remembers its format, so it's
+/// always bound with the format its data was written in.
+pub struct IndexBuffer {
+    buffer: Buffer,
+    format: IndexFormat,
+    count: u32,
+    _tracked: Tracked,
+}
+
+impl IndexBuffer {
+    pub fn new(graphics_device: &GraphicsDevice, label: &str, indices: &Indices) -> Self {
+        let buffer =
+            graphics_device.device().create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: indices.as_bytes(),
+                usage: wgpu::BufferUsage::INDEX,
+            });
+
+        Self {
+            buffer,
+            format: indices.format(),
+            count: indices.len() as u32,
+            _tracked: Tracked::new(ResourceKind::Buffer),
+        }
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        self.format
+    }
+
+    /// The number of indices, for `draw_indexed`.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_index_buffer(self.buffer.slice(..), self.format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{color_target, tests::headless_device, PipelineBuilder, RenderTarget};
+    use futures::executor::block_on;
+    use std::borrow::Cow;
+
+    /// Draws the triangles at their vertex positions in red.
+    const RED_TRIANGLES: &str = "
+        [[stage(vertex)]]
+        fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+            return vec4<f32>(pos, 0.0, 1.0);
+        }
+
+        [[stage(fragment)]]
+        fn fs_main() -> [[location(0)]] vec4<f32> {
+            return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+        }
+    ";
+
+    #[test]
+    fn u16_indices_up_to_65536_vertices() {
+        let indices = Indices::for_vertex_count(u16::MAX as usize + 1, &[0, 1, 65535]);
+
+        assert_eq!(indices, Indices::U16(vec![0, 1, 65535]));
+        assert_eq!(indices.format(), IndexFormat::Uint16);
+        assert_eq!(indices.as_bytes().len(), 3 * 2);
+    }
+
+    #[test]
+    fn u32_indices_past_65536_vertices() {
+        let indices = Indices::for_vertex_count(u16::MAX as usize + 2, &[0, 1, 65536]);
+
+        assert_eq!(indices, Indices::U32(vec![0, 1, 65536]));
+        assert_eq!(indices.format(), IndexFormat::Uint32);
+        assert_eq!(indices.as_bytes().len(), 3 * 4);
+    }
+
+    #[test]
+    fn empty_indices() {
+        let indices = Indices::for_vertex_count(0, &[]);
+
+        assert!(indices.is_empty());
+        assert_eq!(indices.len(), 0);
+    }
+
+    #[test]
+    fn u32_indices_draw_vertices_past_65535() {
+        let graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+        let device = graphics_device.device();
+
+        // Only the last three vertices are on screen, as a triangle covering
+        // the whole target. Indices truncated to u16 would draw nothing.
+        let first = u16::MAX as usize + 1;
+        let mut positions = vec![[-2.0f32, -2.0]; first];
+        positions.extend_from_slice(&[[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]]);
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Large mesh vertices"),
+            contents: bytemuck::cast_slice(&positions),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let first = first as u32;
+        let indices = Indices::for_vertex_count(positions.len(), &[first, first + 1, first + 2]);
+        assert_eq!(indices.format(), IndexFormat::Uint32);
+        let index_buf = IndexBuffer::new(&graphics_device, "Large mesh indices", &indices);
+
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Red triangles"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(RED_TRIANGLES)),
+            flags: wgpu::ShaderFlags::all(),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Red triangles"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let vertex_buffers = [wgpu::VertexBufferLayout {
+            array_stride: 2 * 4,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+        }];
+        let pipeline = PipelineBuilder::new(&layout, &module, &module)
+            .entry_points("vs_main", "fs_main")
+            .vertex_buffers(&vertex_buffers)
+            .color_target(color_target(format, None))
+            .build(device);
+
+        let target = RenderTarget::with_format(&graphics_device, format);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Large mesh test"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Large mesh test"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&pipeline);
+            rpass.set_vertex_buffer(0, vertex_buf.slice(..));
+            index_buf.bind(&mut rpass);
+            rpass.draw_indexed(0..index_buf.count(), 0, 0..1);
+        }
+        graphics_device.queue().submit(Some(encoder.finish()));
+
+        let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0, [255, 0, 0, 255], "Pixel at {}, {}", x, y);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::graphics::TextureError;
+use std::convert::TryInto;
+use wgpu::TextureFormat;
+
+const IDENTIFIER: [u8; 12] =
+    [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+/// The identifier, the 9 u32 header fields and the 32 byte index which
+/// come before the level index.
+const LEVEL_INDEX_OFFSET: usize = 80;
+
+/// The contents of a KTX2 file, with the image data of each mip level
+/// borrowed from the file bytes. Level 0 is the largest.
+pub(crate) struct Ktx2<'a> {
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<&'a [u8]>,
+}
+
+impl<'a> Ktx2<'a> {
+    /// Reads a single 2D image with its mip levels. Arrays, cubemaps, 3D textures
+    /// and supercompressed (Basis, zstd) files aren't supported.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, TextureError> {
+        if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..12] != IDENTIFIER {
+            return Err(TextureError::InvalidKtx2("not a KTX2 file"));
+        }
+
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let vk_format = u32_at(12);
+        let width = u32_at(20);
+        let height = u32_at(24).max(1);
+        let depth = u32_at(28);
+        let layer_count = u32_at(32);
+        let face_count = u32_at(36);
+        let level_count = u32_at(40).max(1) as usize;
+        let supercompression = u32_at(44);
+
+        if depth > 1 || layer_count > 1 || face_count != 1 {
+            return Err(TextureError::InvalidKtx2("only single 2D images are supported"));
+        }
+
+        if supercompression != 0 {
+            return Err(TextureError::InvalidKtx2("supercompressed files aren't supported"));
+        }
+
+        if width == 0 {
+            return Err(TextureError::InvalidKtx2("the image has no width"));
+        }
+
+        // A full chain goes down to 1x1, one level per bit of the larger side.
+        let max_level_count = (32 - width.max(height).leading_zeros()) as usize;
+        if level_count > max_level_count {
+            return Err(TextureError::InvalidKtx2("more mip levels than the image size allows"));
+        }
+
+        let format = texture_format(vk_format).ok_or(TextureError::UnsupportedFormat(vk_format))?;
+
+        if bytes.len() < LEVEL_INDEX_OFFSET + level_count * 24 {
+            return Err(TextureError::InvalidKtx2("the level index is truncated"));
+        }
+
+        let levels = (0..level_count)
+            .map(|level| {
+                let entry = LEVEL_INDEX_OFFSET + level * 24;
+                let offset = u64_at(entry) as usize;
+                let length = u64_at(entry + 8) as usize;
+
+                bytes
+                    .get(offset..offset.saturating_add(length))
+                    .ok_or(TextureError::InvalidKtx2("level data is out of bounds"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { format, width, height, levels })
+    }
+}
+
+/// The wgpu format for a `VkFormat` value. Textures are sampled as linear
+/// everywhere else, so sRGB formats load as their UNORM equivalent.
+fn texture_format(vk_format: u32) -> Option<TextureFormat> {
+    Some(match vk_format {
+        37 | 43 => TextureFormat::Rgba8Unorm,
+        133 | 134 => TextureFormat::Bc1RgbaUnorm,
+        137 | 138 => TextureFormat::Bc3RgbaUnorm,
+        145 | 146 => TextureFormat::Bc7RgbaUnorm,
+        147 | 148 => TextureFormat::Etc2RgbUnorm,
+        151 | 152 => TextureFormat::Etc2RgbA8Unorm,
+        157 | 158 => TextureFormat::Astc4x4RgbaUnorm,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A KTX2 file of an RGBA8 image with `level_count` levels of `level_size`
+    /// bytes each, stored right after the level index.
+    fn ktx2_file(width: u32, height: u32, level_count: u32, level_size: usize) -> Vec<u8> {
+        let mut bytes = IDENTIFIER.to_vec();
+        for field in &[37, 4, width, height, 0, 0, 1, level_count, 0] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.resize(LEVEL_INDEX_OFFSET, 0);
+
+        let level_count = level_count.max(1) as usize;
+        let data_offset = LEVEL_INDEX_OFFSET + level_count * 24;
+        for level in 0..level_count {
+            let offset = (data_offset + level * level_size) as u64;
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&(level_size as u64).to_le_bytes());
+            bytes.extend_from_slice(&(level_size as u64).to_le_bytes());
+        }
+        bytes.resize(data_offset + level_count * level_size, 0xFF);
+
+        bytes
+    }
+
+    fn parse_error(bytes: &[u8]) -> &'static str {
+        match Ktx2::parse(bytes) {
+            Err(TextureError::InvalidKtx2(reason)) => reason,
+            Err(error) => panic!("unexpected error {:?}", error),
+            Ok(_) => panic!("parsed an invalid file"),
+        }
+    }
+
+    #[test]
+    fn parses_mip_levels() {
+        let bytes = ktx2_file(4, 2, 3, 32);
+        let ktx2 = Ktx2::parse(&bytes).unwrap();
+
+        assert_eq!(ktx2.format, TextureFormat::Rgba8Unorm);
+        assert_eq!((ktx2.width, ktx2.height), (4, 2));
+        assert_eq!(ktx2.levels.len(), 3);
+        assert!(ktx2.levels.iter().all(|level| level.len() == 32));
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let bytes = ktx2_file(4, 4, 3, 64);
+
+        assert_eq!(parse_error(&bytes[..40]), "not a KTX2 file");
+        assert_eq!(parse_error(&bytes[..LEVEL_INDEX_OFFSET + 30]), "the level index is truncated");
+        assert_eq!(parse_error(&bytes[..bytes.len() - 1]), "level data is out of bounds");
+    }
+
+    #[test]
+    fn zero_width_is_an_error() {
+        assert_eq!(parse_error(&ktx2_file(0, 4, 1, 16)), "the image has no width");
+    }
+
+    #[test]
+    fn too_many_levels_are_errors() {
+        let expected = "more mip levels than the image size allows";
+
+        // 4x4 has 4x4, 2x2 and 1x1 levels.
+        assert_eq!(parse_error(&ktx2_file(4, 4, 4, 16)), expected);
+        assert_eq!(parse_error(&ktx2_file(1, 1, 40, 16)), expected);
+        assert!(Ktx2::parse(&ktx2_file(4, 1, 3, 16)).is_ok());
+    }
+
+    #[test]
+    fn oversized_level_lengths_are_errors() {
+        let mut bytes = ktx2_file(2, 2, 1, 16);
+        let length_at = LEVEL_INDEX_OFFSET + 8;
+        bytes[length_at..length_at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        assert_eq!(parse_error(&bytes), "level data is out of bounds");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::graphics::{color_target, Color, FrameEncoder, FullscreenPass, GraphicsDevice, Rect};
+use wgpu::{BindGroup, Buffer};
+
+/// A fixed design resolution for the main window. Frames are drawn into the
+/// largest centered viewport with the design aspect ratio, and the bars left
+/// over at the sides or top and bottom are filled with `bar_color`, so nothing
+/// stretches whatever the window size. Set it with `GraphicsDevice::set_letterbox`.
+///
+/// While it's set, drawables should be given `FrameEncoder::design_size`
+/// as their window size, and clip rects are in design pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub design_size: [u32; 2],
+    pub bar_color: Color,
+}
+
+impl Letterbox {
+    /// Black bars around a `design_size` area.
+    pub fn new(design_size: [u32; 2]) -> Self {
+        Self { design_size, bar_color: Color::BLACK }
+    }
+
+    pub fn with_bar_color(self, bar_color: Color) -> Self {
+        Self { bar_color, ..self }
+    }
+
+    /// How many physical pixels one design pixel covers in a `target_size` frame.
+    pub fn scale(&self, target_size: [u32; 2]) -> f32 {
+        let [design_width, design_height] = self.design_size;
+        let scale_x = target_size[0] as f32 / design_width.max(1) as f32;
+        let scale_y = target_size[1] as f32 / design_height.max(1) as f32;
+
+        scale_x.min(scale_y)
+    }
+
+    /// The centered viewport in physical pixels of a `target_size` frame.
+    /// It's rounded to whole pixels, so the edges stay sharp.
+    pub fn viewport(&self, target_size: [u32; 2]) -> Rect {
+        let scale = self.scale(target_size);
+        let width = (self.design_size[0] as f32 * scale).round();
+        let height = (self.design_size[1] as f32 * scale).round();
+        let x = ((target_size[0] as f32 - width) / 2.0).floor();
+        let y = ((target_size[1] as f32 - height) / 2.0).floor();
+
+        Rect::new(x, y, width, height)
+    }
+
+    /// Maps a position in physical window pixels (e.g. `InputState::mouse_position`)
+    /// to design pixels, `None` if it's over the bars.
+    pub fn window_to_design(&self, target_size: [u32; 2], position: [f32; 2]) -> Option<[f32; 2]> {
+        let viewport = self.viewport(target_size);
+        if !viewport.contains(position) {
+            return None;
+        }
+
+        let scale = self.scale(target_size);
+        Some([(position[0] - viewport.x) / scale, (position[1] - viewport.y) / scale])
+    }
+}
+
+/// Fills the viewport with the clear color, as clearing a render pass
+/// always clears the whole frame.
+pub(crate) struct LetterboxFill {
+    pass: FullscreenPass,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl LetterboxFill {
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        let device = graphics_device.device();
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Letterbox Uniform Buffer"),
+            size: 16,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("letterbox"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(16),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(16),
+                },
+            }],
+            label: Some("letterbox"),
+        });
+
+        let format = graphics_device.swap_chain_descriptor().format;
+        let pass = FullscreenPass::new(
+            graphics_device,
+            "letterbox",
+            &[&bind_group_layout],
+            &include_shader!("solid_color.frag"),
+            color_target(format, Some(wgpu::BlendState::REPLACE)),
+        );
+
+        Self { pass, uniform_buffer, bind_group }
+    }
+
+    pub fn render(&self, frame_encoder: &mut FrameEncoder, viewport: Rect, color: Color) {
+        let color = color.to_f32_array();
+        frame_encoder.queue().write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&color));
+
+        self.pass.render_in_viewport(frame_encoder, &[&self.bind_group], viewport);
+    }
+}
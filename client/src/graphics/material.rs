@@ -0,0 +1,332 @@
+use crate::graphics::{
+    color_target, shader::capture_validation_errors, GraphicsDevice, PipelineBuilder, ShaderError,
+    TexturedQuadVertex,
+};
+use std::borrow::Cow;
+use wgpu::{BindGroup, BindGroupLayout, BlendState, Buffer, RenderPipeline, Sampler, TextureView};
+
+/// The kind of resource at one binding of a `Material`'s bind group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialBinding {
+    /// A uniform buffer, `var<uniform>` in WGSL.
+    Uniform,
+
+    /// A filterable 2D texture, `texture_2d<f32>`.
+    Texture,
+
+    /// A filtering sampler, `sampler`.
+    Sampler,
+}
+
+/// What a binding of a `Material` is set to, see `Material::set_resources`.
+pub enum MaterialResource<'a> {
+    Buffer(&'a Buffer),
+    Texture(&'a TextureView),
+    Sampler(&'a Sampler),
+}
+
+impl MaterialResource<'_> {
+    fn binding(&self) -> MaterialBinding {
+        match self {
+            MaterialResource::Buffer(_) => MaterialBinding::Uniform,
+            MaterialResource::Texture(_) => MaterialBinding::Texture,
+            MaterialResource::Sampler(_) => MaterialBinding::Sampler,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MaterialError {
+    /// `set_resources` got a different number of resources than the material has bindings.
+    ResourceCount { expected: usize, actual: usize },
+
+    /// The resource for `binding` is of a different kind than the binding.
+    WrongResource { binding: u32, expected: MaterialBinding },
+}
+
+/// A user supplied WGSL shader with its own bind group, for drawing a
+/// `TexturedQuad` with e.g. extra uniforms or a second texture, see
+/// `TexturedQuad::render_material`. The shader's entry points are `vs_main`
+/// and `fs_main`, and it gets the quad's vertices as:
+///
+/// ```wgsl
+/// [[stage(vertex)]]
+/// fn vs_main([[location(0)]] pos: vec2<f32>, [[location(1)]] uv: vec2<f32>) -> VertexOutput
+/// ```
+///
+/// with `pos` from -1.0 to 1.0 and `uv` from 0.0 to 1.0. Everything else
+/// comes from bind group 0, where `bindings[i]` is at `[[binding(i)]]`.
+pub struct Material {
+    label: String,
+    bindings: Vec<MaterialBinding>,
+    bind_group_layout: BindGroupLayout,
+    bind_group: Option<BindGroup>,
+    pipeline: RenderPipeline,
+}
+
+impl Material {
+    /// Compiles `wgsl_source` and builds the pipeline, drawing into targets of
+    /// `GraphicsDevice::color_format` with `blend`. Shader and pipeline errors
+    /// are returned, so materials can be loaded at runtime.
+    pub async fn new(
+        graphics_device: &GraphicsDevice,
+        label: &str,
+        wgsl_source: &str,
+        bindings: &[MaterialBinding],
+        blend: Option<BlendState>,
+    ) -> Result<Self, ShaderError> {
+        let device = graphics_device.device();
+
+        let entries: Vec<_> = bindings
+            .iter()
+            .enumerate()
+            .map(|(index, binding)| wgpu::BindGroupLayoutEntry {
+                binding: index as u32,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: match binding {
+                    MaterialBinding::Uniform => wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    MaterialBinding::Texture => wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    MaterialBinding::Sampler => {
+                        wgpu::BindingType::Sampler { filtering: true, comparison: false }
+                    },
+                },
+                count: None,
+            })
+            .collect();
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let module = capture_validation_errors(device, || {
+            device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(wgsl_source)),
+                flags: wgpu::ShaderFlags::all(),
+            })
+        })
+        .await?;
+
+        // The same vertex layout as `TexturedQuad`'s own pipeline.
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TexturedQuadVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
+        }];
+
+        let format = graphics_device.color_format();
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &module, &module)
+            .label(label)
+            .entry_points("vs_main", "fs_main")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, blend))
+            .topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .try_build(device)
+            .await?;
+
+        Ok(Self {
+            label: label.to_owned(),
+            bindings: bindings.to_vec(),
+            bind_group_layout,
+            bind_group: None,
+            pipeline,
+        })
+    }
+
+    /// Binds `resources[i]` to binding `i`. There has to be one resource per
+    /// binding, of the kind given to `new`. Call this again after a bound
+    /// texture or render target was recreated.
+    pub fn set_resources(
+        &mut self,
+        graphics_device: &GraphicsDevice,
+        resources: &[MaterialResource],
+    ) -> Result<(), MaterialError> {
+        if resources.len() != self.bindings.len() {
+            return Err(MaterialError::ResourceCount {
+                expected: self.bindings.len(),
+                actual: resources.len(),
+            });
+        }
+
+        let mut entries = Vec::with_capacity(resources.len());
+        for (index, (resource, expected)) in resources.iter().zip(&self.bindings).enumerate() {
+            let binding = index as u32;
+            if resource.binding() != *expected {
+                return Err(MaterialError::WrongResource { binding, expected: *expected });
+            }
+
+            let resource = match *resource {
+                MaterialResource::Buffer(buffer) => {
+                    wgpu::BindingResource::Buffer { buffer, offset: 0, size: None }
+                },
+                MaterialResource::Texture(view) => wgpu::BindingResource::TextureView(view),
+                MaterialResource::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler),
+            };
+            entries.push(wgpu::BindGroupEntry { binding, resource });
+        }
+
+        self.bind_group =
+            Some(graphics_device.device().create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &entries,
+                label: Some(self.label.as_str()),
+            }));
+
+        Ok(())
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn bindings(&self) -> &[MaterialBinding] {
+        &self.bindings
+    }
+
+    /// `None` until `set_resources` succeeded.
+    pub(crate) fn bind_group(&self) -> Option<&BindGroup> {
+        self.bind_group.as_ref()
+    }
+
+    pub(crate) fn pipeline(&self) -> &RenderPipeline {
+        &self.pipeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{tests::headless_device, Color, RenderTarget, TexturedQuad};
+    use futures::executor::block_on;
+
+    /// Samples `left` on the left half of the quad and `right` on the right half.
+    const TWO_TEXTURES: &str = "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0)]] uv: vec2<f32>;
+        };
+
+        [[stage(vertex)]]
+        fn vs_main([[location(0)]] pos: vec2<f32>, [[location(1)]] uv: vec2<f32>) -> VertexOutput {
+            var out: VertexOutput;
+            out.position = vec4<f32>(pos, 0.0, 1.0);
+            out.uv = uv;
+            return out;
+        }
+
+        [[group(0), binding(0)]] var left: texture_2d<f32>;
+        [[group(0), binding(1)]] var right: texture_2d<f32>;
+        [[group(0), binding(2)]] var texture_sampler: sampler;
+
+        [[stage(fragment)]]
+        fn fs_main(input: VertexOutput) -> [[location(0)]] vec4<f32> {
+            let left_color = textureSample(left, texture_sampler, input.uv);
+            let right_color = textureSample(right, texture_sampler, input.uv);
+            if (input.uv.x < 0.5) {
+                return left_color;
+            }
+            return right_color;
+        }
+    ";
+
+    const BINDINGS: [MaterialBinding; 3] =
+        [MaterialBinding::Texture, MaterialBinding::Texture, MaterialBinding::Sampler];
+
+    fn cleared_target(graphics_device: &mut GraphicsDevice, color: Color) -> RenderTarget {
+        let target = RenderTarget::new(graphics_device);
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(color);
+        frame_encoder.finish();
+
+        target
+    }
+
+    #[test]
+    fn material_samples_two_textures() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let red = cleared_target(&mut graphics_device, Color::new(1.0, 0.0, 0.0, 1.0));
+        let blue = cleared_target(&mut graphics_device, Color::new(0.0, 0.0, 1.0, 1.0));
+        let mut material = block_on(Material::new(
+            &graphics_device,
+            "Two textures",
+            TWO_TEXTURES,
+            &BINDINGS,
+            None,
+        ))
+        .unwrap();
+
+        let resources = [
+            MaterialResource::Texture(red.view()),
+            MaterialResource::Texture(blue.view()),
+            MaterialResource::Sampler(red.sampler()),
+        ];
+        material.set_resources(&graphics_device, &resources).unwrap();
+
+        let target = RenderTarget::new(&graphics_device);
+        let quad = TexturedQuad::new(&graphics_device);
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        quad.render_material(&material, &mut frame_encoder);
+        frame_encoder.finish();
+
+        let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if x < 2 { [255, 0, 0, 255] } else { [0, 0, 255, 255] };
+            assert_eq!(pixel.0, expected, "Pixel at {}, {}", x, y);
+        }
+    }
+
+    #[test]
+    fn set_resources_checks_the_bindings() {
+        let graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let texture = RenderTarget::new(&graphics_device);
+        let mut material = block_on(Material::new(
+            &graphics_device,
+            "Two textures",
+            TWO_TEXTURES,
+            &BINDINGS,
+            None,
+        ))
+        .unwrap();
+
+        let too_few = [MaterialResource::Texture(texture.view())];
+        assert!(matches!(
+            material.set_resources(&graphics_device, &too_few),
+            Err(MaterialError::ResourceCount { expected: 3, actual: 1 })
+        ));
+
+        let swapped = [
+            MaterialResource::Texture(texture.view()),
+            MaterialResource::Sampler(texture.sampler()),
+            MaterialResource::Texture(texture.view()),
+        ];
+        assert!(matches!(
+            material.set_resources(&graphics_device, &swapped),
+            Err(MaterialError::WrongResource { binding: 1, expected: MaterialBinding::Texture })
+        ));
+        assert!(material.bind_group().is_none());
+    }
+}
@@ -0,0 +1,177 @@
+/// An axis-aligned rectangle with the origin at the top left. Used for
+/// pixel rects as well as UV rects, where `x` and `y` are the minimum
+/// texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// The rect between two corners, e.g. `[u_min, v_min]` and `[u_max, v_max]`.
+    pub fn from_min_max(min: [f32; 2], max: [f32; 2]) -> Self {
+        Self::new(min[0], min[1], max[0] - min[0], max[1] - min[1])
+    }
+
+    pub fn min(&self) -> [f32; 2] {
+        [self.x, self.y]
+    }
+
+    pub fn max(&self) -> [f32; 2] {
+        [self.x + self.w, self.y + self.h]
+    }
+
+    pub fn center(&self) -> [f32; 2] {
+        [self.x + self.w / 2.0, self.y + self.h / 2.0]
+    }
+
+    /// Whether `point` is inside of the rect. The left and top edges
+    /// are inside, the right and bottom edges are not.
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let [x, y] = point;
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+
+    /// The point `pivot` of the way across the rect, `[0.5, 0.5]` being the center.
+    pub fn pivot_point(&self, pivot: [f32; 2]) -> [f32; 2] {
+        [self.x + self.w * pivot[0], self.y + self.h * pivot[1]]
+    }
+
+    /// The corners after rotating the rect `rotation` radians clockwise on screen
+    /// around its `pivot` point (see `pivot_point`). In the order top left, bottom
+    /// left, bottom right, top right of the unrotated rect.
+    pub fn rotated_corners(&self, rotation: f32, pivot: [f32; 2]) -> [[f32; 2]; 4] {
+        let [px, py] = self.pivot_point(pivot);
+        let (sin, cos) = rotation.sin_cos();
+        let rotate = |x: f32, y: f32| {
+            let (dx, dy) = (x - px, y - py);
+            [px + dx * cos - dy * sin, py + dx * sin + dy * cos]
+        };
+
+        let ([left, top], [right, bottom]) = (self.min(), self.max());
+        [rotate(left, top), rotate(left, bottom), rotate(right, bottom), rotate(right, top)]
+    }
+
+    /// A model matrix which maps the unit square (0, 0) to (1, 1) onto the rect,
+    /// rotated like `rotated_corners`. The same layout as the camera matrices,
+    /// for `TransformBinding` and `TransformBufferPool`.
+    pub fn model_matrix(&self, rotation: f32, pivot: [f32; 2]) -> [[f32; 4]; 4] {
+        let [px, py] = self.pivot_point(pivot);
+        let (sin, cos) = rotation.sin_cos();
+        let (dx, dy) = (self.x - px, self.y - py);
+
+        [
+            [self.w * cos, self.w * sin, 0.0, 0.0],
+            [-self.h * sin, self.h * cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [px + dx * cos - dy * sin, py + dx * sin + dy * cos, 0.0, 1.0],
+        ]
+    }
+
+    /// The smallest rect containing both rects.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let [left, top] = [self.x.min(other.x), self.y.min(other.y)];
+        let right = (self.x + self.w).max(other.x + other.w);
+        let bottom = (self.y + self.h).max(other.y + other.h);
+
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
+    /// The overlap of the two rects, `None` if they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.w).min(other.x + other.w);
+        let bottom = (self.y + self.h).min(other.y + other.h);
+
+        if right > left && bottom > top {
+            Some(Rect::new(left, top, right - left, bottom - top))
+        } else {
+            None
+        }
+    }
+}
+
+/// A color with RGBA components from 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+    pub const CORNFLOWER_BLUE: Color = Color::new(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// A color from 0 - 255 components.
+    pub fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    /// A color from a `0xRRGGBBAA` value, e.g. `Color::hex(0xff8800ff)` for opaque orange.
+    pub fn hex(rgba: u32) -> Self {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        Self::rgba8(r, g, b, a)
+    }
+
+    /// RGBA components, as shaders expect them.
+    pub fn to_f32_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<Color> for wgpu::Color {
+    fn from(color: Color) -> Self {
+        wgpu::Color { r: color.r as f64, g: color.g as f64, b: color.b as f64, a: color.a as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersect_is_the_overlap() {
+        let a = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let b = Rect::new(60.0, 20.0, 100.0, 100.0);
+
+        assert_eq!(a.intersect(&b), Some(Rect::new(60.0, 20.0, 40.0, 30.0)));
+        assert_eq!(b.intersect(&a), a.intersect(&b));
+        assert_eq!(a.intersect(&a), Some(a));
+    }
+
+    #[test]
+    fn intersect_is_none_without_overlap() {
+        let a = Rect::new(0.0, 0.0, 100.0, 50.0);
+
+        assert_eq!(a.intersect(&Rect::new(200.0, 0.0, 10.0, 10.0)), None);
+
+        // Rects only sharing an edge don't overlap either.
+        assert_eq!(a.intersect(&Rect::new(100.0, 0.0, 10.0, 10.0)), None);
+        assert_eq!(a.intersect(&Rect::new(0.0, 50.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn union_contains_both_rects() {
+        let a = Rect::new(10.0, 20.0, 30.0, 40.0);
+        let b = Rect::new(-5.0, 30.0, 10.0, 50.0);
+
+        assert_eq!(a.union(&b), Rect::new(-5.0, 20.0, 45.0, 60.0));
+        assert_eq!(b.union(&a), a.union(&b));
+        assert_eq!(a.union(&a), a);
+    }
+}
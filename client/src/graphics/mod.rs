@@ -1,48 +1,438 @@
 use bytemuck::{Pod, Zeroable};
+use instant::Instant;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use wgpu::{
-    util::DeviceExt, BackendBit, BindGroup, Buffer, CommandEncoder, Device, Instance, Queue,
-    RenderPipeline, Surface, SwapChain, SwapChainDescriptor, SwapChainTexture,
+    util::DeviceExt, AdapterInfo, BackendBit, BindGroup, Buffer, CommandEncoder, Device, Instance,
+    Queue, RenderPipeline, SwapChainDescriptor, SwapChainTexture, TextureView,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 
+/// The `ShaderModuleDescriptor` of the shader `name` in `resources/shaders`, e.g.
+/// `include_shader!("shape.vert")`. Natively that's the SPIR-V the build script
+/// compiles, browsers only take WGSL so on the web it's the port next to it
+/// (`shape.vert.wgsl`), which has to be kept in sync with the GLSL source.
+/// Defined before the modules so all of them can use it.
+macro_rules! include_shader {
+    ($name:literal) => {{
+        #[cfg(not(target_arch = "wasm32"))]
+        let descriptor =
+            wgpu::include_spirv!(concat!("../../../resources/shaders/", $name, ".spv"));
+
+        #[cfg(target_arch = "wasm32")]
+        let descriptor =
+            wgpu::ShaderModuleDescriptor {
+                label: Some($name),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                    concat!("../../../resources/shaders/", $name, ".wgsl")
+                ))),
+                flags: wgpu::ShaderFlags::all(),
+            };
+
+        descriptor
+    }};
+}
+
+mod animation;
+mod background;
+mod bind_group_cache;
+mod bloom;
+mod buffer_pool;
+mod camera;
+mod capture;
+mod color_grade;
+mod compute;
+mod cubemap;
+mod debug_overlay;
+mod frame_clock;
+mod frame_fences;
+mod fullscreen_pass;
+mod index_buffer;
+mod ktx2;
+mod letterbox;
+mod material;
+mod math;
+mod nine_slice;
+mod particles;
+mod picking;
+mod pipeline;
+mod post_process;
+mod profiler;
+mod render_layers;
+mod render_stats;
+mod render_surface;
+mod render_target;
+mod resource_tracker;
+pub mod rng;
+mod sampler;
+mod shader;
+mod shape;
 pub mod text;
+mod texture;
+mod texture_atlas;
+mod tile_map;
+mod tone_map;
+mod transform;
+mod tween;
+
+pub use animation::{Animation, PlaybackMode};
+pub use background::Background;
+pub use bind_group_cache::{BindGroupCache, ResourceId};
+pub use bloom::Bloom;
+pub use buffer_pool::{BufferPool, PooledSlice};
+pub use camera::Camera2D;
+pub use capture::CaptureError;
+pub use color_grade::{ColorGrade, ColorGradeParams};
+pub use compute::{ComputePass, StorageAccess, StorageBuffer};
+pub use cubemap::{CubeFace, Cubemap};
+pub use debug_overlay::DebugOverlay;
+pub use frame_clock::FrameClock;
+pub use fullscreen_pass::FullscreenPass;
+pub use index_buffer::{IndexBuffer, Indices};
+pub use letterbox::Letterbox;
+pub use material::{Material, MaterialBinding, MaterialError, MaterialResource};
+pub use math::{Color, Rect};
+pub use nine_slice::{NineSlice, NineSliceInsets};
+pub use particles::{EmitConfig, ParticleSystem};
+pub use picking::{PickingRenderer, PICKING_FORMAT};
+pub use pipeline::{
+    color_target, DepthKey, PipelineBuilder, PipelineCache, PipelineKey, TargetKey,
+    PREMULTIPLIED_ALPHA_BLENDING,
+};
+pub use post_process::PostProcess;
+pub use render_layers::{LayerDrawable, LayerSpace, RenderLayer};
+pub use render_stats::RenderStats;
+pub use render_surface::RenderSurface;
+pub use render_target::{DepthTarget, RenderTarget, DEPTH_FORMAT, HDR_FORMAT};
+pub use resource_tracker::{ResourceStats, ResourceTracker};
+pub use sampler::SamplerConfig;
+pub use shader::{
+    create_shader_variant, preprocess_shader, try_create_shader_module, ShaderError, ShaderFeatures,
+};
+pub use shape::{ShapeRenderer, SpinnerStyle};
+pub use texture::{Texture, TextureConfig, TextureError};
+pub use texture_atlas::{AtlasError, TextureAtlas};
+pub use tile_map::TileMap;
+pub use tone_map::{ToneMap, ToneMapOperator};
+pub use transform::TransformBufferPool;
+pub use tween::{Easing, Lerp, Tween};
+
+use frame_fences::FrameFences;
+use letterbox::LetterboxFill;
+use profiler::GpuProfiler;
+use render_layers::LayerQueues;
+use transform::TRANSFORM_PUSH_CONSTANT_SIZE;
+
+/// Distances in pixels from each edge of the frame which
+/// may be obscured by notches or rounded display corners.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SafeAreaInsets {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl SafeAreaInsets {
+    /// `rect` shrunk by the insets, with its size clamped to 0.
+    pub fn shrink(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + self.left,
+            rect.y + self.top,
+            (rect.w - self.left - self.right).max(0.0),
+            (rect.h - self.top - self.bottom).max(0.0),
+        )
+    }
+}
+
+/// Picks an adapter from `GraphicsDevice::enumerate_adapters`, see `GraphicsDeviceConfig::adapter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelection {
+    /// The adapter at this index in the list.
+    Index(usize),
+
+    /// The first adapter whose name contains this, ignoring case, e.g. "nvidia".
+    Name(String),
+}
+
+impl AdapterSelection {
+    fn matches(&self, index: usize, info: &AdapterInfo) -> bool {
+        match self {
+            AdapterSelection::Index(selected) => index == *selected,
+            AdapterSelection::Name(name) => info.name.to_lowercase().contains(&name.to_lowercase()),
+        }
+    }
+}
+
+/// Options for creating a `GraphicsDevice`.
+#[derive(Debug, Clone)]
+pub struct GraphicsDeviceConfig {
+    /// Which wgpu backends adapters may be picked from. The `WGPU_BACKEND`
+    /// environment variable (a comma separated list of `vulkan`, `metal`,
+    /// `dx12`, `dx11`, `gl` or `webgpu`) overrides this when set.
+    pub backends: BackendBit,
+
+    /// How many frames the CPU may submit before the GPU has finished them,
+    /// `begin_frame` blocks when there are more. Lower values reduce input latency
+    /// at the cost of throughput. 0 doesn't limit it beyond the swap chain's own
+    /// buffering, which is the default. Ignored on the web, which can't block.
+    pub max_frames_in_flight: u32,
+
+    /// What swap chain frames are cleared to in `begin_frame` and `begin_frame_on`.
+    /// `None` doesn't clear, so whatever was there before shows through. Can be
+    /// changed later with `GraphicsDevice::set_clear_color`.
+    pub clear_color: Option<Color>,
+
+    /// Asks for a transparent window in `run_app`, so clearing to
+    /// `Color::TRANSPARENT` composites the frame over what's behind the window.
+    /// Whether the surface actually keeps alpha is up to the platform and
+    /// backend, where it isn't supported the window stays opaque.
+    pub transparent: bool,
+
+    /// Renders the scene in `HDR_FORMAT` so colors can go above 1.0, for a
+    /// `ToneMap` to bring back to the swap chain's range. See `color_format`.
+    /// Falls back to the swap chain format if the adapter can't render to it.
+    pub hdr: bool,
+
+    /// Forces an adapter instead of letting wgpu pick by power preference, e.g. the
+    /// discrete GPU of a laptop. If it doesn't exist or can't present to the
+    /// window, the default adapter is used. Ignored on the web.
+    pub adapter: Option<AdapterSelection>,
+}
 
-const CORNFLOWER_BLUE: wgpu::Color =
-    wgpu::Color { r: 100.0 / 255.0, g: 149.0 / 255.0, b: 237.0 / 255.0, a: 1.0 };
+impl Default for GraphicsDeviceConfig {
+    fn default() -> Self {
+        // PRIMARY: All the apis that wgpu offers first tier of support for (Vulkan + Metal + DX12 + Browser WebGPU).
+        Self {
+            backends: BackendBit::PRIMARY,
+            max_frames_in_flight: 0,
+            clear_color: Some(Color::CORNFLOWER_BLUE),
+            transparent: false,
+            hdr: false,
+            adapter: None,
+        }
+    }
+}
+
+impl GraphicsDeviceConfig {
+    /// The backends to use, taking the `WGPU_BACKEND` override into account.
+    fn selected_backends(&self) -> BackendBit {
+        let env_backends = match std::env::var("WGPU_BACKEND") {
+            Ok(env_backends) => env_backends,
+            Err(_) => return self.backends,
+        };
+
+        let mut backends = BackendBit::empty();
+        for name in env_backends.split(',') {
+            backends |= match name.trim().to_lowercase().as_str() {
+                "vulkan" | "vk" => BackendBit::VULKAN,
+                "metal" | "mtl" => BackendBit::METAL,
+                "dx12" | "d3d12" => BackendBit::DX12,
+                "dx11" | "d3d11" => BackendBit::DX11,
+                "gl" | "opengl" | "gles" => BackendBit::GL,
+                "webgpu" => BackendBit::BROWSER_WEBGPU,
+                "primary" => BackendBit::PRIMARY,
+                "secondary" => BackendBit::SECONDARY,
+                "all" => BackendBit::all(),
+                other => {
+                    println!("Ignoring unknown backend in WGPU_BACKEND: {:?}", other);
+                    BackendBit::empty()
+                },
+            };
+        }
+
+        if backends.is_empty() {
+            println!("WGPU_BACKEND selected no backends, using {:?}", self.backends);
+            self.backends
+        } else {
+            backends
+        }
+    }
+}
+
+/// The steps of `GraphicsDevice::new_with_progress`, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsInit {
+    RequestingAdapter,
+    RequestingDevice,
+    Ready,
+}
+
+/// How many frames `FrameEncoder::write_dynamic` keeps the buffers of apart,
+/// enough for triple buffered swap chains.
+const DYNAMIC_BUFFER_FRAMES: usize = 3;
+
+/// The MSAA sample counts `GraphicsDevice::msaa_sample_count` picks from, in
+/// increasing order. wgpu at this revision can't query which counts a format
+/// supports (`TextureFormatFeatureFlags` only has storage flags), so these are
+/// the counts WebGPU guarantees for every renderable format.
+const MSAA_SAMPLE_COUNTS: [u32; 2] = [1, 4];
 
+/// Fields are dropped in declaration order, so the GPU resources it owns go
+/// before the queue, device and instance they were created from, starting
+/// with the swap chain and surface. Dropping it waits for the GPU to finish
+/// submitted work first, see `wait_idle`.
 pub struct GraphicsDevice {
-    device: Device,
+    surface: RenderSurface,
+    gpu_profiler: Option<GpuProfiler>,
+    frame_fences: Option<FrameFences>,
+    pipeline_cache: PipelineCache,
+
+    /// Vertex and index data of dynamic drawables, see `FrameEncoder::write_dynamic`.
+    dynamic_buffers: BufferPool,
+
+    /// Created along with the first letterbox.
+    letterbox_fill: Option<LetterboxFill>,
     queue: Queue,
-    surface: Surface,
-    swap_chain_descriptor: SwapChainDescriptor,
-    swap_chain: SwapChain,
+    device: Device,
+    instance: Instance,
+
+    /// What `instance` was created for, which `enumerate_adapters` lists.
+    backends: BackendBit,
+    adapter_info: AdapterInfo,
+    safe_area_insets: SafeAreaInsets,
+    scale_factor: f64,
+    frame_clock: FrameClock,
+    target_frame_time: Option<Duration>,
+    render_layers: Vec<RenderLayer>,
+    clear_color: Option<Color>,
+    letterbox: Option<Letterbox>,
+    last_frame_stats: RenderStats,
+    color_format: wgpu::TextureFormat,
+
+    /// How many frame encoders were created, see `FrameEncoder::frame_index`.
+    frames_begun: u64,
+
+    /// Set when data was written to the queue outside of a frame (e.g. texture
+    /// uploads) which hasn't been waited on with `flush_uploads` yet.
+    uploads_pending: AtomicBool,
 }
 
 impl GraphicsDevice {
-    pub async fn new(window: &Window) -> Self {
+    pub async fn new(window: &Window, config: GraphicsDeviceConfig) -> Self {
+        Self::new_with_progress(window, config, |_| {}).await
+    }
+
+    /// Same as `new`, but calls `on_progress` as initialization moves
+    /// through each `GraphicsInit` state, e.g. to update a splash screen.
+    pub async fn new_with_progress<F: FnMut(GraphicsInit)>(
+        window: &Window,
+        config: GraphicsDeviceConfig,
+        mut on_progress: F,
+    ) -> Self {
         let size = window.inner_size();
 
-        // PRIMARY: All the apis that wgpu offers first tier of support for (Vulkan + Metal + DX12 + Browser WebGPU).
-        let instance = Instance::new(BackendBit::PRIMARY);
+        on_progress(GraphicsInit::RequestingAdapter);
+
+        let backends = config.selected_backends();
+        let instance = Instance::new(backends);
+        // Safety: the window outlives the surface, as the surface is dropped with
+        // `self` and the window is kept alive by the event loop. On the web the
+        // window's canvas has to be in the document already, wgpu finds it there.
         let surface = unsafe { instance.create_surface(window) };
-        let swapchain_format = wgpu::TextureFormat::Bgra8Unorm;
+
+        let selected_adapter = config
+            .adapter
+            .as_ref()
+            .and_then(|selection| Self::select_adapter(&instance, backends, selection, &surface));
+
+        let adapter = match selected_adapter {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    // Prefer low power when on battery, high performance when on mains.
+                    power_preference: wgpu::PowerPreference::default(),
+                    // Request an adapter which can render to our surface
+                    compatible_surface: Some(&surface),
+                })
+                .await
+                .expect("Failed to find an appropiate adapter"),
+        };
+
+        let mut graphics_device = Self::with_adapter(
+            instance,
+            backends,
+            adapter,
+            Some(surface),
+            size,
+            config,
+            on_progress,
+        )
+        .await;
+        graphics_device.scale_factor = window.scale_factor();
+        graphics_device
+    }
+
+    /// A device which doesn't present to a window, for rendering into render
+    /// targets with `begin_frame_to_render_target`, e.g. in tests. It's suspended
+    /// for good, so `begin_frame` always returns `None`, and `size` is what render
+    /// targets are created at. Uses the default adapter, and returns `None` if
+    /// there isn't one.
+    pub async fn new_headless(
+        config: GraphicsDeviceConfig,
+        size: PhysicalSize<u32>,
+    ) -> Option<Self> {
+        let backends = config.selected_backends();
+        let instance = Instance::new(backends);
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                // Prefer low power when on battery, high performance when on mains.
                 power_preference: wgpu::PowerPreference::default(),
-                // Request an adapter which can render to our surface
-                compatible_surface: Some(&surface),
+                compatible_surface: None,
             })
-            .await
-            .expect("Failed to find an appropiate adapter");
+            .await?;
+
+        Some(Self::with_adapter(instance, backends, adapter, None, size, config, |_| {}).await)
+    }
+
+    /// Creates the device and everything else on `adapter`, presenting to `surface`
+    /// (if any) at `size`.
+    async fn with_adapter<F: FnMut(GraphicsInit)>(
+        instance: Instance,
+        backends: BackendBit,
+        adapter: wgpu::Adapter,
+        surface: Option<wgpu::Surface>,
+        size: PhysicalSize<u32>,
+        config: GraphicsDeviceConfig,
+        mut on_progress: F,
+    ) -> Self {
+        let swapchain_format = wgpu::TextureFormat::Bgra8Unorm;
+
+        let adapter_info = adapter.get_info();
+        println!("Using adapter {} ({:?} backend)", adapter_info.name, adapter_info.backend);
+
+        // Only request optional features the adapter has. GPU profiling needs
+        // timestamp queries, samplers use anisotropy when it's configured, KTX2
+        // textures use whichever compressed formats there are, and push constants
+        // are used for transforms when available.
+        let mut features = adapter.features()
+            & (wgpu::Features::TIMESTAMP_QUERY
+                | wgpu::Features::SAMPLER_ANISOTROPY
+                | wgpu::Features::TEXTURE_COMPRESSION_BC
+                | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                | wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR);
+        let mut limits = wgpu::Limits::default();
+
+        if adapter.features().contains(wgpu::Features::PUSH_CONSTANTS)
+            && adapter.limits().max_push_constant_size >= TRANSFORM_PUSH_CONSTANT_SIZE
+        {
+            features |= wgpu::Features::PUSH_CONSTANTS;
+            limits.max_push_constant_size = TRANSFORM_PUSH_CONSTANT_SIZE;
+        }
+
+        on_progress(GraphicsInit::RequestingDevice);
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
+                    features,
+                    limits,
                     // shader_validation: true,
                 },
                 None,
@@ -50,88 +440,976 @@ impl GraphicsDevice {
             .await
             .expect("Failed to create device");
 
-        let swap_chain_descriptor = wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
-            format: swapchain_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Mailbox,
+        let surface = match surface {
+            Some(surface) => RenderSurface::with_preferred_present_mode(
+                &device,
+                surface,
+                swapchain_format,
+                size,
+                adapter_info.backend,
+            ),
+            None => RenderSurface::headless(swapchain_format, size),
         };
 
-        let swap_chain = device.create_swap_chain(&surface, &swap_chain_descriptor);
+        let gpu_profiler = if features.contains(wgpu::Features::TIMESTAMP_QUERY) {
+            Some(GpuProfiler::new(&device, &queue))
+        } else {
+            println!("Adapter doesn't support timestamp queries, GPU frame times are unavailable");
+            None
+        };
+
+        let color_format = if !config.hdr {
+            swapchain_format
+        } else if adapter
+            .get_texture_format_features(HDR_FORMAT)
+            .allowed_usages
+            .contains(wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED)
+        {
+            HDR_FORMAT
+        } else {
+            println!("Adapter can't render to {:?}, HDR is unavailable", HDR_FORMAT);
+            swapchain_format
+        };
+
+        let frame_fences = match config.max_frames_in_flight {
+            0 => None,
+            _ if cfg!(target_arch = "wasm32") => None,
+            max_frames_in_flight => Some(FrameFences::new(&device, max_frames_in_flight)),
+        };
+
+        on_progress(GraphicsInit::Ready);
+
+        Self {
+            adapter_info,
+            device,
+            queue,
+            instance,
+            backends,
+            surface,
+            safe_area_insets: SafeAreaInsets::default(),
+            scale_factor: 1.0,
+            frame_clock: FrameClock::new(),
+            target_frame_time: None,
+            gpu_profiler,
+            frame_fences,
+            pipeline_cache: PipelineCache::new(),
+            dynamic_buffers: BufferPool::new(
+                "Dynamic buffer",
+                wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::INDEX,
+                DYNAMIC_BUFFER_FRAMES,
+            ),
+            render_layers: RenderLayer::defaults(),
+            clear_color: config.clear_color,
+            letterbox: None,
+            letterbox_fill: None,
+            last_frame_stats: RenderStats::default(),
+            color_format,
+            frames_begun: 0,
+            uploads_pending: AtomicBool::new(false),
+        }
+    }
+
+    /// The adapter `selection` picks, `None` (with a warning) if there's
+    /// none or it can't present to `surface`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn select_adapter(
+        instance: &Instance,
+        backends: BackendBit,
+        selection: &AdapterSelection,
+        surface: &wgpu::Surface,
+    ) -> Option<wgpu::Adapter> {
+        let adapter = instance
+            .enumerate_adapters(backends)
+            .enumerate()
+            .find(|(index, adapter)| selection.matches(*index, &adapter.get_info()))
+            .map(|(_, adapter)| adapter);
+
+        match adapter {
+            Some(adapter) if adapter.is_surface_supported(surface) => Some(adapter),
+            Some(adapter) => {
+                let name = adapter.get_info().name;
+                println!("Adapter {} can't present to the window, using the default adapter", name);
+                None
+            },
+            None => {
+                println!("No adapter matches {:?}, using the default adapter", selection);
+                None
+            },
+        }
+    }
+
+    /// Browsers only hand out one adapter, there's nothing to select from.
+    #[cfg(target_arch = "wasm32")]
+    fn select_adapter(
+        _instance: &Instance,
+        _backends: BackendBit,
+        selection: &AdapterSelection,
+        _surface: &wgpu::Surface,
+    ) -> Option<wgpu::Adapter> {
+        println!("Ignoring adapter selection {:?} on the web", selection);
+        None
+    }
+
+    /// Submits pending queue writes and blocks until the GPU has finished
+    /// them, so nothing is drawn with partially uploaded textures. The first
+    /// `begin_frame` does this automatically, so assets loaded before it
+    /// are complete by the time the first frame is shown.
+    pub fn flush_uploads(&self) {
+        self.uploads_pending.store(false, Ordering::Relaxed);
+
+        // Writes are only scheduled along with the next submission.
+        self.queue.submit(std::iter::empty());
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// Blocks until the GPU has finished all submitted work, e.g. before
+    /// destroying resources which may still be in use by in-flight frames.
+    /// Does nothing on the web, where the browser can't be blocked on.
+    pub fn wait_idle(&self) {
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// Whether there were queue writes since the last `flush_uploads`.
+    pub fn has_pending_uploads(&self) -> bool {
+        self.uploads_pending.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn mark_upload_pending(&self) {
+        self.uploads_pending.store(true, Ordering::Relaxed);
+    }
+
+    /// Begins a frame for the main window, `None` while suspended.
+    pub fn begin_frame(&mut self) -> Option<FrameEncoder> {
+        if self.frame_clock.frame_count() == 0 && self.has_pending_uploads() {
+            self.flush_uploads();
+        }
+
+        if let Some(frame_fences) = self.frame_fences.as_mut() {
+            frame_fences.wait(&self.device);
+        }
+
+        let frame = self.surface.current_frame()?;
+        let descriptor = self.surface.swap_chain_descriptor();
+        let size = [descriptor.width, descriptor.height];
+
+        self.frame_clock.tick();
+
+        if let Some(gpu_profiler) = self.gpu_profiler.as_mut() {
+            gpu_profiler.poll(&self.device);
+        }
+
+        let clear_color = self.clear_color;
+        let mut frame_encoder =
+            self.begin_frame_to_target(FrameTarget::SwapChain(frame), size, true);
+        frame_encoder.clear_frame(clear_color);
+
+        Some(frame_encoder)
+    }
+
+    /// Begins a frame which renders to a surface from `create_surface`, usually for
+    /// a secondary window. Unlike `begin_frame` this doesn't tick the frame clock or
+    /// take part in GPU profiling, so those keep measuring the main window.
+    /// Returns `None` while `surface` is suspended.
+    pub fn begin_frame_on(&mut self, surface: &RenderSurface) -> Option<FrameEncoder> {
+        let frame = surface.current_frame()?;
+        let descriptor = surface.swap_chain_descriptor();
+        let size = [descriptor.width, descriptor.height];
+
+        let clear_color = self.clear_color;
+        let mut frame_encoder =
+            self.begin_frame_to_target(FrameTarget::SwapChain(frame), size, false);
+        frame_encoder.clear_frame(clear_color);
+
+        Some(frame_encoder)
+    }
+
+    /// Creates a surface for another window which renders with this device, so
+    /// GPU resources can be shared between windows. The swap chain uses the same
+    /// format and present mode as the main window, so pipelines work with both.
+    pub fn create_surface(&self, window: &Window) -> RenderSurface {
+        // Safety: the same as for the main window, `window` has to outlive the surface.
+        let surface = unsafe { self.instance.create_surface(window) };
+        let descriptor = self.surface.swap_chain_descriptor();
+
+        RenderSurface::new(
+            &self.device,
+            surface,
+            descriptor.format,
+            descriptor.present_mode,
+            window.inner_size(),
+        )
+    }
+
+    /// Begins a frame which renders into one face of `cube` instead of the swap chain.
+    pub fn begin_frame_to_cube_face(&mut self, cube: &Cubemap, face: CubeFace) -> FrameEncoder {
+        let size = [cube.size(), cube.size()];
+        self.begin_frame_to_target(FrameTarget::View(cube.face_view(face)), size, false)
+    }
 
-        Self { device, queue, surface, swap_chain_descriptor, swap_chain }
+    /// Begins a frame which renders into `target` instead of the swap chain, e.g.
+    /// on a device from `new_headless`. Like `begin_frame_on`, this doesn't tick
+    /// the frame clock or take part in GPU profiling.
+    pub fn begin_frame_to_render_target(&mut self, target: &RenderTarget) -> FrameEncoder {
+        let (width, height) = target.size();
+        self.begin_frame_to_target(target.frame_target(), [width, height], false)
     }
 
-    pub fn begin_frame(&mut self) -> FrameEncoder {
-        let frame = self
-            .swap_chain
-            .get_current_frame()
-            .expect("Failed to acquire next swap chain texture")
-            .output;
+    /// `size` is the size of `frame` in pixels. `main_frame` is true for
+    /// frames of the main window, which are profiled and paced.
+    fn begin_frame_to_target(
+        &mut self,
+        frame: FrameTarget,
+        size: [u32; 2],
+        main_frame: bool,
+    ) -> FrameEncoder {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame") });
+
+        let frame_deadline = if main_frame {
+            frame_deadline(
+                self.surface.present_mode(),
+                self.target_frame_time,
+                self.frame_clock.last_frame_instant(),
+            )
+        } else {
+            None
+        };
+
+        let gpu_profiler = if main_frame { self.gpu_profiler.as_mut() } else { None };
+        let frame_fences = if main_frame { self.frame_fences.as_mut() } else { None };
+        let last_frame_stats = if main_frame { Some(&mut self.last_frame_stats) } else { None };
+        let letterbox =
+            if main_frame { self.letterbox.zip(self.letterbox_fill.as_ref()) } else { None };
+
+        if let Some(gpu_profiler) = gpu_profiler.as_deref_mut() {
+            gpu_profiler.begin(&mut encoder);
+        }
 
-        let encoder =
-            self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.frames_begun += 1;
+        self.dynamic_buffers.next_frame();
 
-        FrameEncoder { queue: &mut self.queue, frame, encoder }
+        FrameEncoder {
+            device: &self.device,
+            queue: &mut self.queue,
+            dynamic_buffers: &mut self.dynamic_buffers,
+            gpu_profiler,
+            frame_fences,
+            last_frame_stats,
+            frame_deadline,
+            scale_factor: self.scale_factor,
+            frame_index: self.frames_begun,
+            target_size: size,
+            clip_rect: None,
+            letterbox,
+            layers: LayerQueues::new(&self.render_layers),
+            stats: RenderStats::default(),
+            frame,
+            encoder,
+        }
     }
 
+    /// Resizes the main window's swap chain. Surfaces from `create_surface`
+    /// are resized with `RenderSurface::resize`.
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        self.swap_chain_descriptor.width = new_size.width;
-        self.swap_chain_descriptor.height = new_size.height;
-        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.swap_chain_descriptor);
+        self.surface.resize(&self.device, new_size);
+    }
+
+    /// Drops the main window's surface, call this on winit's `Suspended` event,
+    /// as the window may be destroyed while the app is in the background (e.g. on
+    /// Android). `begin_frame` returns `None` until `resume`. Everything else,
+    /// like textures and pipelines, stays valid.
+    pub fn suspend(&mut self) {
+        self.surface.suspend();
+    }
+
+    /// Creates a new surface for `window` after `suspend`, call this on winit's
+    /// `Resumed` event. Does nothing when not suspended.
+    pub fn resume(&mut self, window: &Window) {
+        if !self.surface.is_suspended() {
+            return;
+        }
+
+        // Safety: the same as for the surface created in `new`.
+        let surface = unsafe { self.instance.create_surface(window) };
+        self.surface.resume(&self.device, surface, window.inner_size());
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_suspended()
+    }
+
+    /// Call this on winit's `ScaleFactorChanged` event, which also resizes the window.
+    pub fn set_scale_factor(&mut self, scale_factor: f64, new_size: PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.resize(new_size);
+    }
+
+    /// The ratio of physical to logical pixels of the main window (e.g. 2.0 on most HiDPI displays).
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Converts a point in logical pixels to physical pixels.
+    pub fn logical_to_physical(&self, point: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale_factor as f32;
+        [point[0] * scale, point[1] * scale]
+    }
+
+    /// Whether transforms can be passed to shaders with push
+    /// constants instead of uniform buffers.
+    pub fn supports_push_constants(&self) -> bool {
+        self.device.features().contains(wgpu::Features::PUSH_CONSTANTS)
+    }
+
+    /// The format drawables create their pipelines for, and which `RenderTarget::new`
+    /// uses. It's the swap chain format, or `HDR_FORMAT` when HDR was enabled in the
+    /// config and is supported. With HDR the scene has to be drawn into a render
+    /// target and then to the swap chain with `ToneMap`, as pipelines and the
+    /// frame target format have to match.
+    pub fn color_format(&self) -> wgpu::TextureFormat {
+        self.color_format
+    }
+
+    /// Whether the scene is rendered in `HDR_FORMAT`, see `color_format`.
+    pub fn is_hdr(&self) -> bool {
+        self.color_format == HDR_FORMAT
+    }
+
+    /// The highest MSAA sample count usable with the swap chain format, the last of
+    /// `MSAA_SAMPLE_COUNTS`.
+    pub fn max_msaa_samples(&self) -> u32 {
+        MSAA_SAMPLE_COUNTS[MSAA_SAMPLE_COUNTS.len() - 1]
+    }
+
+    /// Picks the supported MSAA sample count for a requested one: the smallest of
+    /// `MSAA_SAMPLE_COUNTS` which is at least `requested`, or the highest if none
+    /// is. Use the result for the multisampled texture and the pipeline's
+    /// `MultisampleState` alike, as wgpu rejects render passes where they differ.
+    pub fn msaa_sample_count(&self, requested: u32) -> u32 {
+        let sample_count = supported_sample_count(requested, &MSAA_SAMPLE_COUNTS);
+
+        if sample_count != requested.max(1) {
+            println!(
+                "MSAA with {} samples isn't supported, using {} samples",
+                requested, sample_count
+            );
+        }
+
+        sample_count
+    }
+
+    /// Every adapter of the backends the device was created for, in the order
+    /// `AdapterSelection::Index` refers to. Store the choice (e.g. in a settings
+    /// menu) and pass it in the config the next time the device is created.
+    /// On the web it's just the adapter in use.
+    pub fn enumerate_adapters(&self) -> Vec<AdapterInfo> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.instance
+                .enumerate_adapters(self.backends)
+                .map(|adapter| adapter.get_info())
+                .collect()
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            vec![self.adapter_info.clone()]
+        }
+    }
+
+    /// Information about the adapter the device was created on.
+    pub fn adapter_info(&self) -> AdapterInfo {
+        self.adapter_info.clone()
+    }
+
+    /// A human readable summary of the adapter and the features and limits
+    /// the device was created with, for debug overlays and bug reports.
+    /// wgpu doesn't report driver versions, so the vendor and device IDs
+    /// are included to identify the GPU instead.
+    pub fn capabilities_report(&self) -> String {
+        let info = &self.adapter_info;
+
+        format!(
+            "Adapter: {}\n\
+             Backend: {:?}\n\
+             Device type: {:?}\n\
+             Vendor ID: {:#06x}\n\
+             Device ID: {:#06x}\n\
+             Features: {:?}\n\
+             Limits: {:#?}\n",
+            info.name,
+            info.backend,
+            info.device_type,
+            info.vendor,
+            info.device,
+            self.device.features(),
+            self.device.limits(),
+        )
+    }
+
+    /// How long the GPU spent on a recent frame, in milliseconds. Returns `None`
+    /// if the adapter doesn't support timestamp queries or no frame has been measured yet.
+    pub fn last_gpu_frame_ms(&self) -> Option<f32> {
+        self.gpu_profiler.as_ref().and_then(GpuProfiler::last_frame_ms)
+    }
+
+    /// Replaces the layers `FrameEncoder::submit` draws into, from back to
+    /// front. Defaults to `RenderLayer::defaults`.
+    pub fn set_render_layers(&mut self, layers: Vec<RenderLayer>) {
+        self.render_layers = layers;
+    }
+
+    pub fn render_layers(&self) -> &[RenderLayer] {
+        &self.render_layers
+    }
+
+    /// What swap chain frames are cleared to when they begin, `None` to not clear them.
+    pub fn set_clear_color(&mut self, color: Option<Color>) {
+        self.clear_color = color;
+    }
+
+    pub fn clear_color(&self) -> Option<Color> {
+        self.clear_color
+    }
+
+    /// Letterboxes the main window to a fixed design resolution, or stops
+    /// letterboxing with `None`. Takes effect from the next `begin_frame`.
+    pub fn set_letterbox(&mut self, letterbox: Option<Letterbox>) {
+        if letterbox.is_some() && self.letterbox_fill.is_none() {
+            self.letterbox_fill = Some(LetterboxFill::new(self));
+        }
+
+        self.letterbox = letterbox;
+    }
+
+    pub fn letterbox(&self) -> Option<Letterbox> {
+        self.letterbox
+    }
+
+    /// What the last finished frame of the main window drew, see `RenderStats`.
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats
+    }
+
+    /// Render pipelines shared between drawables.
+    pub fn pipeline_cache(&self) -> &PipelineCache {
+        &self.pipeline_cache
+    }
+
+    /// Limits the main window to `fps` frames per second by sleeping at the
+    /// end of `FrameEncoder::finish`, or renders as fast as possible with `None`.
+    /// This has no effect with the `Fifo` present mode, which is limited by vsync,
+    /// or on the web, where the browser paces frames.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.target_frame_time =
+            fps.filter(|fps| *fps > 0).map(|fps| Duration::from_secs_f64(1.0 / fps as f64));
+    }
+
+    /// Timing of the frames started with `begin_frame`.
+    pub fn frame_clock(&self) -> &FrameClock {
+        &self.frame_clock
     }
 
     pub fn device(&self) -> &Device {
         &self.device
     }
 
+    /// The queue, for `write_buffer` and `write_texture` between frames. Writes
+    /// aren't executed immediately, they're scheduled with the next submission,
+    /// and so take effect before any commands of the next frame. Call
+    /// `flush_uploads` to wait for them before the first frame.
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// The swap chain of the main window.
     pub fn swap_chain_descriptor(&self) -> &SwapChainDescriptor {
-        &self.swap_chain_descriptor
+        self.surface.swap_chain_descriptor()
+    }
+
+    pub fn set_safe_area_insets(&mut self, top: f32, right: f32, bottom: f32, left: f32) {
+        self.safe_area_insets = SafeAreaInsets { top, right, bottom, left };
+    }
+
+    pub fn safe_area_insets(&self) -> SafeAreaInsets {
+        self.safe_area_insets
+    }
+
+    /// The full frame rect in pixels.
+    pub fn frame_rect(&self) -> Rect {
+        Rect::new(
+            0.0,
+            0.0,
+            self.swap_chain_descriptor().width as f32,
+            self.swap_chain_descriptor().height as f32,
+        )
+    }
+
+    /// The frame rect shrunk by the safe area insets. HUD elements
+    /// should be anchored to this instead of the full frame.
+    pub fn safe_area_rect(&self) -> Rect {
+        self.safe_area_insets.shrink(self.frame_rect())
+    }
+}
+
+/// When a frame which started at `frame_start` has to finish to keep to
+/// `target_frame_time`, `None` without a target. Fifo already waits for vsync,
+/// pacing on top of it would halve the frame rate whenever a frame misses its budget.
+fn frame_deadline(
+    present_mode: wgpu::PresentMode,
+    target_frame_time: Option<Duration>,
+    frame_start: Option<Instant>,
+) -> Option<Instant> {
+    if present_mode == wgpu::PresentMode::Fifo {
+        return None;
+    }
+
+    Some(frame_start? + target_frame_time?)
+}
+
+/// The smallest of `supported` which is at least `requested`, or the highest
+/// one if none is. `supported` is in increasing order.
+fn supported_sample_count(requested: u32, supported: &[u32]) -> u32 {
+    let highest = supported[supported.len() - 1];
+    supported.iter().copied().find(|&count| count >= requested).unwrap_or(highest)
+}
+
+impl Drop for GraphicsDevice {
+    fn drop(&mut self) {
+        // Resources mustn't be destroyed while in-flight frames still use them.
+        self.wait_idle();
+    }
+}
+
+/// A clip rect in physical pixels, inside of the frame target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScissorRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// The clip rect and letterbox viewport of a frame, see `FrameEncoder::scissor_rect`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PassClip {
+    /// In logical pixels (design pixels when letterboxed), see `FrameEncoder::set_clip_rect`.
+    clip_rect: Option<Rect>,
+
+    /// Physical pixels per clip rect pixel, the scale factor or the letterbox scale.
+    scale: f32,
+
+    /// The clip rect's origin in physical pixels, the letterbox viewport's corner.
+    origin: [f32; 2],
+    target_size: [u32; 2],
+
+    /// In physical pixels.
+    viewport: Option<Rect>,
+}
+
+impl PassClip {
+    /// The clip rect scaled to physical pixels and clamped to the frame target.
+    fn scissor_rect(&self) -> Option<ScissorRect> {
+        let [width, height] = self.target_size;
+        let [origin_x, origin_y] = self.origin;
+        let clamp = |value: f32, origin: f32, max: u32| {
+            (origin + value * self.scale).round().max(0.0).min(max as f32) as u32
+        };
+
+        self.clip_rect.map(|rect| {
+            let (left, top) = (clamp(rect.x, origin_x, width), clamp(rect.y, origin_y, height));
+            let right = clamp(rect.x + rect.w, origin_x, width);
+            let bottom = clamp(rect.y + rect.h, origin_y, height);
+
+            ScissorRect {
+                x: left,
+                y: top,
+                width: right.saturating_sub(left),
+                height: bottom.saturating_sub(top),
+            }
+        })
+    }
+}
+
+/// Sets the clip rect and viewport from `FrameEncoder::scissor_rect` on `render_pass`,
+/// scaling the clip rect to physical pixels. Returns false if nothing should be drawn
+/// because the clip rect doesn't have any area.
+pub(crate) fn apply_scissor_rect(render_pass: &mut wgpu::RenderPass, clip: PassClip) -> bool {
+    if let Some(Rect { x, y, w, h }) = clip.viewport {
+        render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+    }
+
+    match clip.scissor_rect() {
+        Some(ScissorRect { width: 0, .. }) | Some(ScissorRect { height: 0, .. }) => false,
+        Some(ScissorRect { x, y, width, height }) => {
+            render_pass.set_scissor_rect(x, y, width, height);
+            true
+        },
+        None => true,
+    }
+}
+
+/// Marks the next draw of `render_pass` as `label` in GPU captures
+/// with the `debug-markers` feature, and does nothing without it.
+#[cfg(feature = "debug-markers")]
+pub(crate) fn debug_marker(render_pass: &mut wgpu::RenderPass, label: &str) {
+    render_pass.insert_debug_marker(label);
+}
+
+#[cfg(not(feature = "debug-markers"))]
+pub(crate) fn debug_marker(_render_pass: &mut wgpu::RenderPass, _label: &str) {}
+
+/// The texture a frame renders into.
+pub enum FrameTarget {
+    SwapChain(SwapChainTexture),
+    View(TextureView),
+}
+
+impl FrameTarget {
+    pub fn view(&self) -> &TextureView {
+        match self {
+            FrameTarget::SwapChain(frame) => &frame.view,
+            FrameTarget::View(view) => view,
+        }
     }
 }
 
 pub struct FrameEncoder<'a> {
+    device: &'a Device,
     queue: &'a mut Queue,
-    pub frame: SwapChainTexture,
+    dynamic_buffers: &'a mut BufferPool,
+    gpu_profiler: Option<&'a mut GpuProfiler>,
+    frame_fences: Option<&'a mut FrameFences>,
+
+    /// Where the stats go in `finish`, for frames of the main window.
+    last_frame_stats: Option<&'a mut RenderStats>,
+
+    /// When pacing to a target FPS, `finish` waits until this instant.
+    frame_deadline: Option<Instant>,
+    scale_factor: f64,
+    frame_index: u64,
+    target_size: [u32; 2],
+
+    /// The clip rect in logical pixels, scaled in `apply_scissor_rect`.
+    clip_rect: Option<Rect>,
+    letterbox: Option<(Letterbox, &'a LetterboxFill)>,
+    layers: LayerQueues<'a>,
+    stats: RenderStats,
+    pub frame: FrameTarget,
     pub encoder: CommandEncoder,
 }
 
 impl<'a> FrameEncoder<'a> {
+    /// Makes subsequent drawing go to `target` (e.g. `RenderTarget::frame_target`)
+    /// and returns the previous target, so it can be restored afterwards:
+    ///
+    /// ```ignore
+    /// let swap_chain_frame = frame_encoder.set_target(render_target.frame_target());
+    /// // ... draw the scene ...
+    /// frame_encoder.set_target(swap_chain_frame);
+    /// post_process.render(&mut frame_encoder);
+    /// ```
+    pub fn set_target(&mut self, target: FrameTarget) -> FrameTarget {
+        std::mem::replace(&mut self.frame, target)
+    }
+
+    /// Restricts drawing to `rect`, in logical pixels (design pixels when
+    /// letterboxed), until it's changed again. `None` allows drawing to the
+    /// whole frame target. The rect is clamped to the frame target, and a rect
+    /// without any area inside of it stops everything from drawing. The clip
+    /// rect is kept when switching targets with `set_target`, so it assumes
+    /// same sized targets.
+    pub fn set_clip_rect(&mut self, rect: Option<Rect>) {
+        self.clip_rect = rect;
+    }
+
+    /// The clip rect set with `set_clip_rect` and the letterbox viewport,
+    /// for drawables to apply to their render passes.
+    pub(crate) fn scissor_rect(&self) -> PassClip {
+        let (scale, origin) = match self.letterbox {
+            Some((letterbox, _)) => {
+                let viewport = letterbox.viewport(self.target_size);
+                (letterbox.scale(self.target_size), [viewport.x, viewport.y])
+            },
+            None => (self.scale_factor as f32, [0.0, 0.0]),
+        };
+
+        PassClip {
+            clip_rect: self.clip_rect,
+            scale,
+            origin,
+            target_size: self.target_size,
+            viewport: self.viewport(),
+        }
+    }
+
+    /// The letterbox viewport in physical pixels, `None` when not letterboxed.
+    pub fn viewport(&self) -> Option<Rect> {
+        self.letterbox.map(|(letterbox, _)| letterbox.viewport(self.target_size))
+    }
+
+    /// The size drawables should treat the frame as: the design size when
+    /// letterboxed, the frame target size otherwise.
+    pub fn design_size(&self) -> PhysicalSize<u32> {
+        match self.letterbox {
+            Some((letterbox, _)) => {
+                PhysicalSize::new(letterbox.design_size[0], letterbox.design_size[1])
+            },
+            None => self.size(),
+        }
+    }
+
+    /// Clears a new frame to `clear_color`, or to the bar color with the
+    /// viewport filled with `clear_color` when letterboxed.
+    fn clear_frame(&mut self, clear_color: Option<Color>) {
+        match self.letterbox {
+            Some((letterbox, letterbox_fill)) => {
+                self.clear(letterbox.bar_color);
+
+                if let (Some(color), Some(viewport)) = (clear_color, self.viewport()) {
+                    letterbox_fill.render(self, viewport, color);
+                }
+            },
+            None => {
+                if let Some(color) = clear_color {
+                    self.clear(color);
+                }
+            },
+        }
+    }
+
+    /// Queues `drawable` to be drawn in the layer named `layer` when the frame is
+    /// finished, after everything drawn directly. Queue up its shapes, tiles, etc.
+    /// before submitting it, as it stays borrowed until `finish`.
+    pub fn submit(&mut self, layer: &str, drawable: &'a mut dyn LayerDrawable) {
+        self.layers.submit(layer, drawable);
+    }
+
+    /// The camera for `LayerSpace::World` layers this frame.
+    pub fn set_world_camera(&mut self, camera: Camera2D) {
+        self.layers.set_world_camera(camera);
+    }
+
+    /// Groups the passes `record` adds under `name` in GPU captures, e.g. to tell
+    /// the scene apart from the HUD. Groups can be nested. Only recorded with the
+    /// `debug-markers` feature, without it `record` is just called.
+    pub fn debug_group<R>(&mut self, name: &str, record: impl FnOnce(&mut Self) -> R) -> R {
+        #[cfg(feature = "debug-markers")]
+        self.encoder.push_debug_group(name);
+        #[cfg(not(feature = "debug-markers"))]
+        let _ = name;
+
+        let result = record(self);
+
+        #[cfg(feature = "debug-markers")]
+        self.encoder.pop_debug_group();
+
+        result
+    }
+
+    /// Clears the whole frame target to `color`, ignoring the clip rect.
+    pub fn clear(&mut self, color: Color) {
+        self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("clear"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: self.frame.view(),
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(color.into()), store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+    }
+
+    /// What was drawn so far this frame. Drawables count their draws as they
+    /// record them, layers submitted with `submit` are only drawn in `finish`.
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// The size of the frame target in pixels.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.target_size[0], self.target_size[1])
+    }
+
+    /// The scale factor of the window this frame is for, see `GraphicsDevice::scale_factor`.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Differs for every frame begun on the device, including those of other
+    /// windows and render targets. Queue writes only execute when the frame is
+    /// finished, before any of its commands, so caches use this to avoid
+    /// overwriting data the frame already drew with.
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
     pub fn queue(&mut self) -> &mut Queue {
         &mut self.queue
     }
 
+    /// The device, for creating resources mid-frame (e.g. growing a `BufferPool`).
+    pub fn device(&self) -> &'a Device {
+        self.device
+    }
+
+    /// Uploads vertex or index data which changes every frame into a reused buffer,
+    /// instead of creating one per frame. Look the buffer up with `dynamic_buffers`.
+    pub fn write_dynamic(&mut self, data: &[u8]) -> PooledSlice {
+        self.stats.record_upload(data.len());
+        self.dynamic_buffers.write(self.device, self.queue, data)
+    }
+
+    pub fn dynamic_buffers(&self) -> &BufferPool {
+        &*self.dynamic_buffers
+    }
+
     // TODO(bschwind) - Maybe do this in a Drop impl
-    pub fn finish(self) {
+    pub fn finish(mut self) {
+        // Layers are drawn over the whole frame, whatever the last clip rect was.
+        self.clip_rect = None;
+        let layers = std::mem::replace(&mut self.layers, LayerQueues::new(&[]));
+        layers.flush(&mut self);
+
+        if let Some(gpu_profiler) = self.gpu_profiler.as_deref_mut() {
+            gpu_profiler.end(&mut self.encoder);
+        }
+
+        if let Some(frame_fences) = self.frame_fences.as_deref_mut() {
+            frame_fences.end(self.device, &mut self.encoder);
+        }
+
         self.queue.submit(Some(self.encoder.finish()));
+
+        if let Some(gpu_profiler) = self.gpu_profiler {
+            gpu_profiler.after_submit();
+        }
+
+        if let Some(frame_fences) = self.frame_fences {
+            frame_fences.after_submit();
+        }
+
+        if let Some(last_frame_stats) = self.last_frame_stats {
+            *last_frame_stats = self.stats;
+        }
+
+        if let Some(frame_deadline) = self.frame_deadline {
+            // Present before sleeping, so the sleep doesn't add latency.
+            drop(self.frame);
+            frame_clock::sleep_until(frame_deadline);
+        }
     }
 }
 
+/// Location 0 is `pos` at offset 0, location 1 is `uv` at offset 8.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct TexturedQuadVertex {
+    /// Clip space position.
     pos: [f32; 2],
+
+    /// Texture coordinates, with (0, 0) at the top left of the texture.
     uv: [f32; 2],
 }
 
+/// Depth testing for a `TexturedQuad` against a `DepthTarget`, see
+/// `TexturedQuad::with_depth`. All quads are at a depth of 0.5, so this is for
+/// layering coplanar quads like decals with a depth bias: with `LessEqual`, a
+/// quad with a negative `depth_bias` stays on top of unbiased ones drawn after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadDepth {
+    pub compare: wgpu::CompareFunction,
+
+    /// Constant depth offset, see `PipelineBuilder::depth_bias`.
+    pub depth_bias: i32,
+    pub depth_bias_slope_scale: f32,
+    pub depth_bias_clamp: f32,
+}
+
+impl QuadDepth {
+    fn bias(&self) -> wgpu::DepthBiasState {
+        wgpu::DepthBiasState {
+            constant: self.depth_bias,
+            slope_scale: self.depth_bias_slope_scale,
+            clamp: self.depth_bias_clamp,
+        }
+    }
+}
+
+impl Default for QuadDepth {
+    fn default() -> Self {
+        Self {
+            compare: wgpu::CompareFunction::LessEqual,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }
+    }
+}
+
+const IDENTITY_TRANSFORM: [[f32; 4]; 4] =
+    [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+/// A quad covering the frame target, or wherever its transform puts it. The
+/// bind group it's drawn with has a uniform buffer at binding 0 (unused by the
+/// built-in shader), the texture at binding 1 and a sampler at binding 2. The
+/// transform comes from a `TransformBufferPool` in bind group 1, or push
+/// constants.
 pub struct TexturedQuad {
     vertex_buf: Buffer,
-    index_buf: Buffer,
+    index_buf: IndexBuffer,
     // bind_group: BindGroup,
-    pipeline: RenderPipeline,
+    pipeline: Arc<RenderPipeline>,
+    depth: Option<QuadDepth>,
+
+    /// Holds just the identity transform, for `render`.
+    identity: TransformBufferPool,
 }
 
 impl TexturedQuad {
     pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        Self::with_uv_rect(graphics_device, Rect::new(0.0, 0.0, 1.0, 1.0))
+    }
+
+    /// A quad showing only part of its texture, e.g. `TextureAtlas::uv_rect`.
+    pub fn with_uv_rect(graphics_device: &GraphicsDevice, uv_rect: Rect) -> Self {
+        Self::with_culling(graphics_device, uv_rect, None)
+    }
+
+    /// A quad which culls `cull_mode` faces. Quads are drawn regardless of winding
+    /// by default, culling only makes sense when using them as 3D geometry.
+    pub fn with_culling(
+        graphics_device: &GraphicsDevice,
+        uv_rect: Rect,
+        cull_mode: Option<wgpu::Face>,
+    ) -> Self {
+        Self::create(graphics_device, uv_rect, cull_mode, None)
+    }
+
+    /// A quad which is depth tested against a `DepthTarget`, drawn with
+    /// `render_with_depth`.
+    pub fn with_depth(graphics_device: &GraphicsDevice, uv_rect: Rect, depth: QuadDepth) -> Self {
+        Self::create(graphics_device, uv_rect, None, Some(depth))
+    }
+
+    fn create(
+        graphics_device: &GraphicsDevice,
+        uv_rect: Rect,
+        cull_mode: Option<wgpu::Face>,
+        depth: Option<QuadDepth>,
+    ) -> Self {
+        let [u_min, v_min] = uv_rect.min();
+        let [u_max, v_max] = uv_rect.max();
+
         let vertex_data = vec![
-            TexturedQuadVertex { pos: [-1.0, -1.0], uv: [0.0, 1.0] },
-            TexturedQuadVertex { pos: [-1.0, 1.0], uv: [0.0, 0.0] },
-            TexturedQuadVertex { pos: [1.0, 1.0], uv: [1.0, 0.0] },
-            TexturedQuadVertex { pos: [1.0, -1.0], uv: [1.0, 1.0] },
+            TexturedQuadVertex { pos: [-1.0, -1.0], uv: [u_min, v_max] },
+            TexturedQuadVertex { pos: [-1.0, 1.0], uv: [u_min, v_min] },
+            TexturedQuadVertex { pos: [1.0, 1.0], uv: [u_max, v_min] },
+            TexturedQuadVertex { pos: [1.0, -1.0], uv: [u_max, v_max] },
         ];
 
-        let index_data = vec![0u16, 1, 3, 2];
+        let index_data = Indices::for_vertex_count(vertex_data.len(), &[0, 1, 3, 2]);
 
         let device = graphics_device.device();
 
@@ -141,12 +1419,38 @@ impl TexturedQuad {
             usage: wgpu::BufferUsage::VERTEX,
         });
 
-        let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&index_data),
-            usage: wgpu::BufferUsage::INDEX,
+        let index_buf = IndexBuffer::new(graphics_device, "Index Buffer", &index_data);
+
+        let mut identity = TransformBufferPool::new(graphics_device, 1);
+        identity.push(&IDENTITY_TRANSFORM);
+        identity.upload(graphics_device.queue());
+
+        // let format = wgpu::TextureFormat::Bgra8Unorm;
+        let format = graphics_device.color_format();
+        let key = PipelineKey {
+            shader: "textured_quad",
+            targets: vec![TargetKey::new(format, Some(wgpu::BlendState::REPLACE))],
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            cull_mode,
+            depth: depth.map(|depth| DepthKey::new(DEPTH_FORMAT, depth.compare, depth.bias())),
+            features: ShaderFeatures::NONE,
+        };
+
+        let pipeline = graphics_device.pipeline_cache().get_or_create(key, || {
+            Self::create_pipeline(device, format, cull_mode, depth, &identity)
         });
 
+        Self { vertex_buf, index_buf, pipeline, depth, identity }
+    }
+
+    /// `transforms` is any pool, for laying out the pipeline to fit its transforms.
+    fn create_pipeline(
+        device: &Device,
+        format: wgpu::TextureFormat,
+        cull_mode: Option<wgpu::Face>,
+        depth: Option<QuadDepth>,
+        transforms: &TransformBufferPool,
+    ) -> RenderPipeline {
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("mod"),
             entries: &[
@@ -179,10 +1483,13 @@ impl TexturedQuad {
             ],
         });
 
+        let mut bind_group_layouts = vec![&bind_group_layout];
+        bind_group_layouts.extend(transforms.bind_group_layouts());
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&bind_group_layout],
-            push_constant_ranges: &[],
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: transforms.push_constant_ranges(),
         });
 
         // let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -204,117 +1511,543 @@ impl TexturedQuad {
         //     label: None,
         // });
 
+        // The locations match the inputs of test.vert, and the offsets the
+        // fields of `TexturedQuadVertex`.
+        let attributes = &[
+            // Pos (vec2)
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 0,
+            },
+            // UV (vec2)
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                shader_location: 1,
+            },
+        ];
+
         let buffer_layout = wgpu::VertexBufferLayout {
-            // index_format: wgpu::IndexFormat::Uint16,
             array_stride: (std::mem::size_of::<TexturedQuadVertex>()) as wgpu::BufferAddress,
             step_mode: wgpu::InputStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![
-                // UV (vec2)
-                0 => Float32x2,
-                1 => Float32x2
-            ]
-            // attributes: &[wgpu::VertexBuffer {
-            //     attributes: &[
-            //         // Pos (vec2)
-            //         // wgpu::VertexAttribute {
-            //         //     format: wgpu::VertexFormat::Float2,
-            //         //     offset: 0,
-            //         //     shader_location: 0,
-            //         // },
-            //         // // UV (vec2)
-            //         // wgpu::VertexAttribute {
-            //         //     format: wgpu::VertexFormat::Float2,
-            //         //     offset: 2 * 4,
-            //         //     shader_location: 1,
-            //         // },
-            //         wgpu::vertex_attr_array![
-            //             0 => Float2,
-            //             1 => Float2
-            //         ]
-            //     ],
-            // }],
+            attributes,
         };
 
-        let vs_module = device.create_shader_module(&wgpu::include_spirv!(
-            "../../../resources/shaders/test.vert.spv"
-        ));
-        let fs_module = device.create_shader_module(&wgpu::include_spirv!(
-            "../../../resources/shaders/test.frag.spv"
-        ));
+        let vs_module = if transforms.uses_push_constants() {
+            device.create_shader_module(&include_shader!("test_push.vert"))
+        } else {
+            device.create_shader_module(&include_shader!("test.vert"))
+        };
+        let fs_module = device.create_shader_module(&include_shader!("test.frag"));
 
-        // let format = wgpu::TextureFormat::Bgra8Unorm;
-        let format = graphics_device.swap_chain_descriptor().format;
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("mod"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &vs_module,
-                entry_point: "main",
-                buffers: &[buffer_layout],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &fs_module,
-                entry_point: "main",
-                targets: &[wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState {
+        let vertex_buffers = &[buffer_layout];
+        let mut builder = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("mod")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, Some(wgpu::BlendState::REPLACE)))
+            .primitive(wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleStrip,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Front),
+                cull_mode,
                 ..Default::default()
-            },
+            });
 
-            // todo
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            // rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-            //     front_face: wgpu::FrontFace::Ccw,
-            //     cull_mode: wgpu::CullMode::Front,
-            //     ..Default::default()
-            // }),
-            // primitive_topology: wgpu::PrimitiveTopology::TriangleStrip,
-            // color_states: &[wgpu::ColorStateDescriptor {
-            //     format: graphics_device.swap_chain_descriptor().format,
-            //     color_blend: wgpu::BlendDescriptor::REPLACE,
-            //     alpha_blend: wgpu::BlendDescriptor::REPLACE,
-            //     write_mask: wgpu::ColorWrite::ALL,
-            // }],
-            // depth_stencil_state: None,
-            // vertex_state,
-            // sample_count: 1,
-            // sample_mask: !0,
-            // alpha_to_coverage_enabled: false,
-        });
+        if let Some(depth) = depth {
+            builder = builder.depth_test(DEPTH_FORMAT, depth.compare).depth_bias(
+                depth.depth_bias,
+                depth.depth_bias_slope_scale,
+                depth.depth_bias_clamp,
+            );
+        }
 
-        Self { vertex_buf, index_buf, pipeline }
+        builder.build(device)
     }
 
     pub fn render(&self, bind_group: &wgpu::BindGroup, frame_encoder: &mut FrameEncoder) {
-        let frame = &frame_encoder.frame;
+        if self.depth.is_some() {
+            println!("Trying to render a depth tested quad without a depth target");
+            return;
+        }
+
+        self.draw(&self.pipeline, bind_group, None, Some((&self.identity, 0)), frame_encoder);
+    }
+
+    /// Draws the quad with the transform at `offset` in `transforms` (see
+    /// `TransformBufferPool::push`), which maps the quad's corners from -1.0 to
+    /// 1.0 into clip space. Drawing many quads with transforms from the same pool
+    /// takes no bind group or buffer per quad.
+    pub fn render_transformed(
+        &self,
+        bind_group: &wgpu::BindGroup,
+        transforms: &TransformBufferPool,
+        offset: u32,
+        frame_encoder: &mut FrameEncoder,
+    ) {
+        if self.depth.is_some() {
+            println!("Trying to render a depth tested quad without a depth target");
+            return;
+        }
+
+        self.draw(&self.pipeline, bind_group, None, Some((transforms, offset)), frame_encoder);
+    }
+
+    /// Draws a quad from `with_depth`, testing against and writing to `depth_target`.
+    pub fn render_with_depth(
+        &self,
+        bind_group: &wgpu::BindGroup,
+        depth_target: &DepthTarget,
+        frame_encoder: &mut FrameEncoder,
+    ) {
+        if self.depth.is_none() {
+            println!("Trying to render a quad without depth testing with a depth target");
+            return;
+        }
+
+        let depth_view = Some(depth_target.view());
+        self.draw(&self.pipeline, bind_group, depth_view, Some((&self.identity, 0)), frame_encoder);
+    }
+
+    /// Draws the quad with the shader and resources of `material` instead
+    /// of the built-in textured shader. Nothing is drawn until the
+    /// material's resources have been set.
+    pub fn render_material(&self, material: &Material, frame_encoder: &mut FrameEncoder) {
+        match material.bind_group() {
+            Some(bind_group) => {
+                self.draw(material.pipeline(), bind_group, None, None, frame_encoder)
+            },
+            None => println!("Trying to render material {:?} without resources", material.label()),
+        }
+    }
+
+    fn draw(
+        &self,
+        pipeline: &RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+        depth_view: Option<&TextureView>,
+        transform: Option<(&TransformBufferPool, u32)>,
+        frame_encoder: &mut FrameEncoder,
+    ) {
+        let scissor_rect = frame_encoder.scissor_rect();
+        let view = frame_encoder.frame.view();
         let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
 
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: None,
+            label: Some("textured quad"),
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &frame.view,
+                view,
                 resolve_target: None,
-                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(CORNFLOWER_BLUE), store: true },
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
             }],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: depth_view.map(|view| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Load, store: true }),
+                    stencil_ops: None,
+                }
+            }),
         });
 
-        rpass.set_pipeline(&self.pipeline);
+        rpass.set_pipeline(pipeline);
         // rpass.set_bind_group(0, &self.bind_group, &[]);
         rpass.set_bind_group(0, bind_group, &[]);
-        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+        if let Some((transforms, offset)) = transform {
+            transforms.bind(&mut rpass, 1, offset);
+        }
+        self.index_buf.bind(&mut rpass);
         rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-        rpass.draw_indexed(0..4 as u32, 0, 0..1);
+
+        debug_marker(&mut rpass, "textured quad");
+        if apply_scissor_rect(&mut rpass, scissor_rect) {
+            rpass.draw_indexed(0..self.index_buf.count(), 0, 0..1);
+            stats.record_draw(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::sync::Weak;
+
+    /// A device rendering into render targets of `width` by `height`, or `None`
+    /// on machines without a GPU adapter, where tests needing one skip.
+    pub(crate) fn headless_device(width: u32, height: u32) -> Option<GraphicsDevice> {
+        let config = GraphicsDeviceConfig { clear_color: None, ..GraphicsDeviceConfig::default() };
+        let size = PhysicalSize::new(width, height);
+        let graphics_device = block_on(GraphicsDevice::new_headless(config, size));
+
+        if graphics_device.is_none() {
+            println!("No GPU adapter available, skipping the test");
+        }
+
+        graphics_device
+    }
+
+    /// A bind group for drawing `quad`, whose shader colors it by its UV coordinates.
+    fn quad_bind_group(
+        graphics_device: &GraphicsDevice,
+        quad: &TexturedQuad,
+        texture: &RenderTarget,
+    ) -> (BindGroup, Buffer) {
+        let device = graphics_device.device();
+        let uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Test uniforms"),
+            contents: &[0; 64],
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &quad.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(texture.sampler()),
+                },
+            ],
+            label: Some("Test quad"),
+        });
+
+        (bind_group, uniforms)
+    }
+
+    /// Draws a quad with `decal` depth and then a coplanar one with the default
+    /// depth over it, and returns the pixels of the frame.
+    fn render_decal(graphics_device: &mut GraphicsDevice, decal: QuadDepth) -> Vec<[u8; 4]> {
+        let target = RenderTarget::new(graphics_device);
+        let texture = RenderTarget::new(graphics_device);
+        let depth_target = DepthTarget::new(graphics_device);
+
+        // Colored (1, 0, 1) and (0, 0, 1) by their UV coordinates.
+        let decal = TexturedQuad::with_depth(graphics_device, Rect::new(1.0, 0.0, 0.0, 0.0), decal);
+        let base = QuadDepth::default();
+        let base = TexturedQuad::with_depth(graphics_device, Rect::new(0.0, 0.0, 0.0, 0.0), base);
+        let (bind_group, _uniforms) = quad_bind_group(graphics_device, &base, &texture);
+
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        depth_target.clear(&mut frame_encoder);
+        decal.render_with_depth(&bind_group, &depth_target, &mut frame_encoder);
+        base.render_with_depth(&bind_group, &depth_target, &mut frame_encoder);
+        frame_encoder.finish();
+
+        let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+        image.pixels().map(|pixel| pixel.0).collect()
+    }
+
+    /// Scales a quad to a quarter of the frame, centered on `x`, `y` in clip space.
+    fn quarter(x: f32, y: f32) -> [[f32; 4]; 4] {
+        [[0.5, 0.0, 0.0, 0.0], [0.0, 0.5, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [x, y, 0.0, 1.0]]
+    }
+
+    #[test]
+    fn quad_covers_the_frame_regardless_of_winding() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let texture = RenderTarget::new(&graphics_device);
+        let quad = TexturedQuad::with_uv_rect(&graphics_device, Rect::new(1.0, 0.0, 0.0, 0.0));
+        let (bind_group, _uniforms) = quad_bind_group(&graphics_device, &quad, &texture);
+
+        // Mirroring the quad flips the winding of its triangles.
+        let mut transforms = TransformBufferPool::new(&graphics_device, 1);
+        let mirrored = [
+            [-1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        let mirrored = transforms.push(&mirrored).unwrap();
+        transforms.upload(graphics_device.queue());
+
+        for mirror in &[false, true] {
+            let target = RenderTarget::new(&graphics_device);
+            let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+            frame_encoder.clear(Color::BLACK);
+            if *mirror {
+                quad.render_transformed(&bind_group, &transforms, mirrored, &mut frame_encoder);
+            } else {
+                quad.render(&bind_group, &mut frame_encoder);
+            }
+            frame_encoder.finish();
+
+            let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+            for (x, y, pixel) in image.enumerate_pixels() {
+                assert_eq!(pixel.0, [255, 0, 255, 255], "Pixel at {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn quads_are_drawn_with_their_own_transforms_from_one_pool() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let target = RenderTarget::new(&graphics_device);
+        let texture = RenderTarget::new(&graphics_device);
+        let quad = TexturedQuad::with_uv_rect(&graphics_device, Rect::new(1.0, 0.0, 0.0, 0.0));
+        let (bind_group, _uniforms) = quad_bind_group(&graphics_device, &quad, &texture);
+
+        let mut transforms = TransformBufferPool::new(&graphics_device, 2);
+        let top_left = transforms.push(&quarter(-0.5, 0.5)).unwrap();
+        let bottom_right = transforms.push(&quarter(0.5, -0.5)).unwrap();
+        transforms.upload(graphics_device.queue());
+
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        quad.render_transformed(&bind_group, &transforms, top_left, &mut frame_encoder);
+        quad.render_transformed(&bind_group, &transforms, bottom_right, &mut frame_encoder);
+        frame_encoder.finish();
+
+        let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if (x < 2) == (y < 2) { [255, 0, 255, 255] } else { [0, 0, 0, 255] };
+            assert_eq!(pixel.0, expected, "Pixel at {}, {}", x, y);
+        }
+    }
+
+    /// Samples the quad's texture at its UV coordinates.
+    const SAMPLED_QUAD: &str = "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0)]] uv: vec2<f32>;
+        };
+
+        [[stage(vertex)]]
+        fn vs_main([[location(0)]] pos: vec2<f32>, [[location(1)]] uv: vec2<f32>) -> VertexOutput {
+            var out: VertexOutput;
+            out.position = vec4<f32>(pos, 0.0, 1.0);
+            out.uv = uv;
+            return out;
+        }
+
+        [[group(0), binding(0)]] var quad_texture: texture_2d<f32>;
+        [[group(0), binding(1)]] var quad_sampler: sampler;
+
+        [[stage(fragment)]]
+        fn fs_main(input: VertexOutput) -> [[location(0)]] vec4<f32> {
+            return textureSample(quad_texture, quad_sampler, input.uv);
+        }
+    ";
+
+    #[test]
+    fn quad_samples_its_texture_upright() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        // A different color in each quadrant, so flips and swapped axes all show.
+        let quadrant_color = |x: u32, y: u32| match (x < 2, y < 2) {
+            (true, true) => [255, 0, 0, 255],
+            (false, true) => [0, 255, 0, 255],
+            (true, false) => [0, 0, 255, 255],
+            (false, false) => [255, 255, 255, 255],
+        };
+        let pixels: Vec<u8> =
+            (0..16).flat_map(|i: u32| quadrant_color(i % 4, i / 4).to_vec()).collect();
+        let texture = Texture::from_rgba_with_config(
+            &graphics_device,
+            4,
+            4,
+            &pixels,
+            TextureConfig::pixel_art(),
+        );
+
+        let bindings = [MaterialBinding::Texture, MaterialBinding::Sampler];
+        let mut material = block_on(Material::new(
+            &graphics_device,
+            "Sampled quad",
+            SAMPLED_QUAD,
+            &bindings,
+            None,
+        ))
+        .unwrap();
+        let resources = [
+            MaterialResource::Texture(texture.view()),
+            MaterialResource::Sampler(texture.sampler()),
+        ];
+        material.set_resources(&graphics_device, &resources).unwrap();
+
+        let target = RenderTarget::new(&graphics_device);
+        let quad = TexturedQuad::new(&graphics_device);
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        quad.render_material(&material, &mut frame_encoder);
+        frame_encoder.finish();
+
+        let image = block_on(graphics_device.capture_frame(&target)).unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0, quadrant_color(x, y), "Pixel at {}, {}", x, y);
+        }
+    }
+
+    /// Creates a device and draws a quad into a frame, which is submitted but not
+    /// waited on. Returns a handle to the quad's cached pipeline.
+    fn draw_and_drop_device() -> Option<Weak<RenderPipeline>> {
+        let mut graphics_device = headless_device(4, 4)?;
+
+        let target = RenderTarget::new(&graphics_device);
+        let texture = RenderTarget::new(&graphics_device);
+        let quad = TexturedQuad::new(&graphics_device);
+        let (bind_group, _uniforms) = quad_bind_group(&graphics_device, &quad, &texture);
+
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        quad.render(&bind_group, &mut frame_encoder);
+        frame_encoder.finish();
+
+        Some(Arc::downgrade(&quad.pipeline))
+    }
+
+    /// Drops each device right after submitting a frame, so `Drop` has to wait
+    /// for the frame. The pipeline cache and drawables have to let go of what
+    /// they share.
+    #[test]
+    fn devices_can_be_created_and_dropped_repeatedly() {
+        for _ in 0..5 {
+            let pipeline = match draw_and_drop_device() {
+                Some(pipeline) => pipeline,
+                None => return,
+            };
+
+            assert!(pipeline.upgrade().is_none(), "The pipeline outlived its device");
+        }
+    }
+
+    /// Like `devices_can_be_created_and_dropped_repeatedly`, but checks that
+    /// every tracked resource is gone as well.
+    #[cfg(feature = "resource-tracking")]
+    #[test]
+    fn dropped_devices_leave_no_tracked_resources() {
+        let stats_before = ResourceTracker::stats();
+
+        for _ in 0..5 {
+            if draw_and_drop_device().is_none() {
+                return;
+            }
+        }
+
+        assert_eq!(ResourceTracker::stats(), stats_before);
+    }
+
+    #[test]
+    fn capturing_an_hdr_target_is_an_error() {
+        let graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let target = RenderTarget::with_format(&graphics_device, HDR_FORMAT);
+        let capture = block_on(graphics_device.capture_frame(&target));
+
+        assert!(matches!(capture, Err(CaptureError::UnsupportedFormat(HDR_FORMAT))));
+    }
+
+    #[test]
+    fn depth_biased_quad_stays_on_top_of_coplanar_quad() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let biased = QuadDepth { depth_bias: -8, ..QuadDepth::default() };
+        for _ in 0..3 {
+            let pixels = render_decal(&mut graphics_device, biased);
+            assert!(pixels.iter().all(|pixel| *pixel == [255, 0, 255, 255]), "{:?}", pixels);
+        }
+
+        // Without the bias, the quad drawn last wins the equal depth test.
+        let pixels = render_decal(&mut graphics_device, QuadDepth::default());
+        assert!(pixels.iter().all(|pixel| *pixel == [0, 0, 255, 255]), "{:?}", pixels);
+    }
+
+    #[test]
+    fn safe_area_shrinks_frame_by_insets() {
+        let insets = SafeAreaInsets { top: 40.0, right: 10.0, bottom: 20.0, left: 30.0 };
+        let frame = Rect::new(0.0, 0.0, 800.0, 600.0);
+
+        assert_eq!(insets.shrink(frame), Rect::new(30.0, 40.0, 760.0, 540.0));
+    }
+
+    #[test]
+    fn safe_area_is_empty_when_insets_cover_frame() {
+        let insets = SafeAreaInsets { top: 400.0, right: 500.0, bottom: 400.0, left: 500.0 };
+        let safe_area = insets.shrink(Rect::new(0.0, 0.0, 800.0, 600.0));
+
+        assert_eq!(safe_area.w, 0.0);
+        assert_eq!(safe_area.h, 0.0);
+    }
+
+    #[test]
+    fn msaa_sample_counts_are_rounded_up_to_a_supported_one() {
+        let counts: Vec<_> =
+            [1, 2, 4, 8].iter().map(|&n| supported_sample_count(n, &MSAA_SAMPLE_COUNTS)).collect();
+        assert_eq!(counts, [1, 4, 4, 4]);
+
+        assert_eq!(supported_sample_count(0, &MSAA_SAMPLE_COUNTS), 1);
+        assert_eq!(supported_sample_count(2, &[1, 2, 4, 8]), 2);
+        assert_eq!(supported_sample_count(8, &[1, 2, 4, 8]), 8);
+        assert_eq!(supported_sample_count(16, &[1, 2, 4, 8]), 8);
+    }
+
+    #[test]
+    fn frames_are_paced_unless_presenting_with_fifo() {
+        let start = Instant::now();
+        let target = Some(Duration::from_millis(16));
+
+        for &mode in &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate] {
+            assert_eq!(frame_deadline(mode, target, Some(start)), Some(start + target.unwrap()));
+            assert_eq!(frame_deadline(mode, None, Some(start)), None);
+            assert_eq!(frame_deadline(mode, target, None), None);
+        }
+        assert_eq!(frame_deadline(wgpu::PresentMode::Fifo, target, Some(start)), None);
+    }
+
+    fn pass_clip(clip_rect: Rect, scale: f32, origin: [f32; 2]) -> PassClip {
+        PassClip {
+            clip_rect: Some(clip_rect),
+            scale,
+            origin,
+            target_size: [800, 600],
+            viewport: None,
+        }
+    }
+
+    #[test]
+    fn clip_rect_is_scaled_to_physical_pixels() {
+        let clip = pass_clip(Rect::new(10.0, 20.0, 100.0, 50.0), 2.0, [0.0, 0.0]);
+
+        assert_eq!(
+            clip.scissor_rect(),
+            Some(ScissorRect { x: 20, y: 40, width: 200, height: 100 })
+        );
+    }
+
+    #[test]
+    fn clip_rect_is_offset_into_letterbox_and_clamped() {
+        let clip = pass_clip(Rect::new(-10.0, 0.0, 1000.0, 100.0), 1.5, [100.0, 0.0]);
+
+        assert_eq!(clip.scissor_rect(), Some(ScissorRect { x: 85, y: 0, width: 715, height: 150 }));
+    }
+
+    #[test]
+    fn textured_quad_vertex_matches_attribute_offsets() {
+        let vertex = TexturedQuadVertex::zeroed();
+
+        assert_eq!(std::mem::size_of::<TexturedQuadVertex>(), 16);
+        assert_eq!(bytemuck::offset_of!(vertex, TexturedQuadVertex, pos), 0);
+        assert_eq!(bytemuck::offset_of!(vertex, TexturedQuadVertex, uv), 8);
     }
 }
@@ -0,0 +1,287 @@
+use crate::graphics::{
+    apply_scissor_rect, camera::screen_projection_matrix, color_target, debug_marker, FrameEncoder,
+    GraphicsDevice, PipelineBuilder, Rect,
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer, RenderPipeline, TextureView};
+use winit::dpi::PhysicalSize;
+
+const MAX_PANEL_COUNT: usize = 1024;
+const VERTICES_PER_PANEL: usize = 9 * 6;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct NineSliceVertex {
+    /// Position in screen space pixels.
+    pos: [f32; 2],
+    uv: [f32; 2],
+}
+
+/// Widths in texture pixels of the borders of a nine-slice texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NineSliceInsets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+/// Draws a bordered texture stretched to any size without distorting its
+/// corners. The texture is cut into a 3x3 grid by the insets: corners keep
+/// their size, edges stretch along one axis and the center stretches both ways.
+/// Panels are queued with `draw` and all of them are drawn with a single
+/// draw call in `render`.
+pub struct NineSlice {
+    texture_size: [f32; 2],
+    insets: NineSliceInsets,
+    vertices: Vec<NineSliceVertex>,
+    vertex_buffer: Buffer,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl NineSlice {
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        texture_view: &TextureView,
+        texture_size: [u32; 2],
+        insets: NineSliceInsets,
+    ) -> Self {
+        let device = graphics_device.device();
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Nine Slice Vertex Buffer"),
+            size: (MAX_PANEL_COUNT * VERTICES_PER_PANEL * std::mem::size_of::<NineSliceVertex>())
+                as u64,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Nine Slice Uniform Buffer"),
+            size: 4 * 4 * 4,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("nine slice"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64), // Size of a 4x4 f32 matrix
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { filtering: true, comparison: false },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(64),
+                    },
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: (std::mem::size_of::<NineSliceVertex>()) as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0 => Float32x2,
+                1 => Float32x2,
+            ],
+        }];
+
+        let vs_module = device.create_shader_module(&include_shader!("nine_slice.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("nine_slice.frag"));
+
+        let format = graphics_device.color_format();
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("nine slice")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING)))
+            .topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device);
+
+        Self {
+            texture_size: [texture_size[0] as f32, texture_size[1] as f32],
+            insets,
+            vertices: Vec::new(),
+            vertex_buffer,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Queues a panel covering `rect`, in screen space pixels.
+    /// If `rect` is smaller than the borders, they shrink to fit.
+    pub fn draw(&mut self, rect: Rect) {
+        let insets = self.insets;
+        let [texture_width, texture_height] = self.texture_size;
+
+        let horizontal_scale = (rect.w / (insets.left + insets.right)).min(1.0);
+        let vertical_scale = (rect.h / (insets.top + insets.bottom)).min(1.0);
+
+        let xs = [
+            rect.x,
+            rect.x + insets.left * horizontal_scale,
+            rect.x + rect.w - insets.right * horizontal_scale,
+            rect.x + rect.w,
+        ];
+        let ys = [
+            rect.y,
+            rect.y + insets.top * vertical_scale,
+            rect.y + rect.h - insets.bottom * vertical_scale,
+            rect.y + rect.h,
+        ];
+
+        let us = [0.0, insets.left / texture_width, 1.0 - insets.right / texture_width, 1.0];
+        let vs = [0.0, insets.top / texture_height, 1.0 - insets.bottom / texture_height, 1.0];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let vertex = |c: usize, r: usize| NineSliceVertex {
+                    pos: [xs[c], ys[r]],
+                    uv: [us[c], vs[r]],
+                };
+
+                let top_left = vertex(col, row);
+                let bottom_left = vertex(col, row + 1);
+                let bottom_right = vertex(col + 1, row + 1);
+                let top_right = vertex(col + 1, row);
+
+                self.vertices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    bottom_right,
+                    top_left,
+                    bottom_right,
+                    top_right,
+                ]);
+            }
+        }
+    }
+
+    /// Draws all queued panels with a single draw call, and clears the queue.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder, window_size: PhysicalSize<u32>) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let max_vertices = MAX_PANEL_COUNT * VERTICES_PER_PANEL;
+        if self.vertices.len() > max_vertices {
+            println!(
+                "Trying to render more nine slice panels than the maximum. Max = {}, attempted render count = {}",
+                MAX_PANEL_COUNT,
+                self.vertices.len() / VERTICES_PER_PANEL
+            );
+            self.vertices.clear();
+            return;
+        }
+
+        let queue = frame_encoder.queue();
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+
+        let proj = screen_projection_matrix(window_size.width as f32, window_size.height as f32);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&proj));
+
+        frame_encoder.stats.record_upload(std::mem::size_of_val(&self.vertices[..]));
+
+        let scissor_rect = frame_encoder.scissor_rect();
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("nine slice"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let vertex_bytes = (self.vertices.len() * std::mem::size_of::<NineSliceVertex>()) as u64;
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..vertex_bytes));
+
+        debug_marker(&mut rpass, "nine slice");
+        if apply_scissor_rect(&mut rpass, scissor_rect) {
+            rpass.draw(0..self.vertices.len() as u32, 0..1);
+            stats.record_draw(self.vertices.len() as u32 / 3);
+        }
+
+        self.vertices.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nine_slice_vertex_matches_attribute_offsets() {
+        let vertex = NineSliceVertex::zeroed();
+
+        assert_eq!(std::mem::size_of::<NineSliceVertex>(), 16);
+        assert_eq!(bytemuck::offset_of!(vertex, NineSliceVertex, pos), 0);
+        assert_eq!(bytemuck::offset_of!(vertex, NineSliceVertex, uv), 8);
+    }
+}
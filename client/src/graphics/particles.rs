@@ -0,0 +1,393 @@
+use crate::graphics::{
+    apply_scissor_rect, color_target, debug_marker, rng, Camera2D, Color, ComputePass,
+    FrameEncoder, GraphicsDevice, PipelineBuilder, StorageAccess, StorageBuffer,
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer, RenderPipeline};
+
+/// Must match `local_size_x` in particles.comp.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Source color times its alpha, added on top of what's already drawn.
+const ADDITIVE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+/// Laid out like `Particle` in particles.comp, following std430 rules.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    color: [f32; 4],
+
+    /// Seconds left to live, dead at 0.
+    life: f32,
+    lifetime: f32,
+    size: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct ParticleParams {
+    gravity: [f32; 2],
+    dt: f32,
+    particle_count: u32,
+}
+
+/// How `ParticleSystem::emit` spawns particles. Each particle gets a random
+/// direction within `spread` radians of `direction`, and a speed and lifetime
+/// picked evenly between the min and max, drawn from the shared `rng`.
+#[derive(Debug, Clone, Copy)]
+pub struct EmitConfig {
+    /// Where particles spawn, in world space.
+    pub position: [f32; 2],
+
+    /// The center of the emitted directions, in radians, 0 pointing right.
+    pub direction: f32,
+    pub spread: f32,
+
+    pub min_speed: f32,
+    pub max_speed: f32,
+
+    /// In seconds.
+    pub min_lifetime: f32,
+    pub max_lifetime: f32,
+
+    /// Particles fade out from this color over their lifetime.
+    pub color: Color,
+
+    /// Width and height of each particle in world units.
+    pub size: f32,
+}
+
+impl Default for EmitConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            direction: 0.0,
+            spread: std::f32::consts::PI,
+            min_speed: 50.0,
+            max_speed: 100.0,
+            min_lifetime: 0.5,
+            max_lifetime: 1.0,
+            color: Color::WHITE,
+            size: 8.0,
+        }
+    }
+}
+
+/// Particles simulated on the GPU with a compute shader and drawn as
+/// additive blended, instanced quads. Emitted particles are queued and
+/// uploaded by `update_and_draw`, into the slots of particles which died.
+/// Lifetimes are tracked on the CPU as well, so dead slots are found
+/// without reading anything back from the GPU. When every slot is alive,
+/// further particles are dropped.
+pub struct ParticleSystem {
+    capacity: usize,
+    gravity: [f32; 2],
+
+    /// Seconds left to live of the particle in each slot.
+    lives: Vec<f32>,
+
+    /// Where to start looking for a dead slot.
+    next_slot: usize,
+    emitted: Vec<(usize, Particle)>,
+
+    params_buffer: StorageBuffer,
+    particle_buffer: StorageBuffer,
+    compute_pass: ComputePass,
+    compute_bind_group: BindGroup,
+
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl ParticleSystem {
+    /// `capacity` is the most particles which can be alive at once.
+    pub fn new(graphics_device: &GraphicsDevice, capacity: usize) -> Self {
+        let device = graphics_device.device();
+        let capacity = capacity.max(1);
+
+        let params_buffer = StorageBuffer::new(
+            graphics_device,
+            "Particle Params Buffer",
+            std::mem::size_of::<ParticleParams>() as u64,
+            wgpu::BufferUsage::empty(),
+        );
+        let particle_buffer = StorageBuffer::new(
+            graphics_device,
+            "Particle Buffer",
+            (capacity * std::mem::size_of::<Particle>()) as u64,
+            wgpu::BufferUsage::VERTEX,
+        );
+
+        let compute_pass = ComputePass::new(
+            graphics_device,
+            "particles",
+            &include_shader!("particles.comp"),
+            &[StorageAccess::ReadOnly, StorageAccess::ReadWrite],
+        );
+        let compute_bind_group =
+            compute_pass.create_bind_group(graphics_device, &[&params_buffer, &particle_buffer]);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Uniform Buffer"),
+            size: 4 * 4 * 4,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64), // Size of a 4x4 f32 matrix
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(64),
+                },
+            }],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // The instances are read straight out of the storage buffer, skipping the
+        // velocity and padding the vertex shader doesn't need.
+        let attributes = &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 16,
+                shader_location: 1,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 32,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 36,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 40,
+                shader_location: 4,
+            },
+        ];
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: (std::mem::size_of::<Particle>()) as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes,
+        }];
+
+        let vs_module = device.create_shader_module(&include_shader!("particle.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("particle.frag"));
+
+        let format = graphics_device.color_format();
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("particles")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, Some(ADDITIVE_BLENDING)))
+            .topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .build(device);
+
+        Self {
+            capacity,
+            gravity: [0.0, 0.0],
+            lives: vec![0.0; capacity],
+            next_slot: 0,
+            emitted: Vec::new(),
+            params_buffer,
+            particle_buffer,
+            compute_pass,
+            compute_bind_group,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Acceleration applied to every particle, in world units per second squared.
+    pub fn set_gravity(&mut self, gravity: [f32; 2]) {
+        self.gravity = gravity;
+    }
+
+    /// How many particles are alive, including ones emitted since the last frame.
+    pub fn live_count(&self) -> usize {
+        self.lives.iter().filter(|life| **life > 0.0).count()
+    }
+
+    /// Queues `count` new particles, which start moving on the next `update_and_draw`.
+    pub fn emit(&mut self, count: usize, config: EmitConfig) {
+        for _ in 0..count {
+            let slot = match self.find_dead_slot() {
+                Some(slot) => slot,
+                None => {
+                    println!(
+                        "Trying to emit more particles than the capacity of {}",
+                        self.capacity
+                    );
+                    return;
+                },
+            };
+
+            let angle = config.direction + rng::range(-config.spread, config.spread);
+            let speed = rng::range(config.min_speed, config.max_speed);
+            let lifetime = rng::range(config.min_lifetime, config.max_lifetime);
+
+            self.lives[slot] = lifetime;
+            self.emitted.push((
+                slot,
+                Particle {
+                    position: config.position,
+                    velocity: [angle.cos() * speed, angle.sin() * speed],
+                    color: config.color.to_f32_array(),
+                    life: lifetime,
+                    lifetime,
+                    size: config.size,
+                    _padding: 0.0,
+                },
+            ));
+        }
+    }
+
+    fn find_dead_slot(&mut self) -> Option<usize> {
+        let slot = (0..self.capacity)
+            .map(|i| (self.next_slot + i) % self.capacity)
+            .find(|slot| self.lives[*slot] <= 0.0)?;
+
+        self.next_slot = (slot + 1) % self.capacity;
+        Some(slot)
+    }
+
+    /// Uploads emitted particles, moves every particle forward by `dt`
+    /// seconds on the GPU, and draws the live ones with `camera`.
+    pub fn update_and_draw(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        dt: f32,
+        camera: &Camera2D,
+    ) {
+        let particle_size = std::mem::size_of::<Particle>() as u64;
+        let queue = frame_encoder.queue();
+
+        for (slot, particle) in self.emitted.drain(..) {
+            self.particle_buffer.write(
+                queue,
+                slot as u64 * particle_size,
+                bytemuck::bytes_of(&particle),
+            );
+        }
+
+        // Only dispatch and draw up to the last live particle.
+        let particle_count = match self.lives.iter().rposition(|life| *life > 0.0) {
+            Some(last_live) => last_live + 1,
+            None => return,
+        };
+
+        for life in &mut self.lives {
+            *life -= dt;
+        }
+
+        let params =
+            ParticleParams { gravity: self.gravity, dt, particle_count: particle_count as u32 };
+        self.params_buffer.write(queue, 0, bytemuck::bytes_of(&params));
+
+        let window_size = frame_encoder.design_size();
+        let view_proj =
+            camera.view_projection_matrix(window_size.width as f32, window_size.height as f32);
+        frame_encoder.queue().write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&view_proj),
+        );
+
+        let workgroups = (particle_count as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        self.compute_pass.dispatch(frame_encoder, &self.compute_bind_group, [workgroups, 1, 1]);
+
+        let scissor_rect = frame_encoder.scissor_rect();
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("particles"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(
+            0,
+            self.particle_buffer.buffer().slice(..particle_count as u64 * particle_size),
+        );
+
+        debug_marker(&mut rpass, "particles");
+        if apply_scissor_rect(&mut rpass, scissor_rect) {
+            rpass.draw(0..4, 0..particle_count as u32);
+            stats.record_draw(2 * particle_count as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The render pipeline reads the instances with hardcoded offsets, and
+    /// particles.comp expects the std430 layout.
+    #[test]
+    fn particle_matches_attribute_offsets() {
+        let particle = Particle::zeroed();
+
+        assert_eq!(std::mem::size_of::<Particle>(), 48);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, position), 0);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, velocity), 8);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, color), 16);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, life), 32);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, lifetime), 36);
+        assert_eq!(bytemuck::offset_of!(particle, Particle, size), 40);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::graphics::{
+    apply_scissor_rect, color_target, debug_marker, Camera2D, FrameEncoder, GraphicsDevice,
+    PipelineBuilder, Rect, RenderTarget,
+};
+use bytemuck::{Pod, Zeroable};
+use std::num::NonZeroU32;
+use wgpu::{BindGroup, Buffer, RenderPipeline, TextureFormat};
+use winit::dpi::PhysicalSize;
+
+/// The format of picking targets, one object id per texel.
+pub const PICKING_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+const MAX_VERTEX_COUNT: usize = 60_000;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct PickingVertex {
+    /// Position in world space.
+    pos: [f32; 2],
+    id: u32,
+}
+
+/// Draws object ids into a picking target, a `RenderTarget` created with
+/// `RenderTarget::with_format(graphics_device, PICKING_FORMAT)`, so the
+/// object under the cursor can be found with `GraphicsDevice::pick`.
+/// Queue the clickable area of each object with `draw_rect` using the same
+/// camera as the visible scene, then `render` with the picking target set
+/// on the `FrameEncoder`. Id 0 means no object, so don't use it for one.
+pub struct PickingRenderer {
+    vertices: Vec<PickingVertex>,
+    vertex_buffer: Buffer,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl PickingRenderer {
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        let device = graphics_device.device();
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Vertex Buffer"),
+            size: (MAX_VERTEX_COUNT * std::mem::size_of::<PickingVertex>()) as u64,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking Uniform Buffer"),
+            size: 4 * 4 * 4,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("picking"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64), // Size of a 4x4 f32 matrix
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(64),
+                },
+            }],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: (std::mem::size_of::<PickingVertex>()) as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0 => Float32x2,
+                1 => Uint32,
+            ],
+        }];
+
+        let vs_module = device.create_shader_module(&include_shader!("picking.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("picking.frag"));
+
+        // Integer targets can't be blended, later ids simply replace earlier ones.
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("picking")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(PICKING_FORMAT, None))
+            .topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device);
+
+        Self { vertices: Vec::new(), vertex_buffer, uniform_buffer, bind_group, pipeline }
+    }
+
+    /// Queues `rect`, in world space, as belonging to the object `id`.
+    /// Rects drawn later are on top.
+    pub fn draw_rect(&mut self, rect: Rect, id: u32) {
+        let ([left, top], [right, bottom]) = (rect.min(), rect.max());
+        let corners = [[left, top], [right, top], [right, bottom], [left, bottom]];
+
+        for i in &[0, 1, 2, 0, 2, 3] {
+            self.vertices.push(PickingVertex { pos: corners[*i], id });
+        }
+    }
+
+    /// Clears the current frame target to id 0, draws all queued rects, and
+    /// clears the queue. `window_size` is the size of the visible frame.
+    pub fn render(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        if self.vertices.len() > MAX_VERTEX_COUNT {
+            println!(
+                "Trying to render more picking vertices than the maximum. Max = {}, attempted render count = {}",
+                MAX_VERTEX_COUNT,
+                self.vertices.len()
+            );
+            self.vertices.truncate(MAX_VERTEX_COUNT);
+        }
+
+        let queue = frame_encoder.queue();
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+
+        let view_proj =
+            camera.view_projection_matrix(window_size.width as f32, window_size.height as f32);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&view_proj));
+
+        frame_encoder.stats.record_upload(std::mem::size_of_val(&self.vertices[..]));
+
+        let scissor_rect = frame_encoder.scissor_rect();
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("picking"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let vertex_bytes = (self.vertices.len() * std::mem::size_of::<PickingVertex>()) as u64;
+
+        if !self.vertices.is_empty() {
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..vertex_bytes));
+
+            debug_marker(&mut rpass, "picking");
+            if apply_scissor_rect(&mut rpass, scissor_rect) {
+                rpass.draw(0..self.vertices.len() as u32, 0..1);
+                stats.record_draw(self.vertices.len() as u32 / 3);
+            }
+        }
+
+        self.vertices.clear();
+    }
+}
+
+impl GraphicsDevice {
+    /// Reads the object id at `point` from `target`, a picking target drawn with a
+    /// `PickingRenderer`. `point` is in logical pixels, like
+    /// `InputState::logical_mouse_position`. Returns `None` when there's no object
+    /// there, or the point is outside of the target.
+    ///
+    /// Like `capture_frame`, this blocks on the GPU natively and resolves once the
+    /// browser has mapped the buffer on the web.
+    pub async fn pick(&self, target: &RenderTarget, point: [f32; 2]) -> Option<u32> {
+        if target.format() != PICKING_FORMAT {
+            println!("Trying to pick from a render target with format {:?}", target.format());
+            return None;
+        }
+
+        let [x, y] = self.logical_to_physical(point);
+        let (width, height) = target.size();
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return None;
+        }
+
+        // A single row still has to be padded to 256 bytes.
+        let bytes_per_row = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Picking buffer"),
+            size: bytes_per_row as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("pick") });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: target.texture(),
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: x as u32, y: y as u32, z: 0 },
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let map = slice.map_async(wgpu::MapMode::Read);
+
+        // Mapping only completes while the device is polled.
+        self.device.poll(wgpu::Maintain::Wait);
+
+        if let Err(e) = map.await {
+            println!("Failed to map the picking buffer: {:?}", e);
+            return None;
+        }
+
+        let id = {
+            let data = slice.get_mapped_range();
+            u32::from_ne_bytes([data[0], data[1], data[2], data[3]])
+        };
+
+        buffer.unmap();
+
+        if id == 0 {
+            None
+        } else {
+            Some(id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picking_vertex_matches_attribute_offsets() {
+        let vertex = PickingVertex::zeroed();
+
+        assert_eq!(std::mem::size_of::<PickingVertex>(), 12);
+        assert_eq!(bytemuck::offset_of!(vertex, PickingVertex, pos), 0);
+        assert_eq!(bytemuck::offset_of!(vertex, PickingVertex, id), 8);
+    }
+}
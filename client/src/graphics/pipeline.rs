@@ -0,0 +1,462 @@
+use crate::graphics::{
+    resource_tracker::{ResourceKind, Tracked},
+    shader::{capture_validation_errors, ShaderError, ShaderFeatures},
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, ColorTargetState, ColorWrite,
+    CompareFunction, DepthBiasState, DepthStencilState, Device, Face, MultisampleState,
+    PipelineLayout, PrimitiveState, PrimitiveTopology, RenderPipeline, ShaderModule, StencilState,
+    TextureFormat, VertexBufferLayout,
+};
+
+/// Blending for colors which are already multiplied by their alpha, like
+/// textures loaded with `TextureConfig::premultiply_alpha`. Use it only with
+/// premultiplied colors, and `BlendState::ALPHA_BLENDING` only with straight
+/// ones. Mixing them up either darkens edges or makes them too bright.
+pub const PREMULTIPLIED_ALPHA_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::OneMinusSrcAlpha,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::OneMinusSrcAlpha,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// A color target which blends with `blend` and writes all channels.
+pub fn color_target(format: TextureFormat, blend: Option<BlendState>) -> ColorTargetState {
+    ColorTargetState { format, blend, write_mask: ColorWrite::ALL }
+}
+
+/// Builds render pipelines with the defaults used throughout the graphics
+/// module (`main` entry points, no depth buffer, no multisampling).
+/// Each color target carries its own blend state and write mask, so
+/// pipelines for multiple render targets can e.g. alpha blend into the
+/// first target while writing raw values into the second.
+pub struct PipelineBuilder<'a> {
+    label: Option<&'a str>,
+    layout: &'a PipelineLayout,
+    vertex_module: &'a ShaderModule,
+    fragment_module: &'a ShaderModule,
+    vertex_entry_point: &'a str,
+    fragment_entry_point: &'a str,
+    vertex_buffers: &'a [VertexBufferLayout<'a>],
+    color_targets: Vec<ColorTargetState>,
+    primitive: PrimitiveState,
+    depth_stencil: Option<DepthStencilState>,
+    depth_bias: Option<DepthBiasState>,
+    multisample: MultisampleState,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    pub fn new(
+        layout: &'a PipelineLayout,
+        vertex_module: &'a ShaderModule,
+        fragment_module: &'a ShaderModule,
+    ) -> Self {
+        Self {
+            label: None,
+            layout,
+            vertex_module,
+            fragment_module,
+            vertex_entry_point: "main",
+            fragment_entry_point: "main",
+            vertex_buffers: &[],
+            color_targets: Vec::new(),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            depth_bias: None,
+            multisample: MultisampleState::default(),
+        }
+    }
+
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Names the entry points instead of `main`, e.g. when both
+    /// stages are in the same WGSL module.
+    pub fn entry_points(mut self, vertex: &'a str, fragment: &'a str) -> Self {
+        self.vertex_entry_point = vertex;
+        self.fragment_entry_point = fragment;
+        self
+    }
+
+    pub fn vertex_buffers(mut self, vertex_buffers: &'a [VertexBufferLayout<'a>]) -> Self {
+        self.vertex_buffers = vertex_buffers;
+        self
+    }
+
+    /// Appends a color target. Targets are bound to fragment
+    /// shader outputs in the order they are added.
+    pub fn color_target(mut self, target: ColorTargetState) -> Self {
+        self.color_targets.push(target);
+        self
+    }
+
+    /// Appends several color targets, each with its own blend state and write mask.
+    pub fn color_targets(mut self, targets: &[ColorTargetState]) -> Self {
+        self.color_targets.extend_from_slice(targets);
+        self
+    }
+
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.primitive.topology = topology;
+        self
+    }
+
+    pub fn primitive(mut self, primitive: PrimitiveState) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn depth_stencil(mut self, depth_stencil: Option<DepthStencilState>) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    /// Tests and writes depth against a depth attachment of `format`.
+    pub fn depth_test(self, format: TextureFormat, compare: CompareFunction) -> Self {
+        self.depth_stencil(Some(DepthStencilState {
+            format,
+            depth_write_enabled: true,
+            depth_compare: compare,
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+            clamp_depth: false,
+        }))
+    }
+
+    /// Offsets the depth of every fragment so coplanar geometry, like decals
+    /// stacked on a quad, doesn't z-fight. With a `Less` depth test a negative
+    /// `depth_bias` draws on top. The final offset is
+    /// `depth_bias * r + depth_bias_slope_scale * max_depth_slope`, limited to
+    /// `depth_bias_clamp` unless that is 0.0 (`r` is the smallest resolvable depth).
+    /// Only has an effect when depth testing is enabled, `build` warns otherwise.
+    pub fn depth_bias(
+        mut self,
+        depth_bias: i32,
+        depth_bias_slope_scale: f32,
+        depth_bias_clamp: f32,
+    ) -> Self {
+        self.depth_bias = Some(DepthBiasState {
+            constant: depth_bias,
+            slope_scale: depth_bias_slope_scale,
+            clamp: depth_bias_clamp,
+        });
+        self
+    }
+
+    pub fn multisample(mut self, multisample: MultisampleState) -> Self {
+        self.multisample = multisample;
+        self
+    }
+
+    /// Same as `build`, but returns shader and pipeline validation errors
+    /// instead of panicking. Use this for shaders loaded at runtime.
+    pub async fn try_build(self, device: &Device) -> Result<RenderPipeline, ShaderError> {
+        capture_validation_errors(device, || self.build(device)).await
+    }
+
+    pub fn build(mut self, device: &Device) -> RenderPipeline {
+        match (self.depth_stencil.as_mut(), self.depth_bias) {
+            (Some(depth_stencil), Some(bias)) => depth_stencil.bias = bias,
+            (None, Some(_)) => {
+                println!(
+                    "Ignoring the depth bias of pipeline {:?} without a depth test",
+                    self.label
+                )
+            },
+            _ => {},
+        }
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: self.label,
+            layout: Some(self.layout),
+            vertex: wgpu::VertexState {
+                module: self.vertex_module,
+                entry_point: self.vertex_entry_point,
+                buffers: self.vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: self.fragment_module,
+                entry_point: self.fragment_entry_point,
+                targets: &self.color_targets,
+            }),
+            primitive: self.primitive,
+            depth_stencil: self.depth_stencil,
+            multisample: self.multisample,
+        })
+    }
+}
+
+/// The parts of a pipeline description which distinguish cached pipelines.
+/// `shader` identifies the shaders (and with them the vertex and bind group
+/// layouts), so every drawable type should use its own name, and `features`
+/// the variant of them, see `preprocess_shader`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: &'static str,
+    /// One per color target, in the order they're added to the `PipelineBuilder`.
+    pub targets: Vec<TargetKey>,
+    pub topology: PrimitiveTopology,
+    pub cull_mode: Option<Face>,
+    pub depth: Option<DepthKey>,
+    pub features: ShaderFeatures,
+}
+
+/// A color target of a `PipelineKey`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetKey {
+    pub format: TextureFormat,
+    pub blend: Option<BlendState>,
+    pub write_mask: ColorWrite,
+}
+
+impl TargetKey {
+    /// The key of `color_target(format, blend)`, which writes all channels.
+    pub fn new(format: TextureFormat, blend: Option<BlendState>) -> Self {
+        Self::from(&color_target(format, blend))
+    }
+}
+
+impl From<&ColorTargetState> for TargetKey {
+    fn from(target: &ColorTargetState) -> Self {
+        Self { format: target.format, blend: target.blend, write_mask: target.write_mask }
+    }
+}
+
+/// The depth test of a `PipelineKey`. The floats of the bias are kept as
+/// bits, so that keys can be hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthKey {
+    format: TextureFormat,
+    compare: CompareFunction,
+    bias: i32,
+    bias_slope_scale: u32,
+    bias_clamp: u32,
+}
+
+impl DepthKey {
+    pub fn new(format: TextureFormat, compare: CompareFunction, bias: DepthBiasState) -> Self {
+        Self {
+            format,
+            compare,
+            bias: bias.constant,
+            bias_slope_scale: bias.slope_scale.to_bits(),
+            bias_clamp: bias.clamp.to_bits(),
+        }
+    }
+}
+
+/// Shares render pipelines between drawables with the same `PipelineKey`, so
+/// shaders are compiled and pipelines built once instead of per drawable.
+/// One lives on the `GraphicsDevice`, see `GraphicsDevice::pipeline_cache`.
+#[derive(Default)]
+pub struct PipelineCache {
+    pipelines: Mutex<HashMap<PipelineKey, (Arc<RenderPipeline>, Tracked)>>,
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pipeline cached for `key`, or builds it with `create` and caches it.
+    pub fn get_or_create<F: FnOnce() -> RenderPipeline>(
+        &self,
+        key: PipelineKey,
+        create: F,
+    ) -> Arc<RenderPipeline> {
+        let mut pipelines = self.pipelines.lock().expect("Pipeline cache lock was poisoned");
+        let (pipeline, _) = pipelines
+            .entry(key)
+            .or_insert_with(|| (Arc::new(create()), Tracked::new(ResourceKind::Pipeline)));
+        pipeline.clone()
+    }
+
+    /// Same as `get_or_create` for pipelines which are built asynchronously and
+    /// can fail, like ones using `create_shader_variant`. Nothing is cached when
+    /// `create` fails.
+    pub async fn try_get_or_create<F, Fut>(
+        &self,
+        key: PipelineKey,
+        create: F,
+    ) -> Result<Arc<RenderPipeline>, ShaderError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<RenderPipeline, ShaderError>>,
+    {
+        if let Some((pipeline, _)) =
+            self.pipelines.lock().expect("Pipeline cache lock was poisoned").get(&key)
+        {
+            return Ok(pipeline.clone());
+        }
+
+        // The lock isn't held while building, the first pipeline to finish is kept.
+        let pipeline = Arc::new(create().await?);
+        let mut pipelines = self.pipelines.lock().expect("Pipeline cache lock was poisoned");
+        let (pipeline, _) = pipelines
+            .entry(key)
+            .or_insert_with(|| (pipeline, Tracked::new(ResourceKind::Pipeline)));
+        Ok(pipeline.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.lock().expect("Pipeline cache lock was poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all cached pipelines. Drawables keep the pipelines they already hold.
+    pub fn clear(&self) {
+        self.pipelines.lock().expect("Pipeline cache lock was poisoned").clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{tests::headless_device, Color, RenderTarget};
+    use futures::executor::block_on;
+    use std::borrow::Cow;
+
+    /// Covers the target with a half transparent red triangle, in both outputs.
+    const TWO_OUTPUTS: &str = "
+        [[stage(vertex)]]
+        fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+            let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+            return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+        }
+
+        struct Outputs {
+            [[location(0)]] blended: vec4<f32>;
+            [[location(1)]] replaced: vec4<f32>;
+        };
+
+        [[stage(fragment)]]
+        fn fs_main() -> Outputs {
+            var out: Outputs;
+            out.blended = vec4<f32>(1.0, 0.0, 0.0, 0.5);
+            out.replaced = out.blended;
+            return out;
+        }
+    ";
+
+    fn key(targets: Vec<TargetKey>) -> PipelineKey {
+        PipelineKey {
+            shader: "test",
+            targets,
+            topology: PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            depth: None,
+            features: ShaderFeatures::NONE,
+        }
+    }
+
+    #[test]
+    fn keys_differ_by_the_blend_state_of_each_target() {
+        let format = TextureFormat::Rgba8Unorm;
+        let blended = TargetKey::new(format, Some(BlendState::ALPHA_BLENDING));
+        let replaced = TargetKey::new(format, Some(BlendState::REPLACE));
+
+        assert_eq!(key(vec![blended, replaced]), key(vec![blended, replaced]));
+        assert_ne!(key(vec![blended, replaced]), key(vec![replaced, blended]));
+        assert_ne!(key(vec![blended, replaced]), key(vec![blended, blended]));
+        assert_ne!(key(vec![blended]), key(vec![blended, blended]));
+    }
+
+    #[test]
+    fn keys_differ_by_the_write_mask_of_each_target() {
+        let format = TextureFormat::Rgba8Unorm;
+        let all = TargetKey::new(format, None);
+        let color_only = TargetKey { write_mask: ColorWrite::COLOR, ..all };
+
+        assert_eq!(TargetKey::from(&color_target(format, None)), all);
+        assert_ne!(key(vec![all, all]), key(vec![all, color_only]));
+        assert_ne!(key(vec![all, color_only]), key(vec![color_only, all]));
+    }
+
+    #[test]
+    fn each_target_blends_and_masks_writes_separately() {
+        let mut graphics_device = match headless_device(4, 4) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+
+        let format = TextureFormat::Rgba8Unorm;
+        let blended = RenderTarget::with_format(&graphics_device, format);
+        let replaced = RenderTarget::with_format(&graphics_device, format);
+        for (target, color) in
+            [(&blended, Color::BLACK), (&replaced, Color::rgba8(0, 0, 255, 64))].iter()
+        {
+            let mut frame_encoder = graphics_device.begin_frame_to_render_target(target);
+            frame_encoder.clear(*color);
+            frame_encoder.finish();
+        }
+
+        let device = graphics_device.device();
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Two outputs"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(TWO_OUTPUTS)),
+            flags: wgpu::ShaderFlags::all(),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Two outputs"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = PipelineBuilder::new(&layout, &module, &module)
+            .entry_points("vs_main", "fs_main")
+            .color_target(color_target(format, Some(BlendState::ALPHA_BLENDING)))
+            .color_target(ColorTargetState {
+                format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrite::COLOR,
+            })
+            .build(device);
+
+        let attachment = |view| wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+        };
+        let mut encoder = device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("MRT test") });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("MRT test"),
+                color_attachments: &[attachment(blended.view()), attachment(replaced.view())],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+        graphics_device.queue().submit(Some(encoder.finish()));
+
+        // Half of the red over opaque black, with the alpha blended the same way.
+        let image = block_on(graphics_device.capture_frame(&blended)).unwrap();
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            assert!((127..=128).contains(&r) && g == 0 && b == 0, "Blended {:?}", pixel);
+            assert!((191..=192).contains(&a), "Blended {:?}", pixel);
+        }
+
+        // The red replaces the color, the cleared alpha is kept.
+        let image = block_on(graphics_device.capture_frame(&replaced)).unwrap();
+        for pixel in image.pixels() {
+            assert_eq!(pixel.0, [255, 0, 0, 64], "Replaced {:?}", pixel);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::graphics::{
+    color_target, Color, FrameEncoder, FullscreenPass, GraphicsDevice, RenderTarget,
+};
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Queue, ShaderModuleDescriptor};
+
+/// Draws a `RenderTarget` to the current frame target with a `FullscreenPass`,
+/// running it through a custom fragment shader on the way.
+/// The fragment shader receives the UV coordinates at location 0 and
+/// the render target as:
+///
+/// ```glsl
+/// layout(set = 0, binding = 0) uniform texture2D scene_texture;
+/// layout(set = 0, binding = 1) uniform sampler scene_sampler;
+/// layout(location = 0) in vec2 vert_uv;
+/// ```
+///
+/// Post processes created `with_uniforms` also get a uniform buffer, written
+/// with `write_uniforms`:
+///
+/// ```glsl
+/// layout(set = 0, binding = 2) uniform Params { ... };
+/// ```
+///
+/// The source texture is bound up front, so call `set_source` again
+/// after resizing the render target. Browsers only take WGSL shaders,
+/// see the `.wgsl` ports of the built-in ones in `resources/shaders`.
+pub struct PostProcess {
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    uniform_buffer: Option<Buffer>,
+    pass: FullscreenPass,
+}
+
+impl PostProcess {
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        source: &RenderTarget,
+        fragment_shader: &ShaderModuleDescriptor,
+    ) -> Self {
+        Self::create(graphics_device, source, fragment_shader, None)
+    }
+
+    /// Same as `new`, with a `uniform_size` byte uniform buffer for the fragment shader.
+    pub fn with_uniforms(
+        graphics_device: &GraphicsDevice,
+        source: &RenderTarget,
+        fragment_shader: &ShaderModuleDescriptor,
+        uniform_size: u64,
+    ) -> Self {
+        Self::create(graphics_device, source, fragment_shader, Some(uniform_size))
+    }
+
+    fn create(
+        graphics_device: &GraphicsDevice,
+        source: &RenderTarget,
+        fragment_shader: &ShaderModuleDescriptor,
+        uniform_size: Option<u64>,
+    ) -> Self {
+        let device = graphics_device.device();
+
+        let uniform_buffer = uniform_size.map(|size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Post Process Uniform Buffer"),
+                size,
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler { filtering: true, comparison: false },
+                count: None,
+            },
+        ];
+
+        if let Some(size) = uniform_size {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(size),
+                },
+                count: None,
+            });
+        }
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post process"),
+            entries: &entries,
+        });
+
+        let format = graphics_device.swap_chain_descriptor().format;
+        let pass = FullscreenPass::new(
+            graphics_device,
+            "post process",
+            &[&bind_group_layout],
+            fragment_shader,
+            color_target(format, Some(wgpu::BlendState::REPLACE)),
+        );
+
+        let bind_group = Self::create_bind_group(
+            graphics_device,
+            &bind_group_layout,
+            source,
+            uniform_buffer.as_ref(),
+        );
+
+        Self { bind_group_layout, bind_group, uniform_buffer, pass }
+    }
+
+    /// A post process which copies the render target unchanged.
+    pub fn passthrough(graphics_device: &GraphicsDevice, source: &RenderTarget) -> Self {
+        Self::new(graphics_device, source, &include_shader!("post_passthrough.frag"))
+    }
+
+    fn create_bind_group(
+        graphics_device: &GraphicsDevice,
+        bind_group_layout: &BindGroupLayout,
+        source: &RenderTarget,
+        uniform_buffer: Option<&Buffer>,
+    ) -> BindGroup {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(source.sampler()),
+            },
+        ];
+
+        if let Some(buffer) = uniform_buffer {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer { buffer, offset: 0, size: None },
+            });
+        }
+
+        graphics_device.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &entries,
+            label: None,
+        })
+    }
+
+    /// Switches to sampling `source`. The bound texture view goes stale
+    /// when a render target is resized, so call this after `RenderTarget::resize`.
+    pub fn set_source(&mut self, graphics_device: &GraphicsDevice, source: &RenderTarget) {
+        self.bind_group = Self::create_bind_group(
+            graphics_device,
+            &self.bind_group_layout,
+            source,
+            self.uniform_buffer.as_ref(),
+        );
+    }
+
+    /// Writes `data` to the start of the uniform buffer of a post process created
+    /// `with_uniforms`. Does nothing for post processes without uniforms.
+    pub fn write_uniforms(&self, queue: &Queue, data: &[u8]) {
+        if let Some(buffer) = &self.uniform_buffer {
+            queue.write_buffer(buffer, 0, data);
+        }
+    }
+
+    /// Draws the source render target over the whole frame target of `frame_encoder`.
+    pub fn render(&self, frame_encoder: &mut FrameEncoder) {
+        self.pass.render(frame_encoder, &[&self.bind_group], Some(Color::BLACK));
+    }
+}
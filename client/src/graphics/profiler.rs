@@ -0,0 +1,113 @@
+use futures::FutureExt;
+use std::{future::Future, pin::Pin};
+use wgpu::{BufferAsyncError, CommandEncoder, Device, QuerySet, Queue};
+
+const TIMESTAMP_COUNT: u32 = 2;
+const TIMESTAMP_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), BufferAsyncError>> + Send>>;
+
+/// Measures GPU time per frame by writing timestamps at the start and end
+/// of the frame's command buffer. Results are read back without blocking,
+/// so they lag a frame or two behind, and frames which start while the
+/// previous readback is still in flight are not measured.
+pub(crate) struct GpuProfiler {
+    query_set: QuerySet,
+    readback_buffer: wgpu::Buffer,
+    timestamp_period: f32,
+    recording: bool,
+    pending_map: Option<MapFuture>,
+    last_frame_ms: Option<f32>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU frame timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: TIMESTAMP_COUNT,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU frame timestamp readback"),
+            size: TIMESTAMP_COUNT as u64 * TIMESTAMP_SIZE,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            readback_buffer,
+            timestamp_period: queue.get_timestamp_period(),
+            recording: false,
+            pending_map: None,
+            last_frame_ms: None,
+        }
+    }
+
+    pub fn begin(&mut self, encoder: &mut CommandEncoder) {
+        // The readback buffer can't be written while it's being mapped.
+        if self.pending_map.is_some() {
+            return;
+        }
+
+        encoder.write_timestamp(&self.query_set, 0);
+        self.recording = true;
+    }
+
+    pub fn end(&mut self, encoder: &mut CommandEncoder) {
+        if !self.recording {
+            return;
+        }
+
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..TIMESTAMP_COUNT, &self.readback_buffer, 0);
+    }
+
+    /// Starts reading back the timestamps once the frame has been submitted.
+    pub fn after_submit(&mut self) {
+        if !self.recording {
+            return;
+        }
+
+        self.recording = false;
+        self.pending_map =
+            Some(Box::pin(self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read)));
+    }
+
+    /// Picks up the result of a previous frame's readback, if it has finished.
+    pub fn poll(&mut self, device: &Device) {
+        let pending_map = match self.pending_map.as_mut() {
+            Some(pending_map) => pending_map,
+            None => return,
+        };
+
+        device.poll(wgpu::Maintain::Poll);
+
+        let result = match pending_map.now_or_never() {
+            Some(result) => result,
+            None => return,
+        };
+
+        self.pending_map = None;
+
+        if let Err(e) = result {
+            println!("Failed to read back GPU timestamps: {:?}", e);
+            return;
+        }
+
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            let ticks = timestamps[1].saturating_sub(timestamps[0]);
+
+            self.last_frame_ms = Some(ticks as f32 * self.timestamp_period / 1_000_000.0);
+        }
+
+        self.readback_buffer.unmap();
+    }
+
+    pub fn last_frame_ms(&self) -> Option<f32> {
+        self.last_frame_ms
+    }
+}
@@ -0,0 +1,174 @@
+use crate::graphics::{
+    Background, Camera2D, Color, DebugOverlay, FrameEncoder, NineSlice, ShapeRenderer, TileMap,
+};
+use winit::dpi::PhysicalSize;
+
+/// Which camera a layer is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayerSpace {
+    /// The camera set with `FrameEncoder::set_world_camera`.
+    World,
+
+    /// Screen pixels, with the origin at the top left of the frame.
+    Screen,
+}
+
+/// A layer drawables are submitted to with `FrameEncoder::submit`. Layers are
+/// drawn in the order they were given to `GraphicsDevice::set_render_layers`,
+/// back to front, when the frame is finished.
+#[derive(Debug, Clone)]
+pub struct RenderLayer {
+    pub name: &'static str,
+    pub space: LayerSpace,
+
+    /// Clears the frame to this color before the layer is drawn.
+    pub clear: Option<Color>,
+}
+
+impl RenderLayer {
+    pub const BACKGROUND: &'static str = "background";
+    pub const WORLD: &'static str = "world";
+    pub const UI: &'static str = "ui";
+
+    pub fn world(name: &'static str) -> Self {
+        Self { name, space: LayerSpace::World, clear: None }
+    }
+
+    pub fn screen(name: &'static str) -> Self {
+        Self { name, space: LayerSpace::Screen, clear: None }
+    }
+
+    pub fn with_clear(mut self, color: Color) -> Self {
+        self.clear = Some(color);
+        self
+    }
+
+    /// `BACKGROUND` and `WORLD` layers drawn with the world camera,
+    /// and a `UI` layer in screen space on top of them.
+    pub fn defaults() -> Vec<RenderLayer> {
+        vec![
+            RenderLayer::world(Self::BACKGROUND),
+            RenderLayer::world(Self::WORLD),
+            RenderLayer::screen(Self::UI),
+        ]
+    }
+}
+
+/// Something which can be submitted to a `RenderLayer`. `camera` is
+/// the layer's camera, and `window_size` is the size of the frame.
+pub trait LayerDrawable {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    );
+}
+
+impl LayerDrawable for ShapeRenderer {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        self.render(frame_encoder, camera, window_size);
+    }
+}
+
+impl LayerDrawable for TileMap {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        self.draw(frame_encoder, camera, window_size);
+    }
+}
+
+/// Backgrounds fill the whole frame, whatever the layer's space.
+impl LayerDrawable for Background {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        _camera: &Camera2D,
+        _window_size: PhysicalSize<u32>,
+    ) {
+        self.render(frame_encoder);
+    }
+}
+
+/// Nine slices are always drawn in screen space.
+impl LayerDrawable for NineSlice {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        _camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        self.render(frame_encoder, window_size);
+    }
+}
+
+/// Debug text is always drawn in screen space.
+impl LayerDrawable for DebugOverlay {
+    fn render_layer(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        _camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        self.render(frame_encoder, window_size);
+    }
+}
+
+/// The drawables submitted to each layer during a frame.
+pub(crate) struct LayerQueues<'a> {
+    layers: Vec<(RenderLayer, Vec<&'a mut dyn LayerDrawable>)>,
+    world_camera: Camera2D,
+}
+
+impl<'a> LayerQueues<'a> {
+    pub(crate) fn new(layers: &[RenderLayer]) -> Self {
+        Self {
+            layers: layers.iter().map(|layer| (layer.clone(), Vec::new())).collect(),
+            world_camera: Camera2D::default(),
+        }
+    }
+
+    pub(crate) fn set_world_camera(&mut self, camera: Camera2D) {
+        self.world_camera = camera;
+    }
+
+    pub(crate) fn submit(&mut self, layer: &str, drawable: &'a mut dyn LayerDrawable) {
+        match self.layers.iter_mut().find(|(render_layer, _)| render_layer.name == layer) {
+            Some((_, drawables)) => drawables.push(drawable),
+            None => println!("Trying to submit to render layer {:?}, which doesn't exist", layer),
+        }
+    }
+
+    /// Draws every layer back to front.
+    pub(crate) fn flush(self, frame_encoder: &mut FrameEncoder<'a>) {
+        let window_size = frame_encoder.design_size();
+        let (width, height) = (window_size.width as f32, window_size.height as f32);
+        let screen_camera = Camera2D::new([width / 2.0, height / 2.0], 1.0);
+
+        for (layer, drawables) in self.layers {
+            let camera = match layer.space {
+                LayerSpace::World => &self.world_camera,
+                LayerSpace::Screen => &screen_camera,
+            };
+
+            frame_encoder.debug_group(layer.name, |frame_encoder| {
+                if let Some(color) = layer.clear {
+                    frame_encoder.clear(color);
+                }
+
+                for drawable in drawables {
+                    drawable.render_layer(frame_encoder, camera, window_size);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,23 @@
+/// What the drawables of a frame did, for checking that batching works.
+/// See `FrameEncoder::stats` and `GraphicsDevice::last_frame_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+
+    /// Triangles across all draw calls, counting every instance.
+    pub triangles: u32,
+
+    /// Vertex and instance data written to GPU buffers, not counting uniforms.
+    pub vertex_bytes: u64,
+}
+
+impl RenderStats {
+    pub(crate) fn record_draw(&mut self, triangles: u32) {
+        self.draw_calls += 1;
+        self.triangles += triangles;
+    }
+
+    pub(crate) fn record_upload(&mut self, bytes: usize) {
+        self.vertex_bytes += bytes as u64;
+    }
+}
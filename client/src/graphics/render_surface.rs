@@ -0,0 +1,184 @@
+use crate::graphics::resource_tracker::{ResourceKind, Tracked};
+use wgpu::{
+    Backend, Device, PresentMode, Surface, SwapChain, SwapChainDescriptor, SwapChainTexture,
+};
+use winit::dpi::PhysicalSize;
+
+/// A window's surface and the swap chain presenting to it. The `GraphicsDevice`
+/// owns the surface of the window it was created with, and more windows can
+/// share its device with surfaces from `GraphicsDevice::create_surface`.
+///
+/// The surface can be dropped with `suspend` while the window is gone, e.g.
+/// when a mobile app is in the background. The swap chain descriptor is kept,
+/// so pipelines can still be created for its format.
+pub struct RenderSurface {
+    /// `None` while suspended.
+    surface: Option<PresentingSurface>,
+    swap_chain_descriptor: SwapChainDescriptor,
+}
+
+/// The parts of a `RenderSurface` which only exist while it isn't suspended.
+struct PresentingSurface {
+    // Fields are dropped in declaration order, and the swap chain has to be
+    // dropped before the surface it presents to.
+    swap_chain: SwapChain,
+    surface: Surface,
+    _tracked: Tracked,
+}
+
+impl PresentingSurface {
+    fn new(device: &Device, surface: Surface, descriptor: &SwapChainDescriptor) -> Self {
+        let swap_chain = device.create_swap_chain(&surface, descriptor);
+        Self { swap_chain, surface, _tracked: Tracked::new(ResourceKind::SwapChain) }
+    }
+}
+
+/// Present modes `with_preferred_present_mode` picks from, in order. Every
+/// surface supports Fifo, so the list ends with it.
+const PRESENT_MODE_PREFERENCE: [PresentMode; 3] =
+    [PresentMode::Mailbox, PresentMode::Immediate, PresentMode::Fifo];
+
+/// The present modes surfaces of `backend` can support. This version of wgpu
+/// can't list a surface's present modes, so this goes by what each backend
+/// implements. A surface asked for a mode it doesn't support anyway (e.g.
+/// Mailbox on some Vulkan drivers) presents with Fifo instead.
+fn backend_present_modes(backend: Backend) -> &'static [PresentMode] {
+    match backend {
+        Backend::Vulkan => &[PresentMode::Mailbox, PresentMode::Immediate, PresentMode::Fifo],
+        Backend::Metal | Backend::Dx12 | Backend::Dx11 => {
+            &[PresentMode::Immediate, PresentMode::Fifo]
+        },
+        _ => &[PresentMode::Fifo],
+    }
+}
+
+/// The first mode of `preference` which is `supported`, Fifo if there's none.
+fn choose_present_mode(preference: &[PresentMode], supported: &[PresentMode]) -> PresentMode {
+    preference.iter().copied().find(|mode| supported.contains(mode)).unwrap_or(PresentMode::Fifo)
+}
+
+impl RenderSurface {
+    pub(crate) fn new(
+        device: &Device,
+        surface: Surface,
+        format: wgpu::TextureFormat,
+        present_mode: PresentMode,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let swap_chain_descriptor = Self::descriptor(format, present_mode, size);
+        let surface = PresentingSurface::new(device, surface, &swap_chain_descriptor);
+        Self { surface: Some(surface), swap_chain_descriptor }
+    }
+
+    /// Same as `new`, with the first of Mailbox, Immediate and Fifo which
+    /// surfaces of `backend` support.
+    pub(crate) fn with_preferred_present_mode(
+        device: &Device,
+        surface: Surface,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        backend: Backend,
+    ) -> Self {
+        let present_mode =
+            choose_present_mode(&PRESENT_MODE_PREFERENCE, backend_present_modes(backend));
+        println!("Presenting with {:?}", present_mode);
+
+        Self::new(device, surface, format, present_mode, size)
+    }
+
+    /// A surface which never presents, for devices without a window. It's
+    /// suspended for good, and only has a descriptor for `format` and `size`.
+    pub(crate) fn headless(format: wgpu::TextureFormat, size: PhysicalSize<u32>) -> Self {
+        let swap_chain_descriptor = Self::descriptor(format, PresentMode::Fifo, size);
+        Self { surface: None, swap_chain_descriptor }
+    }
+
+    fn descriptor(
+        format: wgpu::TextureFormat,
+        present_mode: PresentMode,
+        size: PhysicalSize<u32>,
+    ) -> SwapChainDescriptor {
+        wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode,
+        }
+    }
+
+    /// Recreates the swap chain, call this when the window is resized.
+    pub fn resize(&mut self, device: &Device, new_size: PhysicalSize<u32>) {
+        self.swap_chain_descriptor.width = new_size.width;
+        self.swap_chain_descriptor.height = new_size.height;
+
+        if let Some(surface) = self.surface.as_mut() {
+            surface.swap_chain =
+                device.create_swap_chain(&surface.surface, &self.swap_chain_descriptor);
+        }
+    }
+
+    /// Drops the swap chain and surface, call this before the window goes away.
+    pub fn suspend(&mut self) {
+        self.surface = None;
+    }
+
+    /// Takes a new `surface` after `suspend`, for the window at its current `size`.
+    pub(crate) fn resume(&mut self, device: &Device, surface: Surface, size: PhysicalSize<u32>) {
+        self.swap_chain_descriptor.width = size.width;
+        self.swap_chain_descriptor.height = size.height;
+
+        self.surface = Some(PresentingSurface::new(device, surface, &self.swap_chain_descriptor));
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    pub fn swap_chain_descriptor(&self) -> &SwapChainDescriptor {
+        &self.swap_chain_descriptor
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.swap_chain_descriptor.present_mode
+    }
+
+    /// The next swap chain texture, `None` while suspended.
+    pub(crate) fn current_frame(&self) -> Option<SwapChainTexture> {
+        let surface = self.surface.as_ref()?;
+
+        Some(
+            surface
+                .swap_chain
+                .get_current_frame()
+                .expect("Failed to acquire next swap chain texture")
+                .output,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn present_modes_are_chosen_in_preference_order() {
+        let choose =
+            |backend| choose_present_mode(&PRESENT_MODE_PREFERENCE, backend_present_modes(backend));
+
+        assert_eq!(choose(Backend::Vulkan), PresentMode::Mailbox);
+        assert_eq!(choose(Backend::Metal), PresentMode::Immediate);
+        assert_eq!(choose(Backend::Dx12), PresentMode::Immediate);
+        assert_eq!(choose(Backend::Gl), PresentMode::Fifo);
+        assert_eq!(choose(Backend::BrowserWebGpu), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn fifo_is_chosen_without_a_supported_preference() {
+        assert_eq!(choose_present_mode(&[PresentMode::Mailbox], &[]), PresentMode::Fifo);
+        assert_eq!(
+            choose_present_mode(&[PresentMode::Immediate], &[PresentMode::Mailbox]),
+            PresentMode::Fifo
+        );
+    }
+}
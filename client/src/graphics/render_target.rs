@@ -0,0 +1,219 @@
+use crate::graphics::{
+    resource_tracker::{ResourceKind, Tracked},
+    FrameEncoder, FrameTarget, GraphicsDevice, ResourceId,
+};
+use wgpu::{Sampler, Texture, TextureFormat, TextureView};
+
+/// The format of HDR render targets, 16 bit float channels which can go above 1.0.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The format of `DepthTarget`s. It's fixed point, so a constant depth
+/// bias offsets by the same amount at any depth.
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth24Plus;
+
+/// An offscreen color texture the size of the swap chain. Drawables render
+/// into it when it's set as the target of a `FrameEncoder`, and it can then
+/// be sampled, e.g. by a `PostProcess` drawing to the swap chain.
+///
+/// With HDR enabled in the `GraphicsDeviceConfig` it's an `HDR_FORMAT` float
+/// texture, which is what drawables render to then, see `GraphicsDevice::color_format`.
+pub struct RenderTarget {
+    id: ResourceId,
+    texture: Texture,
+    view: TextureView,
+    sampler: Sampler,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    _tracked: Tracked,
+}
+
+impl RenderTarget {
+    /// Creates a render target matching the swap chain size,
+    /// in the format drawables render in.
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        let format = graphics_device.color_format();
+        Self::with_format(graphics_device, format)
+    }
+
+    pub fn with_format(graphics_device: &GraphicsDevice, format: TextureFormat) -> Self {
+        let descriptor = graphics_device.swap_chain_descriptor();
+        let (width, height) = (descriptor.width, descriptor.height);
+        let (texture, view) = Self::create_texture(graphics_device, format, width, height);
+
+        let sampler = graphics_device.device().create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            id: ResourceId::new(),
+            texture,
+            view,
+            sampler,
+            format,
+            width,
+            height,
+            _tracked: Tracked::new(ResourceKind::Texture),
+        }
+    }
+
+    fn create_texture(
+        graphics_device: &GraphicsDevice,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> (Texture, TextureView) {
+        let texture = graphics_device.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("Render target texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    /// Recreates the texture if the swap chain size changed, which also
+    /// gives it a new `id`. Call this after `GraphicsDevice::resize`.
+    pub fn resize(&mut self, graphics_device: &GraphicsDevice) {
+        let descriptor = graphics_device.swap_chain_descriptor();
+        if (descriptor.width, descriptor.height) == (self.width, self.height) {
+            return;
+        }
+
+        self.width = descriptor.width;
+        self.height = descriptor.height;
+
+        let (texture, view) =
+            Self::create_texture(graphics_device, self.format, self.width, self.height);
+        self.id = ResourceId::new();
+        self.texture = texture;
+        self.view = view;
+    }
+
+    /// A frame target for `FrameEncoder::set_target`.
+    pub fn frame_target(&self) -> FrameTarget {
+        FrameTarget::View(self.texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Identifies the current texture in a `BindGroupCache`.
+    pub fn id(&self) -> ResourceId {
+        self.id
+    }
+
+    pub(crate) fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// A view of the texture, for sampling in shaders.
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.format == HDR_FORMAT
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// A depth texture the size of the swap chain, for drawables with depth testing
+/// like `TexturedQuad::with_depth`. Clear it with `clear` at the start of each
+/// frame, before anything is drawn with it.
+pub struct DepthTarget {
+    /// Only kept alive for `view`.
+    _texture: Texture,
+    view: TextureView,
+    width: u32,
+    height: u32,
+    _tracked: Tracked,
+}
+
+impl DepthTarget {
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        let descriptor = graphics_device.swap_chain_descriptor();
+        let (width, height) = (descriptor.width, descriptor.height);
+        let (texture, view) = Self::create_texture(graphics_device, width, height);
+
+        Self { texture, view, width, height, _tracked: Tracked::new(ResourceKind::Texture) }
+    }
+
+    fn create_texture(
+        graphics_device: &GraphicsDevice,
+        width: u32,
+        height: u32,
+    ) -> (Texture, TextureView) {
+        let texture = graphics_device.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth target texture"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    /// Recreates the texture if the swap chain size changed.
+    /// Call this after `GraphicsDevice::resize`.
+    pub fn resize(&mut self, graphics_device: &GraphicsDevice) {
+        let descriptor = graphics_device.swap_chain_descriptor();
+        if (descriptor.width, descriptor.height) == (self.width, self.height) {
+            return;
+        }
+
+        self.width = descriptor.width;
+        self.height = descriptor.height;
+        let (texture, view) = Self::create_texture(graphics_device, self.width, self.height);
+        self._texture = texture;
+        self.view = view;
+    }
+
+    /// Clears the depth to 1.0, the far end of the depth range.
+    pub fn clear(&self, frame_encoder: &mut FrameEncoder) {
+        frame_encoder.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("clear depth"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.view,
+                depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: true }),
+                stencil_ops: None,
+            }),
+        });
+    }
+
+    pub(crate) fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(feature = "resource-tracking")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(all(feature = "resource-tracking", not(test)))]
+static LIVE_COUNTS: [AtomicUsize; 5] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+// Tests run in parallel on their own threads, so they each count only their
+// own resources and can check for leaks.
+#[cfg(all(feature = "resource-tracking", test))]
+thread_local! {
+    static LIVE_COUNTS: [AtomicUsize; 5] = [
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+    ];
+}
+
+#[cfg(all(feature = "resource-tracking", not(test)))]
+fn with_live_count<R>(kind: ResourceKind, f: impl FnOnce(&AtomicUsize) -> R) -> R {
+    f(&LIVE_COUNTS[kind as usize])
+}
+
+#[cfg(all(feature = "resource-tracking", test))]
+fn with_live_count<R>(kind: ResourceKind, f: impl FnOnce(&AtomicUsize) -> R) -> R {
+    LIVE_COUNTS.with(|counts| f(&counts[kind as usize]))
+}
+
+/// How many resources created by the graphics helpers are alive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceStats {
+    /// `Texture`, `RenderTarget` and `Cubemap` textures.
+    pub textures: usize,
+
+    /// `StorageBuffer`, `IndexBuffer` and `BufferPool` buffers.
+    pub buffers: usize,
+
+    /// Bind groups held by a `BindGroupCache`.
+    pub bind_groups: usize,
+
+    /// Pipelines held by a `PipelineCache`.
+    pub pipelines: usize,
+
+    /// One per `RenderSurface` which isn't suspended.
+    pub swap_chains: usize,
+}
+
+/// Counts the live GPU resources of each kind, for spotting leaks in a debug
+/// overlay. A count which keeps going up (e.g. while resizing the window)
+/// means resources are created without the old ones being dropped.
+///
+/// Counting is only compiled in with the `resource-tracking` feature,
+/// without it `stats` is all zeros and tracking costs nothing.
+pub struct ResourceTracker;
+
+impl ResourceTracker {
+    pub fn is_enabled() -> bool {
+        cfg!(feature = "resource-tracking")
+    }
+
+    #[cfg(feature = "resource-tracking")]
+    pub fn stats() -> ResourceStats {
+        let count = |kind| with_live_count(kind, |count| count.load(Ordering::Relaxed));
+
+        ResourceStats {
+            textures: count(ResourceKind::Texture),
+            buffers: count(ResourceKind::Buffer),
+            bind_groups: count(ResourceKind::BindGroup),
+            pipelines: count(ResourceKind::Pipeline),
+            swap_chains: count(ResourceKind::SwapChain),
+        }
+    }
+
+    #[cfg(not(feature = "resource-tracking"))]
+    pub fn stats() -> ResourceStats {
+        ResourceStats::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ResourceKind {
+    Texture,
+    Buffer,
+    BindGroup,
+    Pipeline,
+    SwapChain,
+}
+
+/// Counts one resource of a kind as alive until it's dropped. Keep it next
+/// to the wgpu resource it stands for.
+pub(crate) struct Tracked {
+    #[cfg(feature = "resource-tracking")]
+    kind: ResourceKind,
+}
+
+impl Tracked {
+    #[cfg(feature = "resource-tracking")]
+    pub fn new(kind: ResourceKind) -> Self {
+        with_live_count(kind, |count| count.fetch_add(1, Ordering::Relaxed));
+        Self { kind }
+    }
+
+    #[cfg(not(feature = "resource-tracking"))]
+    pub fn new(_kind: ResourceKind) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(feature = "resource-tracking")]
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        with_live_count(self.kind, |count| count.fetch_sub(1, Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,133 @@
+//! Seeded randomness for effects, reproducible for replays and pixel tests.
+//! The free functions draw from a generator shared by the drawables of the
+//! current thread (e.g. `ParticleSystem::emit`), so after `set_seed` the same
+//! sequence of frames produces the same visuals. Use an own `Rng` for
+//! randomness which shouldn't shift what the drawables get.
+
+use crate::graphics::Color;
+use std::cell::RefCell;
+
+const DEFAULT_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+thread_local! {
+    static SHARED: RefCell<Rng> = RefCell::new(Rng::new(DEFAULT_SEED));
+}
+
+/// A xorshift64* generator. It's fast and looks random enough for
+/// visuals, but isn't suitable for anything security related.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed with a splitmix64 step, as xorshift needs a nonzero
+        // state and similar seeds would otherwise start out similar.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        Self { state: if z == 0 { DEFAULT_SEED } else { z } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A float from 0 up to, but not including, 1.
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A float from `min` up to `max`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+
+    /// A vector of length 1 pointing in a random direction.
+    pub fn unit_vec2(&mut self) -> [f32; 2] {
+        let angle = self.range(0.0, std::f32::consts::TAU);
+        [angle.cos(), angle.sin()]
+    }
+
+    /// An opaque color with random RGB components.
+    pub fn color(&mut self) -> Color {
+        Color::new(self.unit(), self.unit(), self.unit(), 1.0)
+    }
+}
+
+/// Restarts the shared generator of the current thread from `seed`.
+pub fn set_seed(seed: u64) {
+    SHARED.with(|rng| *rng.borrow_mut() = Rng::new(seed));
+}
+
+/// See `Rng::unit`.
+pub fn unit() -> f32 {
+    SHARED.with(|rng| rng.borrow_mut().unit())
+}
+
+/// See `Rng::range`.
+pub fn range(min: f32, max: f32) -> f32 {
+    SHARED.with(|rng| rng.borrow_mut().range(min, max))
+}
+
+/// See `Rng::unit_vec2`.
+pub fn unit_vec2() -> [f32; 2] {
+    SHARED.with(|rng| rng.borrow_mut().unit_vec2())
+}
+
+/// See `Rng::color`.
+pub fn color() -> Color {
+    SHARED.with(|rng| rng.borrow_mut().color())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(rng: &mut Rng) -> Vec<u64> {
+        (0..16).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        assert_eq!(sequence(&mut Rng::new(42)), sequence(&mut Rng::new(42)));
+        assert_ne!(sequence(&mut Rng::new(42)), sequence(&mut Rng::new(43)));
+    }
+
+    #[test]
+    fn zero_seed_works() {
+        let values = sequence(&mut Rng::new(0));
+
+        assert!(values.iter().all(|&value| value != 0));
+    }
+
+    #[test]
+    fn set_seed_restarts_the_shared_generator() {
+        set_seed(7);
+        let first: Vec<f32> = (0..8).map(|_| unit()).collect();
+        set_seed(7);
+        let second: Vec<f32> = (0..8).map(|_| unit()).collect();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn floats_stay_in_range() {
+        let mut rng = Rng::new(1);
+
+        for _ in 0..1000 {
+            let unit = rng.unit();
+            assert!((0.0..1.0).contains(&unit), "{}", unit);
+
+            let value = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&value), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::num::NonZeroU8;
+use wgpu::{
+    AddressMode, BindGroupLayoutEntry, CompareFunction, Device, FilterMode, Sampler, ShaderStage,
+};
+
+/// The highest anisotropy level wgpu accepts.
+const MAX_ANISOTROPY: u8 = 16;
+
+/// How a texture is sampled. wgpu validates that a sampler which filters
+/// linearly is only bound to a layout entry declared with `filtering: true`,
+/// next to a texture declared `filterable: true`, so bind group layouts for
+/// a sampler should be built with `texture_layout_entry` and
+/// `sampler_layout_entry` to keep the flags in sync. The same goes for
+/// comparison samplers, which need a comparison layout entry next to a
+/// depth texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub address_mode: AddressMode,
+
+    /// Anisotropic filtering level, sharpening textures seen at steep angles.
+    /// It's rounded down to a power of two up to 16, and only used for
+    /// trilinear sampling of mipmapped textures on devices with the
+    /// `SAMPLER_ANISOTROPY` feature. Otherwise sampling stays trilinear.
+    pub anisotropy_clamp: Option<NonZeroU8>,
+
+    /// Makes this a comparison sampler, for depth textures like shadow maps.
+    /// Sampling returns how many of the texels pass `compare` against the
+    /// reference value from the shader (e.g. with `sampler2DShadow`), instead
+    /// of the depth itself.
+    pub compare: Option<CompareFunction>,
+
+    /// The range of mip levels sampling may pick from, 0.0 being the full size
+    /// texture. Sampling normally picks the level matching how much the texture is
+    /// scaled down, clamping both ends to the same level forces it, e.g. 0.0 to keep
+    /// pixel art crisp. This version of wgpu has no LOD bias, narrowing the range
+    /// is the way to shift mip selection.
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self::smooth()
+    }
+}
+
+impl SamplerConfig {
+    /// Nearest neighbor sampling, which keeps hard pixel edges when scaled up.
+    pub fn pixel_art() -> Self {
+        Self {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            address_mode: AddressMode::ClampToEdge,
+            anisotropy_clamp: None,
+            compare: None,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+        }
+    }
+
+    /// Linear sampling between pixels and mip levels, for smooth scaling.
+    pub fn smooth() -> Self {
+        Self {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            address_mode: AddressMode::ClampToEdge,
+            anisotropy_clamp: None,
+            compare: None,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+        }
+    }
+
+    /// Smooth sampling with 16x anisotropic filtering where it's supported,
+    /// for mipmapped textures like floors seen at a steep angle.
+    pub fn anisotropic() -> Self {
+        Self { anisotropy_clamp: NonZeroU8::new(MAX_ANISOTROPY), ..Self::smooth() }
+    }
+
+    /// A comparison sampler for shadow maps. Linear filtering blends the results of
+    /// comparing the 2x2 nearest texels, which softens shadow edges for free.
+    /// `CompareFunction::LessEqual` is the usual choice, passing where the
+    /// fragment is no further from the light than the shadow map depth.
+    pub fn comparison(compare: CompareFunction) -> Self {
+        Self { mipmap_filter: FilterMode::Nearest, compare: Some(compare), ..Self::smooth() }
+    }
+
+    /// Only samples mip levels from `min` to `max`, see `lod_min_clamp`.
+    pub fn with_lod_clamp(self, min: f32, max: f32) -> Self {
+        Self { lod_min_clamp: min, lod_max_clamp: max.max(min), ..self }
+    }
+
+    /// Whether any of the filters blend between texels.
+    pub fn is_filtering(&self) -> bool {
+        [self.mag_filter, self.min_filter, self.mipmap_filter].contains(&FilterMode::Linear)
+    }
+
+    /// The anisotropy level to create the sampler with, `None` where it's not supported.
+    fn supported_anisotropy(&self, device: &Device) -> Option<NonZeroU8> {
+        let trilinear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == FilterMode::Linear);
+
+        if !trilinear || !device.features().contains(wgpu::Features::SAMPLER_ANISOTROPY) {
+            return None;
+        }
+
+        // Valid levels are 1, 2, 4, 8 and 16.
+        self.anisotropy_clamp.and_then(|level| {
+            let level = level.get().min(MAX_ANISOTROPY);
+            NonZeroU8::new(1 << (7 - level.leading_zeros()))
+        })
+    }
+
+    /// Creates the sampler. Anisotropy is dropped unless `has_mips`, as it
+    /// only picks between mip levels.
+    pub fn create_sampler(&self, device: &Device, has_mips: bool) -> Sampler {
+        let anisotropy_clamp = if has_mips { self.supported_anisotropy(device) } else { None };
+
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp,
+            compare: self.compare,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            ..Default::default()
+        })
+    }
+
+    /// The layout entry for a 2D texture sampled with this config, a float
+    /// texture or a depth texture for comparison samplers.
+    pub fn texture_layout_entry(
+        &self,
+        binding: u32,
+        visibility: ShaderStage,
+    ) -> BindGroupLayoutEntry {
+        let sample_type = match self.compare {
+            Some(_) => wgpu::TextureSampleType::Depth,
+            None => wgpu::TextureSampleType::Float { filterable: self.is_filtering() },
+        };
+
+        BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+
+    /// The layout entry for a sampler created with this config.
+    pub fn sampler_layout_entry(
+        &self,
+        binding: u32,
+        visibility: ShaderStage,
+    ) -> BindGroupLayoutEntry {
+        BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Sampler {
+                filtering: self.is_filtering(),
+                comparison: self.compare.is_some(),
+            },
+            count: None,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::{borrow::Cow, ops::BitOr};
+use wgpu::{Device, ShaderModule, ShaderModuleDescriptor};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    /// wgpu rejected the shader or a pipeline using it. Holds wgpu's error message.
+    Validation(String),
+
+    /// An `#ifdef` or `#ifndef` names a define which isn't one of the `ShaderFeatures`.
+    UnknownDefine { line: usize, name: String },
+
+    /// An `#else` or `#endif` without an `#ifdef`, or an `#ifdef` without an `#endif`.
+    UnbalancedDirective { line: usize },
+
+    /// A second `#else` for the same `#ifdef`.
+    DuplicateElse { line: usize },
+}
+
+/// A set of toggles for shader variants, checked with `#ifdef` in shader sources
+/// passed to `preprocess_shader`. Combine them with `|`, e.g. `TINT | OUTLINE`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderFeatures(u32);
+
+impl ShaderFeatures {
+    pub const NONE: ShaderFeatures = ShaderFeatures(0);
+
+    /// Multiply the output by a tint color.
+    pub const TINT: ShaderFeatures = ShaderFeatures(1 << 0);
+
+    /// Sample a signed distance field instead of coverage.
+    pub const SDF: ShaderFeatures = ShaderFeatures(1 << 1);
+
+    /// Draw an outline around the shape.
+    pub const OUTLINE: ShaderFeatures = ShaderFeatures(1 << 2);
+
+    /// The define name of each feature, as written after `#ifdef`.
+    const NAMES: &'static [(ShaderFeatures, &'static str)] = &[
+        (ShaderFeatures::TINT, "TINT"),
+        (ShaderFeatures::SDF, "SDF"),
+        (ShaderFeatures::OUTLINE, "OUTLINE"),
+    ];
+
+    /// The feature with the define `name`, `None` if there isn't one.
+    pub fn from_name(name: &str) -> Option<ShaderFeatures> {
+        Self::NAMES.iter().find(|(_, feature_name)| *feature_name == name).map(|(f, _)| *f)
+    }
+
+    pub fn contains(self, other: ShaderFeatures) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ShaderFeatures {
+    type Output = ShaderFeatures;
+
+    fn bitor(self, other: ShaderFeatures) -> ShaderFeatures {
+        ShaderFeatures(self.0 | other.0)
+    }
+}
+
+/// Resolves `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` lines in `source`
+/// against `features`, which may be nested. Every other line is kept as is, so
+/// this works for WGSL as well as GLSL. Removed lines are left empty so line
+/// numbers in compiler errors still match the source.
+pub fn preprocess_shader(source: &str, features: ShaderFeatures) -> Result<String, ShaderError> {
+    // Whether each open `#ifdef` block (and the ones around it) is included,
+    // and whether its `#else` was seen yet.
+    let mut included_stack: Vec<(bool, bool)> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut words = line.split_whitespace();
+        let outer_included = included_stack.last().map_or(true, |&(included, _)| included);
+
+        match words.next() {
+            Some(directive @ "#ifdef") | Some(directive @ "#ifndef") => {
+                let name = words.next().unwrap_or("");
+                let feature = ShaderFeatures::from_name(name).ok_or_else(|| {
+                    ShaderError::UnknownDefine { line: line_number, name: name.to_string() }
+                })?;
+
+                let enabled = features.contains(feature) == (directive == "#ifdef");
+                included_stack.push((outer_included && enabled, false));
+            },
+            Some("#else") => {
+                let (included, seen_else) = included_stack
+                    .pop()
+                    .ok_or(ShaderError::UnbalancedDirective { line: line_number })?;
+                if seen_else {
+                    return Err(ShaderError::DuplicateElse { line: line_number });
+                }
+
+                let outer_included = included_stack.last().map_or(true, |&(included, _)| included);
+                included_stack.push((outer_included && !included, true));
+            },
+            Some("#endif") => {
+                included_stack
+                    .pop()
+                    .ok_or(ShaderError::UnbalancedDirective { line: line_number })?;
+            },
+            _ if outer_included => output.push_str(line),
+            _ => {},
+        }
+
+        output.push('\n');
+    }
+
+    if included_stack.is_empty() {
+        Ok(output)
+    } else {
+        Err(ShaderError::UnbalancedDirective { line: source.lines().count() })
+    }
+}
+
+/// Preprocesses the WGSL `source` with `features` and compiles it, for building
+/// pipeline variants at runtime. Cache the pipelines with a `PipelineKey` with the
+/// same `features`, so each variant is only compiled once.
+pub async fn create_shader_variant(
+    device: &Device,
+    label: &str,
+    source: &str,
+    features: ShaderFeatures,
+) -> Result<ShaderModule, ShaderError> {
+    let source = preprocess_shader(source, features)?;
+
+    try_create_shader_module(
+        device,
+        &ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+            flags: wgpu::ShaderFlags::all(),
+        },
+    )
+    .await
+}
+
+/// Compiles `descriptor`, returning validation errors instead of letting
+/// wgpu panic, so a broken shader can be reported while the last good
+/// pipeline keeps being used.
+pub async fn try_create_shader_module(
+    device: &Device,
+    descriptor: &ShaderModuleDescriptor<'_>,
+) -> Result<ShaderModule, ShaderError> {
+    capture_validation_errors(device, || device.create_shader_module(descriptor)).await
+}
+
+/// Runs `create` inside a validation error scope. Errors in a scope are
+/// reported to it instead of the uncaptured error handler, which panics.
+pub(crate) async fn capture_validation_errors<T, F: FnOnce() -> T>(
+    device: &Device,
+    create: F,
+) -> Result<T, ShaderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+
+    match device.pop_error_scope().await {
+        Some(error) => Err(ShaderError::Validation(error.to_string())),
+        None => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NESTED: &str = "a\n#ifdef TINT\nb\n#ifndef SDF\nc\n#else\nd\n#endif\n#else\ne\n#endif\nf";
+
+    fn kept_lines(output: &str) -> Vec<&str> {
+        output.lines().filter(|line| !line.is_empty()).collect()
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let empty = preprocess_shader(NESTED, ShaderFeatures::NONE).unwrap();
+        let tint = preprocess_shader(NESTED, ShaderFeatures::TINT).unwrap();
+        let tint_sdf = preprocess_shader(NESTED, ShaderFeatures::TINT | ShaderFeatures::SDF);
+
+        assert_eq!(kept_lines(&empty), ["a", "e", "f"]);
+        assert_eq!(kept_lines(&tint), ["a", "b", "c", "f"]);
+        assert_eq!(kept_lines(&tint_sdf.unwrap()), ["a", "b", "d", "f"]);
+    }
+
+    #[test]
+    fn removed_lines_keep_line_numbers() {
+        let output = preprocess_shader(NESTED, ShaderFeatures::NONE).unwrap();
+
+        assert_eq!(output.lines().count(), NESTED.lines().count());
+        assert_eq!(output.lines().nth(9), Some("e"));
+    }
+
+    #[test]
+    fn ifndef_is_the_inverse_of_ifdef() {
+        let source = "#ifndef OUTLINE\nplain\n#endif";
+
+        let without_outline = preprocess_shader(source, ShaderFeatures::NONE).unwrap();
+        let with_outline = preprocess_shader(source, ShaderFeatures::OUTLINE).unwrap();
+
+        assert_eq!(kept_lines(&without_outline), ["plain"]);
+        assert!(kept_lines(&with_outline).is_empty());
+    }
+
+    #[test]
+    fn missing_endif_is_an_error() {
+        let result = preprocess_shader("#ifdef TINT\na\n#else\nb", ShaderFeatures::NONE);
+
+        assert!(matches!(result, Err(ShaderError::UnbalancedDirective { line: 4 })));
+    }
+
+    #[test]
+    fn unbalanced_endif_is_an_error() {
+        let result = preprocess_shader("a\n#endif", ShaderFeatures::NONE);
+
+        assert!(matches!(result, Err(ShaderError::UnbalancedDirective { line: 2 })));
+    }
+
+    #[test]
+    fn duplicate_else_is_an_error() {
+        let source = "#ifdef TINT\na\n#else\nb\n#else\nc\n#endif";
+        let result = preprocess_shader(source, ShaderFeatures::NONE);
+
+        assert!(matches!(result, Err(ShaderError::DuplicateElse { line: 5 })));
+    }
+
+    #[test]
+    fn unknown_define_is_an_error() {
+        let result = preprocess_shader("#ifdef NOPE\n#endif", ShaderFeatures::NONE);
+
+        assert!(matches!(result, Err(ShaderError::UnknownDefine { line: 1, .. })));
+    }
+}
@@ -0,0 +1,440 @@
+use crate::graphics::{
+    apply_scissor_rect, color_target, debug_marker, transform::TransformBinding, Camera2D, Color,
+    FrameEncoder, GraphicsDevice, PipelineBuilder, Rect,
+};
+use bytemuck::{Pod, Zeroable};
+use std::f32::consts::PI;
+use wgpu::RenderPipeline;
+use winit::dpi::PhysicalSize;
+
+const TAU: f32 = 2.0 * PI;
+
+/// The look of a loading spinner drawn with `ShapeRenderer::draw_spinner_styled`.
+#[derive(Debug, Clone, Copy)]
+pub struct SpinnerStyle {
+    /// How much of the ring the arc covers, in radians.
+    pub arc_length: f32,
+
+    /// Width of the ring in pixels.
+    pub thickness: f32,
+
+    /// Full rotations per second.
+    pub revolutions_per_second: f32,
+}
+
+impl Default for SpinnerStyle {
+    fn default() -> Self {
+        Self { arc_length: PI * 1.5, thickness: 4.0, revolutions_per_second: 1.0 }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct ShapeVertex {
+    /// Position in world space.
+    pos: [f32; 2],
+    color: [f32; 4],
+
+    /// Offset from the center of `ring`.
+    local: [f32; 2],
+    ring: Ring,
+}
+
+/// The part of a ring a shape is cut down to by the signed distance field in
+/// shape.frag, for arcs and spinners. Solid shapes have a `half_thickness`
+/// of 0.0, which turns the ring off.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct Ring {
+    radius: f32,
+    half_thickness: f32,
+
+    /// Where the arc starts, in radians from the positive X axis, from 0.0 to TAU.
+    start_angle: f32,
+
+    /// How far the arc goes clockwise from `start_angle`, from 0.0 to TAU.
+    sweep: f32,
+}
+
+impl Ring {
+    fn arc(radius: f32, thickness: f32, start_angle: f32, sweep: f32) -> Self {
+        // A negative sweep is the same arc, going clockwise from its other end.
+        let (start_angle, sweep) =
+            if sweep < 0.0 { (start_angle + sweep, -sweep) } else { (start_angle, sweep) };
+
+        Self {
+            radius,
+            half_thickness: (thickness / 2.0).max(0.0),
+            start_angle: start_angle.rem_euclid(TAU),
+            sweep: sweep.min(TAU),
+        }
+    }
+
+    /// The arc of a spinner at `time` seconds.
+    fn spinner(radius: f32, time: f32, style: &SpinnerStyle) -> Self {
+        let start_angle = (time * style.revolutions_per_second).fract() * TAU;
+        Self::arc(radius, style.thickness, start_angle, style.arc_length)
+    }
+}
+
+/// The corners of a line from `a` to `b`, `thickness` wide, in counter-clockwise
+/// order. `None` if the line has no length.
+fn line_corners(a: [f32; 2], b: [f32; 2], thickness: f32) -> Option<[[f32; 2]; 4]> {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+
+    if length == 0.0 {
+        return None;
+    }
+
+    // Perpendicular to the line, half the thickness long.
+    let nx = -dy / length * thickness / 2.0;
+    let ny = dx / length * thickness / 2.0;
+
+    Some([
+        [a[0] + nx, a[1] + ny],
+        [a[0] - nx, a[1] - ny],
+        [b[0] - nx, b[1] - ny],
+        [b[0] + nx, b[1] + ny],
+    ])
+}
+
+/// The top, bottom, left and right edges of a rectangle's outline, which don't
+/// overlap so translucent outlines have no darker corners. The thickness is
+/// limited to half of the rectangle's size.
+fn outline_edges(rect: Rect, thickness: f32) -> [Rect; 4] {
+    let t = thickness.min(rect.w / 2.0).min(rect.h / 2.0);
+    let inner_height = rect.h - 2.0 * t;
+
+    [
+        Rect::new(rect.x, rect.y, rect.w, t),
+        Rect::new(rect.x, rect.y + rect.h - t, rect.w, t),
+        Rect::new(rect.x, rect.y + t, t, inner_height),
+        Rect::new(rect.x + rect.w - t, rect.y + t, t, inner_height),
+    ]
+}
+
+/// Draws solid colored lines and rectangles, mostly for debug overlays.
+/// Shapes are queued up with the `draw_*` functions during a frame and
+/// then drawn together with a single draw call in `render`.
+pub struct ShapeRenderer {
+    vertices: Vec<ShapeVertex>,
+    transform: TransformBinding,
+    pipeline: RenderPipeline,
+}
+
+impl ShapeRenderer {
+    pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        let device = graphics_device.device();
+
+        let transform = TransformBinding::new(graphics_device, "Shape Transform");
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &transform.bind_group_layouts(),
+            push_constant_ranges: transform.push_constant_ranges(),
+        });
+
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: (std::mem::size_of::<ShapeVertex>()) as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0 => Float32x2,
+                1 => Float32x4,
+                2 => Float32x2,
+                3 => Float32x4,
+            ],
+        }];
+
+        let vs_module = if transform.uses_push_constants() {
+            device.create_shader_module(&include_shader!("shape_push.vert"))
+        } else {
+            device.create_shader_module(&include_shader!("shape.vert"))
+        };
+        let fs_module = device.create_shader_module(&include_shader!("shape.frag"));
+
+        let format = graphics_device.color_format();
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("shape")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING)))
+            .topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device);
+
+        Self { vertices: Vec::new(), transform, pipeline }
+    }
+
+    /// Queues a line from `a` to `b`, `thickness` pixels wide.
+    pub fn draw_line(&mut self, a: [f32; 2], b: [f32; 2], color: Color, thickness: f32) {
+        if let Some(corners) = line_corners(a, b, thickness) {
+            self.push_quad(corners, color);
+        }
+    }
+
+    /// Queues a filled rectangle.
+    pub fn draw_rect(&mut self, rect: Rect, color: Color) {
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.w, rect.y + rect.h);
+
+        self.push_quad([[left, top], [left, bottom], [right, bottom], [right, top]], color);
+    }
+
+    /// Queues a filled rectangle rotated `rotation` radians clockwise around its
+    /// `pivot`, `[0.5, 0.5]` for the center or e.g. `[0.0, 0.0]` for the top left.
+    /// It's batched with everything else, so spinning shapes cost no extra draw calls.
+    pub fn draw_rect_rotated(&mut self, rect: Rect, color: Color, rotation: f32, pivot: [f32; 2]) {
+        self.push_quad(rect.rotated_corners(rotation, pivot), color);
+    }
+
+    /// Queues the outline of a rectangle. The outline is drawn
+    /// inside of `rect`, `thickness` pixels wide.
+    pub fn draw_rect_outline(&mut self, rect: Rect, color: Color, thickness: f32) {
+        for edge in &outline_edges(rect, thickness) {
+            self.draw_rect(*edge, color);
+        }
+    }
+
+    /// Queues an arc (part of a ring) centered on `center`. Angles are in radians,
+    /// starting from the positive X axis and increasing clockwise on screen.
+    /// The ring is `thickness` pixels wide, centered on `radius`. It's drawn as a
+    /// single antialiased quad, cut down to the arc by shape.frag.
+    pub fn draw_arc(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        start_angle: f32,
+        sweep: f32,
+        color: Color,
+        thickness: f32,
+    ) {
+        self.push_ring(center, Ring::arc(radius, thickness, start_angle, sweep), color);
+    }
+
+    /// Queues a loading spinner with the default style.
+    /// `time` is in seconds, and drives the rotation of the arc.
+    pub fn draw_spinner(&mut self, center: [f32; 2], radius: f32, color: Color, time: f32) {
+        self.draw_spinner_styled(center, radius, color, time, SpinnerStyle::default());
+    }
+
+    pub fn draw_spinner_styled(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        color: Color,
+        time: f32,
+        style: SpinnerStyle,
+    ) {
+        self.push_ring(center, Ring::spinner(radius, time, &style), color);
+    }
+
+    /// Pushes two triangles for a quad with corners in counter-clockwise order.
+    fn push_quad(&mut self, corners: [[f32; 2]; 4], color: Color) {
+        let color = color.to_f32_array();
+
+        for i in &[0, 1, 2, 0, 2, 3] {
+            let (local, ring) = ([0.0, 0.0], Ring::default());
+            self.vertices.push(ShapeVertex { pos: corners[*i], color, local, ring });
+        }
+    }
+
+    /// Pushes a quad covering `ring` around `center`, with a pixel to spare
+    /// for antialiasing.
+    fn push_ring(&mut self, center: [f32; 2], ring: Ring, color: Color) {
+        if ring.half_thickness == 0.0 || ring.sweep == 0.0 {
+            return;
+        }
+
+        let color = color.to_f32_array();
+        let extent = ring.radius + ring.half_thickness + 1.0;
+        let corners = [[-extent, -extent], [-extent, extent], [extent, extent], [extent, -extent]];
+
+        for i in &[0, 1, 2, 0, 2, 3] {
+            let local = corners[*i];
+            let pos = [center[0] + local[0], center[1] + local[1]];
+            self.vertices.push(ShapeVertex { pos, color, local, ring });
+        }
+    }
+
+    /// Draws all queued shapes with a single draw call, and clears the queue.
+    pub fn render(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let vertices = frame_encoder.write_dynamic(bytemuck::cast_slice(&self.vertices));
+
+        let view_proj =
+            camera.view_projection_matrix(window_size.width as f32, window_size.height as f32);
+        self.transform.write(frame_encoder.queue(), &view_proj);
+
+        let scissor_rect = frame_encoder.scissor_rect();
+        let dynamic_buffers = &*frame_encoder.dynamic_buffers;
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shapes"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        self.transform.bind(&mut rpass, &view_proj);
+        rpass.set_vertex_buffer(0, dynamic_buffers.slice(vertices));
+
+        debug_marker(&mut rpass, "shapes");
+        if apply_scissor_rect(&mut rpass, scissor_rect) {
+            rpass.draw(0..self.vertices.len() as u32, 0..1);
+            stats.record_draw(self.vertices.len() as u32 / 3);
+        }
+
+        self.vertices.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether shape.frag covers `local`, leaving out the antialiasing.
+    fn covers(ring: &Ring, local: [f32; 2]) -> bool {
+        let ring_dist = (local[0].hypot(local[1]) - ring.radius).abs() - ring.half_thickness;
+        let angle = (local[1].atan2(local[0]) - ring.start_angle).rem_euclid(TAU);
+        ring_dist <= 0.0 && angle <= ring.sweep
+    }
+
+    /// The angle halfway along the covered part of the ring, sampled around its radius.
+    fn arc_middle(ring: &Ring) -> f32 {
+        let (mut x, mut y) = (0.0, 0.0);
+        for i in 0..3600 {
+            let angle = i as f32 / 3600.0 * TAU;
+            let (sin, cos) = angle.sin_cos();
+            if covers(ring, [cos * ring.radius, sin * ring.radius]) {
+                x += cos;
+                y += sin;
+            }
+        }
+
+        f32::atan2(y, x).rem_euclid(TAU)
+    }
+
+    /// How far the covered part of the ring goes along the ray at `angle`.
+    fn covered_width(ring: &Ring, angle: f32) -> f32 {
+        let step = 0.01;
+        let (sin, cos) = angle.sin_cos();
+        let covered = (0..10_000).filter(|i| {
+            let r = *i as f32 * step;
+            covers(ring, [cos * r, sin * r])
+        });
+
+        covered.count() as f32 * step
+    }
+
+    fn area(rect: &Rect) -> f32 {
+        rect.w * rect.h
+    }
+
+    #[test]
+    fn line_is_as_thick_as_requested() {
+        let corners = line_corners([10.0, 10.0], [20.0, 10.0], 4.0).unwrap();
+
+        assert_eq!(corners, [[10.0, 12.0], [10.0, 8.0], [20.0, 8.0], [20.0, 12.0]]);
+    }
+
+    #[test]
+    fn diagonal_line_is_widened_perpendicular_to_it() {
+        let [a, b, c, _] = line_corners([0.0, 0.0], [3.0, 4.0], 2.0).unwrap();
+
+        let width = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+        let along = [c[0] - b[0], c[1] - b[1]];
+        let across = [a[0] - b[0], a[1] - b[1]];
+        assert!((width - 2.0).abs() < 1e-5);
+        assert!((along[0] * across[0] + along[1] * across[1]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn zero_length_line_is_not_drawn() {
+        assert_eq!(line_corners([5.0, 5.0], [5.0, 5.0], 2.0), None);
+    }
+
+    #[test]
+    fn outline_edges_cover_the_border_once() {
+        let rect = Rect::new(10.0, 20.0, 100.0, 50.0);
+        let edges = outline_edges(rect, 2.0);
+
+        // The border of a 100x50 rect minus its 96x46 inside, without overlaps.
+        let covered: f32 = edges.iter().map(area).sum();
+        assert_eq!(covered, 100.0 * 50.0 - 96.0 * 46.0);
+        for edge in &edges {
+            assert!(edge.x >= rect.x && edge.y >= rect.y);
+            assert!(edge.x + edge.w <= rect.x + rect.w && edge.y + edge.h <= rect.y + rect.h);
+        }
+    }
+
+    #[test]
+    fn thick_outline_fills_small_rect() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 4.0);
+        let covered: f32 = outline_edges(rect, 8.0).iter().map(area).sum();
+
+        assert_eq!(covered, area(&rect));
+    }
+
+    #[test]
+    fn spinner_rotates_with_time() {
+        let style = SpinnerStyle { revolutions_per_second: 0.5, ..SpinnerStyle::default() };
+
+        let start = arc_middle(&Ring::spinner(20.0, 0.0, &style));
+        let later = arc_middle(&Ring::spinner(20.0, 0.25, &style));
+
+        // Half a revolution per second, so an eighth of a turn after 0.25 seconds.
+        let rotation = (later - start).rem_euclid(TAU);
+        assert!((rotation - TAU / 8.0).abs() < 0.01, "rotated by {}", rotation);
+    }
+
+    #[test]
+    fn spinner_arc_has_the_configured_length_and_thickness() {
+        let style =
+            SpinnerStyle { arc_length: PI / 2.0, thickness: 6.0, revolutions_per_second: 1.0 };
+        let ring = Ring::spinner(20.0, 0.3, &style);
+        let middle = arc_middle(&ring);
+
+        assert!((covered_width(&ring, middle) - 6.0).abs() < 0.05);
+
+        // The arc ends a quarter turn from its middle, past which the ring is masked out.
+        assert!(covered_width(&ring, middle + PI / 4.0 - 0.01) > 0.0);
+        assert_eq!(covered_width(&ring, middle + PI / 4.0 + 0.01), 0.0);
+        assert!(covered_width(&ring, middle - PI / 4.0 + 0.01) > 0.0);
+        assert_eq!(covered_width(&ring, middle - PI / 4.0 - 0.01), 0.0);
+    }
+
+    #[test]
+    fn negative_sweep_goes_counter_clockwise() {
+        let ring = Ring::arc(10.0, 2.0, 0.0, -PI / 2.0);
+
+        assert!(covers(&ring, [0.0, -10.0 + 0.1]));
+        assert!(!covers(&ring, [0.0, 10.0 - 0.1]));
+        assert_eq!(ring.start_angle, 1.5 * PI);
+    }
+
+    #[test]
+    fn shape_vertex_matches_attribute_offsets() {
+        let vertex = ShapeVertex::zeroed();
+
+        assert_eq!(std::mem::size_of::<Ring>(), 16);
+        assert_eq!(std::mem::size_of::<ShapeVertex>(), 48);
+        assert_eq!(bytemuck::offset_of!(vertex, ShapeVertex, pos), 0);
+        assert_eq!(bytemuck::offset_of!(vertex, ShapeVertex, color), 8);
+        assert_eq!(bytemuck::offset_of!(vertex, ShapeVertex, local), 24);
+        assert_eq!(bytemuck::offset_of!(vertex, ShapeVertex, ring), 32);
+    }
+}
@@ -1,74 +1,192 @@
-use crate::graphics::{FrameEncoder, GraphicsDevice};
+use crate::graphics::{Color, FrameEncoder, GraphicsDevice, Rect};
 use fontdue::{
-    layout::{CoordinateSystem, HorizontalAlign, Layout, LayoutSettings, TextStyle, VerticalAlign},
+    layout::{
+        CoordinateSystem, GlyphPosition, HorizontalAlign, Layout, LayoutSettings,
+        TextStyle as LayoutStyle, VerticalAlign,
+    },
     Font as FontdueFont, FontSettings, Metrics,
 };
 use gpu::GlyphPainter;
+pub use gpu::SdfParams;
 use rect_packer::Packer;
 use std::{
-    borrow::Borrow,
-    collections::{hash_map::Entry, HashMap},
+    borrow::{Borrow, Cow},
+    collections::HashMap,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
 };
+use unicode_segmentation::UnicodeSegmentation;
 use winit::dpi::PhysicalSize;
 
 const BITMAP_WIDTH: u32 = 4096;
 const BITMAP_HEIGHT: u32 = 4096;
 const BORDER_PADDING: u32 = 2;
 const RECTANGLE_PADDING: u32 = 2;
+const ELLIPSIS: &str = "\u{2026}";
 
-pub const WHITE: Color = Color::new(255, 255, 255, 255);
+/// How far in pixels the distance field extends beyond a glyph's
+/// edges when rendering in SDF mode. This caps the outline and glow widths.
+const SDF_PADDING: u32 = 8;
 
+/// The size in pixels SDF glyphs are rasterized at. Each glyph is cached
+/// once at this size and scaled to whatever size text is drawn at.
+const SDF_FONT_SIZE: u32 = 64;
+
+/// Identifies a font loaded at runtime with `TextSystem::load_font`. It only
+/// refers to a font of the text system which loaded it, others skip text using it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontId {
+    /// The `FontData::collection` of the text system the font was loaded into.
+    collection: usize,
+    index: usize,
+}
+
+/// Hands out `FontData::collection`s.
+static NEXT_FONT_COLLECTION: AtomicUsize = AtomicUsize::new(0);
+
+/// A font at a size in logical pixels. Text is rasterized at the
+/// size multiplied by the window's scale factor, so it stays crisp on HiDPI displays.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Font {
     SpaceMono400(u32),
+    Custom(FontId, u32),
+}
+
+/// A font without a size, which glyphs are cached per.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FontFace {
+    SpaceMono400,
+    Custom(FontId),
 }
 
 impl Font {
+    fn face(&self) -> FontFace {
+        match self {
+            Font::SpaceMono400(_) => FontFace::SpaceMono400,
+            Font::Custom(font_id, _) => FontFace::Custom(*font_id),
+        }
+    }
+
     fn size(&self) -> u32 {
         use Font::*;
 
         match self {
             SpaceMono400(size) => *size,
+            Custom(_, size) => *size,
         }
     }
 
-    fn font_bytes(&self) -> &'static [u8] {
+    fn with_size(self, size: u32) -> Self {
         match self {
-            Font::SpaceMono400(_) => include_bytes!("../../../resources/fonts/space_mono_400.ttf"),
+            Font::SpaceMono400(_) => Font::SpaceMono400(size),
+            Font::Custom(font_id, _) => Font::Custom(font_id, size),
+        }
+    }
+
+    /// The font glyphs of this font are rasterized and cached in: the font
+    /// itself for bitmap text, `SDF_FONT_SIZE` for any size of SDF text.
+    fn cached(self, sdf: bool) -> Self {
+        if sdf {
+            self.with_size(SDF_FONT_SIZE)
+        } else {
+            self
+        }
+    }
+
+    /// The same font with its size multiplied by `scale_factor`.
+    fn scaled(self, scale_factor: f64) -> Self {
+        use Font::*;
+
+        let scale = |size: u32| ((size as f64 * scale_factor).round() as u32).max(1);
+
+        match self {
+            SpaceMono400(size) => SpaceMono400(scale(size)),
+            Custom(font_id, size) => Custom(font_id, scale(size)),
         }
     }
 }
 
+#[derive(Debug)]
+pub enum FontLoadError {
+    /// The bytes could not be parsed as a TrueType or OpenType font.
+    InvalidFontData(&'static str),
+}
+
 struct FontData {
     /// A map of Fonts to their indices in `rasterizers` and `fonts`.
     rasterizer_indices: HashMap<Font, usize>,
     rasterizers: Vec<FontdueFont>,
     fonts: Vec<Font>,
+
+    /// The raw font file data of fonts loaded at runtime, indexed by `FontId::index`.
+    custom_font_bytes: Vec<Vec<u8>>,
+
+    /// Tells apart the `FontId`s of different text systems.
+    collection: usize,
 }
 
 impl FontData {
     fn new() -> Self {
-        FontData { rasterizer_indices: HashMap::new(), rasterizers: Vec::new(), fonts: Vec::new() }
+        FontData {
+            rasterizer_indices: HashMap::new(),
+            rasterizers: Vec::new(),
+            fonts: Vec::new(),
+            custom_font_bytes: Vec::new(),
+            collection: NEXT_FONT_COLLECTION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn load_font(&mut self, bytes: &[u8]) -> Result<FontId, FontLoadError> {
+        // Parse once up front so malformed data is reported here instead of
+        // panicking later when a rasterizer is created for a specific size.
+        FontdueFont::from_bytes(bytes, FontSettings::default())
+            .map_err(FontLoadError::InvalidFontData)?;
+
+        let font_id = FontId { collection: self.collection, index: self.custom_font_bytes.len() };
+        self.custom_font_bytes.push(bytes.to_vec());
+
+        Ok(font_id)
+    }
+
+    /// `None` for fonts of another text system.
+    fn font_bytes(&self, font: &Font) -> Option<&[u8]> {
+        match font {
+            Font::SpaceMono400(_) => {
+                Some(include_bytes!("../../../resources/fonts/space_mono_400.ttf"))
+            },
+            Font::Custom(FontId { collection, index }, _) if *collection == self.collection => {
+                self.custom_font_bytes.get(*index).map(Vec::as_slice)
+            },
+            Font::Custom(..) => None,
+        }
     }
 
     /// Creates and stores a rasterizer for this Font if one doesn't already exist.
-    fn create_rasterizer(&mut self, font: Font) {
+    /// Returns false if `font` wasn't loaded by this text system.
+    fn create_rasterizer(&mut self, font: Font) -> bool {
         // Asserting this as it otherwise causes a sudden segfault.
         assert!(font.size() > 0, "expecting a positive font size");
 
-        if let Entry::Vacant(entry) = self.rasterizer_indices.entry(font) {
-            let font_index = self.rasterizers.len();
+        if !self.rasterizer_indices.contains_key(&font) {
+            let font_bytes = match self.font_bytes(&font) {
+                Some(font_bytes) => font_bytes,
+                None => return false,
+            };
 
+            // The bytes were parsed successfully in `load_font`.
             let rasterizer = FontdueFont::from_bytes(
-                font.font_bytes(),
+                font_bytes,
                 FontSettings { scale: font.size() as f32, ..FontSettings::default() },
             )
             .unwrap();
 
+            let font_index = self.rasterizers.len();
             self.rasterizers.push(rasterizer);
             self.fonts.push(font);
-            entry.insert(font_index);
+            self.rasterizer_indices.insert(font, font_index);
         }
+
+        true
     }
 
     fn rasterizer_for_font(&self, font: &Font) -> Option<&FontdueFont> {
@@ -122,17 +240,48 @@ pub struct CharacterMetadata {
     texture_y: f32,      // Texture space
     texture_width: f32,  // Texture space
     texture_height: f32, // Texture space
+
+    /// Padding in pixels around the glyph bitmap in the texture
+    /// (the distance field spread in SDF mode, otherwise 0).
+    padding: u32,
+
+    /// The atlas space reserved for the glyph, in pixels. This can be larger
+    /// than the glyph when it took over the slot of an evicted one.
+    slot: rect_packer::Rect,
+
+    /// The `FrameEncoder::frame_index` the glyph was last drawn in.
+    last_used: u64,
+}
+
+/// Effects drawn behind a `StyledText`, for keeping it readable over busy
+/// backgrounds. Widths and offsets are in logical pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// Outline color and width. SDF text gets a true outline from the distance
+    /// field, limited to `SDF_PADDING` pixels. Bitmap text is drawn offset in
+    /// the outline color in 8 directions, which works for thin outlines.
+    pub outline: Option<(Color, f32)>,
+
+    /// Shadow color and `[x, y]` offset, positive Y is down.
+    pub shadow: Option<(Color, [f32; 2])>,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct StyledText<'a> {
     pub text: &'a str,
     pub font: Font,
     pub color: Color,
+    pub style: TextStyle,
 }
 
 impl<'a> StyledText<'a> {
     pub fn default_styling(text: &'a str) -> Self {
-        StyledText { text, font: Font::SpaceMono400(60), color: WHITE }
+        StyledText {
+            text,
+            font: Font::SpaceMono400(60),
+            color: Color::WHITE,
+            style: TextStyle::default(),
+        }
     }
 }
 
@@ -141,7 +290,7 @@ impl<'a> StyledText<'a> {
 ///    End   = bottom of the text box aligned to the Y coord
 /// X: Start = left side of the text box aligned to the X coord
 ///    End   = right side of the text box aligned to the X coord
-/// Units are in pixels.
+/// Units are in logical pixels, like font sizes.
 #[derive(Debug)]
 pub enum AxisAlign {
     Start(i32),
@@ -150,53 +299,122 @@ pub enum AxisAlign {
     WindowCenter,
 }
 
+impl AxisAlign {
+    fn scaled(self, scale_factor: f64) -> Self {
+        let scale = |offset: i32| (offset as f64 * scale_factor).round() as i32;
+
+        match self {
+            AxisAlign::Start(offset) => AxisAlign::Start(scale(offset)),
+            AxisAlign::End(offset) => AxisAlign::End(scale(offset)),
+            AxisAlign::Center(offset) => AxisAlign::Center(scale(offset)),
+            AxisAlign::WindowCenter => AxisAlign::WindowCenter,
+        }
+    }
+}
+
 impl Default for AxisAlign {
     fn default() -> Self {
         AxisAlign::Start(0)
     }
 }
 
+/// What to do with text which is wider than the max width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wrap onto the next line.
+    Wrap,
+
+    /// Cut the text off at the last character that fits.
+    Clip,
+
+    /// Cut the text off and append an ellipsis ("Some long lab…"), so
+    /// that the result including the ellipsis fits. If not even the
+    /// ellipsis fits, nothing is drawn.
+    Ellipsis,
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow::Wrap
+    }
+}
+
 /// Describes alignment for a block of text. Max width
 /// and height are optional and default to the window width
-/// and height.
+/// and height. Offsets and max sizes are in logical pixels,
+/// while the bounds they're relative to are in physical pixels.
 #[derive(Debug, Default)]
 pub struct TextAlignment {
     pub x: AxisAlign,
     pub y: AxisAlign,
     pub max_width: Option<u32>,
     pub max_height: Option<u32>,
+    pub overflow: Overflow,
+
+    /// Draws glyphs at their fractional pen positions, and keeps the fractional
+    /// part of the bounds' x position, instead of snapping glyphs to whole pixels,
+    /// so scrolling or animated text moves smoothly.
+    /// Glyphs between pixels are bilinearly filtered, so they're a little softer.
+    pub subpixel: bool,
 }
 
 impl TextAlignment {
     pub fn new(x: AxisAlign, y: AxisAlign) -> Self {
-        Self { x, y, max_width: None, max_height: None }
+        Self { x, y, ..Self::default() }
     }
 
     pub fn left_top(x: i32, y: i32) -> Self {
-        Self { x: AxisAlign::Start(x), y: AxisAlign::Start(y), max_width: None, max_height: None }
+        Self { x: AxisAlign::Start(x), y: AxisAlign::Start(y), ..Self::default() }
     }
 
-    fn into_layout_settings(self, window_size: PhysicalSize<u32>) -> LayoutSettings {
-        let window_width = window_size.width as i32;
-        let window_height = window_size.height as i32;
-        let max_width = self.max_width.unwrap_or(window_width as u32) as i32;
-        let max_height = self.max_height.unwrap_or(window_height as u32) as i32;
+    /// The same alignment with its offsets and max sizes multiplied by `scale_factor`.
+    fn scaled(self, scale_factor: f64) -> Self {
+        let scale = |size: u32| (size as f64 * scale_factor).round() as u32;
+
+        Self {
+            x: self.x.scaled(scale_factor),
+            y: self.y.scaled(scale_factor),
+            max_width: self.max_width.map(scale),
+            max_height: self.max_height.map(scale),
+            ..self
+        }
+    }
+
+    /// How far right of the whole pixel position lines are drawn, the part
+    /// of `bounds.x` which `into_layout_settings` drops. None without `subpixel`.
+    fn subpixel_offset(&self, bounds: Rect) -> Option<f32> {
+        if self.subpixel {
+            Some(bounds.x - (bounds.x as i32) as f32)
+        } else {
+            None
+        }
+    }
+
+    /// Computes layout settings with all alignments relative to `bounds`
+    /// (usually the window, or the safe area for HUD elements).
+    fn into_layout_settings(self, bounds: Rect) -> LayoutSettings {
+        let bounds_x = bounds.x as i32;
+        let bounds_y = bounds.y as i32;
+        let bounds_width = bounds.w as i32;
+        let bounds_height = bounds.h as i32;
+        let max_width = self.max_width.unwrap_or(bounds_width as u32) as i32;
+        let max_height = self.max_height.unwrap_or(bounds_height as u32) as i32;
 
         let (x, horizontal_align) = match self.x {
-            AxisAlign::Start(x) => (x, HorizontalAlign::Left),
-            AxisAlign::End(x) => (window_width - x - max_width, HorizontalAlign::Right),
-            AxisAlign::Center(x) => (x - (max_width / 2), HorizontalAlign::Center),
+            AxisAlign::Start(x) => (bounds_x + x, HorizontalAlign::Left),
+            AxisAlign::End(x) => (bounds_x + bounds_width - x - max_width, HorizontalAlign::Right),
+            AxisAlign::Center(x) => (bounds_x + x - (max_width / 2), HorizontalAlign::Center),
             AxisAlign::WindowCenter => {
-                ((window_width / 2) - (max_width / 2), HorizontalAlign::Center)
+                (bounds_x + (bounds_width / 2) - (max_width / 2), HorizontalAlign::Center)
             },
         };
 
         let (y, vertical_align) = match self.y {
-            AxisAlign::Start(y) => (y, VerticalAlign::Top),
-            AxisAlign::End(y) => (window_height - y - max_height, VerticalAlign::Bottom),
-            AxisAlign::Center(y) => (y - (max_height / 2), VerticalAlign::Middle),
+            AxisAlign::Start(y) => (bounds_y + y, VerticalAlign::Top),
+            AxisAlign::End(y) => (bounds_y + bounds_height - y - max_height, VerticalAlign::Bottom),
+            AxisAlign::Center(y) => (bounds_y + y - (max_height / 2), VerticalAlign::Middle),
             AxisAlign::WindowCenter => {
-                ((window_height / 2) - (max_height / 2), VerticalAlign::Middle)
+                (bounds_y + (bounds_height / 2) - (max_height / 2), VerticalAlign::Middle)
             },
         };
 
@@ -212,15 +430,160 @@ impl TextAlignment {
     }
 }
 
+/// Shortens `text_elements` so they fit on a single line of `max_width` pixels,
+/// according to `overflow`, measuring text with `text_width`. The suffix takes on
+/// the style of the last character kept, so it's measured in the font it's drawn in.
+fn truncate_to_width<'a>(
+    text_elements: &mut Vec<StyledText<'a>>,
+    max_width: f32,
+    overflow: Overflow,
+    text_width: impl Fn(&str, Font) -> f32,
+) {
+    let suffix = match overflow {
+        Overflow::Wrap => return,
+        Overflow::Clip => "",
+        Overflow::Ellipsis => ELLIPSIS,
+    };
+
+    // For every character, its element, where it ends in the element's text, and
+    // the width of the line up to and including it.
+    let mut ends = Vec::new();
+    let mut total_width = 0.0;
+    for (element_index, element) in text_elements.iter().enumerate() {
+        for (i, c) in element.text.char_indices() {
+            let end = i + c.len_utf8();
+            total_width += text_width(&element.text[i..end], element.font);
+            ends.push((element_index, end, total_width));
+        }
+    }
+
+    if total_width <= max_width || ends.is_empty() {
+        return;
+    }
+
+    // When no character is kept, the suffix is styled like the first one.
+    let (first_element, _, _) = ends[0];
+    let kept = (0..ends.len()).rev().find_map(|kept| {
+        let (element_index, end, used_width) = match kept {
+            0 => (first_element, 0, 0.0),
+            _ => ends[kept - 1],
+        };
+
+        let suffix_width = text_width(suffix, text_elements[element_index].font);
+        (used_width + suffix_width <= max_width).then(|| (element_index, end))
+    });
+
+    let (element_index, end) = match kept {
+        Some(kept) => kept,
+        None => {
+            // Not even the suffix fits.
+            text_elements.clear();
+            return;
+        },
+    };
+
+    text_elements.truncate(element_index + 1);
+    let last = &mut text_elements[element_index];
+    last.text = &last.text[..end];
+
+    let style = *last;
+    text_elements.push(StyledText { text: suffix, ..style });
+    text_elements.retain(|element| !element.text.is_empty());
+}
+
+/// Groups the rects of the characters of `text` by grapheme, for
+/// `TextSystem::accessible_layout`. `char_rect` is called for each character in
+/// order, and returns `None` for ones without a glyph. Graphemes without any
+/// rect are merged into the preceding one, or get `origin` at the start.
+fn grapheme_clusters(
+    text: &str,
+    origin: Rect,
+    mut char_rect: impl FnMut(char) -> Option<Rect>,
+) -> Vec<(Range<usize>, Rect)> {
+    let mut clusters: Vec<(Range<usize>, Rect)> = Vec::new();
+
+    for (start, grapheme) in text.grapheme_indices(true) {
+        let range = start..start + grapheme.len();
+        let rect = grapheme.chars().fold(None, |rect: Option<Rect>, c| match char_rect(c) {
+            Some(char_rect) => Some(rect.map_or(char_rect, |rect| rect.union(&char_rect))),
+            None => rect,
+        });
+
+        match (rect, clusters.last_mut()) {
+            (Some(rect), _) => clusters.push((range, rect)),
+            (None, Some((last_range, _))) => last_range.end = range.end,
+            (None, None) => clusters.push((range, origin)),
+        }
+    }
+
+    clusters
+}
+
+/// How far right of their fontdue positions `glyphs` are drawn. fontdue snaps
+/// glyphs to whole pixels, so for subpixel text each glyph is moved to its
+/// unrounded pen position, the sum of the advances before it on its line, and
+/// then by `subpixel_offset`. Without an offset glyphs stay where they are.
+fn glyph_offsets(
+    subpixel_offset: Option<f32>,
+    glyphs: &[GlyphPosition<usize>],
+    fonts: &[FontdueFont],
+) -> Vec<f32> {
+    let line_offset = match subpixel_offset {
+        Some(line_offset) => line_offset,
+        None => return vec![0.0; glyphs.len()],
+    };
+
+    let mut pen_x = 0.0;
+    let mut line_baseline: Option<f32> = None;
+
+    glyphs
+        .iter()
+        .map(|glyph| {
+            let metrics = fonts[glyph.key.font_index].metrics(glyph.key.c, glyph.key.px);
+            let xmin = metrics.xmin as f32;
+            let baseline = glyph.y + glyph.height as f32 + metrics.ymin as f32;
+
+            // Each line starts where fontdue put its first glyph.
+            if line_baseline.map_or(true, |line_baseline| (line_baseline - baseline).abs() > 0.5) {
+                pen_x = glyph.x - xmin;
+                line_baseline = Some(baseline);
+            }
+
+            let offset = pen_x + xmin - glyph.x + line_offset;
+            pen_x += metrics.advance_width;
+            offset
+        })
+        .collect()
+}
+
+/// Returns `bitmap`, `width` pixels wide, at the top left of a cleared bitmap
+/// the size of `slot`.
+fn fill_slot(bitmap: &[u8], width: usize, slot: rect_packer::Rect) -> Cow<[u8]> {
+    let (slot_width, slot_height) = (slot.width as usize, slot.height as usize);
+    if bitmap.len() == slot_width * slot_height {
+        return Cow::Borrowed(bitmap);
+    }
+
+    let mut filled = vec![0; slot_width * slot_height];
+    for (row, glyph_row) in filled.chunks_exact_mut(slot_width).zip(bitmap.chunks_exact(width)) {
+        row[..width].copy_from_slice(glyph_row);
+    }
+
+    Cow::Owned(filled)
+}
+
 // TODO - Make this public only to the module
 #[derive(Debug)]
 pub struct PositionedGlyph {
     x: f32,
     y: f32,
-    width: usize,
-    height: usize,
+    width: f32,
+    height: f32,
     color: Color,
 
+    /// Outline color and width in pixels of the rasterized glyph, drawn by the SDF shader.
+    outline: Option<(Color, f32)>,
+
     // Texture properties
     texture_x: f32,
     texture_y: f32,
@@ -228,41 +591,252 @@ pub struct PositionedGlyph {
     texture_height: f32,
 }
 
-pub struct TextSystem {
-    font_data: FontData,
-
-    /// A map of styled characters to their associated metadata
-    /// (their location in the font bitmap, width, height, etc.)
-    char_metadata: HashMap<StyledCharacter, CharacterMetadata>,
+/// Caches rasterized glyphs in the glyph atlas texture, with a separate cache
+/// per font face, keyed by character and font size. Each glyph is rasterized
+/// and packed into the atlas once, after which its cached UV coordinates are
+/// reused. The faces share the atlas. SDF glyphs are only cached at
+/// `SDF_FONT_SIZE`, so there's one entry per character for any text size.
+///
+/// When the atlas is full, a new glyph takes over the slot of the least
+/// recently used glyph it fits in. Glyphs used in the current frame are never
+/// evicted, as text drawn earlier in the frame still samples them and texture
+/// uploads execute before any of the frame's draws. If no slot can be taken
+/// over, the glyph is left out until the next frame, which starts with an
+/// empty atlas.
+pub struct GlyphCache {
+    /// For each font face, a map of characters and font sizes to their associated
+    /// metadata (their location in the font bitmap, width, height, etc.)
+    fonts: HashMap<FontFace, HashMap<(char, u32), CharacterMetadata>>,
 
     /// Data structure to pack glyph rectangles into a larger GPU bitmap.
     glyph_packer: Packer,
 
+    /// Sum of the areas of all packed glyph rectangles, in pixels.
+    used_area: u64,
+
+    /// The `FrameEncoder::frame_index` glyphs were last packed for.
+    frame_index: u64,
+
+    /// Set when a glyph didn't fit even after evicting, so the next frame
+    /// clears the cache.
+    full: bool,
+}
+
+impl GlyphCache {
+    fn new() -> Self {
+        Self {
+            fonts: HashMap::new(),
+            glyph_packer: Self::new_packer(),
+            used_area: 0,
+            frame_index: 0,
+            full: false,
+        }
+    }
+
+    /// Call before packing glyphs for the frame `frame_index`. Evicts every
+    /// cached glyph if a glyph didn't fit during an earlier frame.
+    fn begin_frame(&mut self, frame_index: u64) {
+        if self.full && frame_index != self.frame_index {
+            println!("Glyph texture is full, evicting all cached glyphs");
+            self.clear();
+        }
+
+        self.frame_index = frame_index;
+    }
+
+    fn new_packer() -> Packer {
+        Packer::new(rect_packer::Config {
+            width: BITMAP_WIDTH as i32,
+            height: BITMAP_HEIGHT as i32,
+            border_padding: BORDER_PADDING as i32,
+            rectangle_padding: RECTANGLE_PADDING as i32,
+        })
+    }
+
+    fn get(&self, c: &StyledCharacter) -> Option<&CharacterMetadata> {
+        self.fonts.get(&c.font.face())?.get(&(c.character, c.font.size()))
+    }
+
+    /// Marks a cached glyph as used in the current frame, so it isn't evicted
+    /// before the frame is drawn. Returns false if the glyph isn't cached.
+    fn touch(&mut self, c: &StyledCharacter) -> bool {
+        let glyph = self
+            .fonts
+            .get_mut(&c.font.face())
+            .and_then(|glyphs| glyphs.get_mut(&(c.character, c.font.size())));
+
+        match glyph {
+            Some(metadata) => {
+                metadata.last_used = self.frame_index;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Reserves space in the atlas for a glyph with the given metrics, plus
+    /// `padding` pixels on each side, and caches its texture coordinates.
+    /// Evicts the least recently used glyph which wasn't used this frame if the
+    /// atlas is full. Returns the reserved slot in pixels, which the glyph is
+    /// at the top left of, or None if there's no space left.
+    fn pack(
+        &mut self,
+        c: StyledCharacter,
+        metrics: Metrics,
+        padding: u32,
+    ) -> Option<rect_packer::Rect> {
+        let can_rotate = false;
+        let width = metrics.width as u32 + 2 * padding;
+        let height = metrics.height as u32 + 2 * padding;
+        let slot = match self.glyph_packer.pack(width as i32, height as i32, can_rotate) {
+            Some(slot) => {
+                self.used_area += slot.width as u64 * slot.height as u64;
+                slot
+            },
+            None => match self.evict_slot(width, height) {
+                Some(slot) => slot,
+                None => {
+                    self.full = true;
+                    return None;
+                },
+            },
+        };
+
+        let float_width = BITMAP_WIDTH as f32;
+        let float_height = BITMAP_HEIGHT as f32;
+
+        let char_metadata = CharacterMetadata {
+            metrics,
+            texture_x: slot.x as f32 / float_width,
+            texture_y: slot.y as f32 / float_height,
+            texture_width: width as f32 / float_width,
+            texture_height: height as f32 / float_height,
+            padding,
+            slot,
+            last_used: self.frame_index,
+        };
+
+        let glyphs = self.fonts.entry(c.font.face()).or_insert_with(HashMap::new);
+        glyphs.insert((c.character, c.font.size()), char_metadata);
+
+        Some(slot)
+    }
+
+    /// Evicts the least recently used glyph with a slot of at least `width` x
+    /// `height` pixels, preferring the smallest slot among equally old glyphs.
+    /// Glyphs used in the current frame are kept. Returns the freed slot.
+    fn evict_slot(&mut self, width: u32, height: u32) -> Option<rect_packer::Rect> {
+        let frame_index = self.frame_index;
+        let (face, key, slot) = self
+            .fonts
+            .iter()
+            .flat_map(|(face, glyphs)| {
+                glyphs.iter().map(move |(key, metadata)| (*face, *key, metadata))
+            })
+            .filter(|(_, _, metadata)| {
+                metadata.last_used < frame_index
+                    && metadata.slot.width as u32 >= width
+                    && metadata.slot.height as u32 >= height
+            })
+            .min_by_key(|(_, _, metadata)| {
+                (metadata.last_used, metadata.slot.width * metadata.slot.height)
+            })
+            .map(|(face, key, metadata)| (face, key, metadata.slot))?;
+
+        if let Some(glyphs) = self.fonts.get_mut(&face) {
+            glyphs.remove(&key);
+        }
+
+        Some(slot)
+    }
+
+    /// Evicts every cached glyph. The old atlas contents are simply
+    /// overwritten as glyphs get packed again.
+    fn clear(&mut self) {
+        self.fonts.clear();
+        self.glyph_packer = Self::new_packer();
+        self.used_area = 0;
+        self.full = false;
+    }
+
+    /// Returns the number of cached glyphs and the fraction (0.0 - 1.0)
+    /// of the atlas area they occupy.
+    pub fn stats(&self) -> (usize, f32) {
+        let atlas_area = BITMAP_WIDTH as u64 * BITMAP_HEIGHT as u64;
+        let glyph_count = self.fonts.values().map(HashMap::len).sum();
+        (glyph_count, self.used_area as f32 / atlas_area as f32)
+    }
+
+    /// The number of glyphs cached for `font`'s face, at any size.
+    pub fn font_glyph_count(&self, font: Font) -> usize {
+        self.fonts.get(&font.face()).map_or(0, HashMap::len)
+    }
+}
+
+pub struct TextSystem {
+    font_data: FontData,
+
+    /// Rasterized glyphs and their location in the glyph texture.
+    glyph_cache: GlyphCache,
+
     /// Object to perform text layout on content blocks.
     layout: Layout<usize>,
 
     /// GPU glyph renderer.
     pub glyph_painter: GlyphPainter,
+
+    /// The scale factor of the last frame rendered to, used to
+    /// rasterize at physical pixel sizes.
+    scale_factor: f64,
+
+    /// Glyphs from `queue_text` waiting for `flush`, and the window size they
+    /// were laid out for.
+    queued: Vec<PositionedGlyph>,
+    queued_window_size: PhysicalSize<u32>,
 }
 
 impl TextSystem {
     pub fn new(graphics_device: &GraphicsDevice) -> Self {
-        let font_data = FontData::new();
-        let char_metadata = HashMap::new();
+        Self::with_glyph_painter(GlyphPainter::new(graphics_device, None))
+    }
 
-        let packer_config = rect_packer::Config {
-            width: BITMAP_WIDTH as i32,
-            height: BITMAP_HEIGHT as i32,
-            border_padding: BORDER_PADDING as i32,
-            rectangle_padding: RECTANGLE_PADDING as i32,
-        };
+    /// Creates a text system which rasterizes glyphs to signed distance fields
+    /// instead of coverage bitmaps. The edges stay sharp when the text is scaled,
+    /// and `params` adds an optional outline and glow.
+    pub fn new_sdf(graphics_device: &GraphicsDevice, params: SdfParams) -> Self {
+        Self::with_glyph_painter(GlyphPainter::new(graphics_device, Some(params)))
+    }
 
-        let glyph_packer = Packer::new(packer_config);
+    fn with_glyph_painter(glyph_painter: GlyphPainter) -> Self {
+        let font_data = FontData::new();
+        let glyph_cache = GlyphCache::new();
         let layout = Layout::new(CoordinateSystem::PositiveYDown);
 
-        let glyph_painter = GlyphPainter::new(graphics_device);
+        Self {
+            font_data,
+            glyph_cache,
+            layout,
+            glyph_painter,
+            scale_factor: 1.0,
+            queued: Vec::new(),
+            queued_window_size: PhysicalSize::new(0, 0),
+        }
+    }
+
+    /// Updates the outline and glow of an SDF text system.
+    /// Has no effect on bitmap text.
+    pub fn set_sdf_params(&mut self, params: SdfParams) {
+        self.glyph_painter.set_sdf_params(params);
+    }
 
-        Self { font_data, char_metadata, glyph_packer, layout, glyph_painter }
+    /// Loads a TrueType or OpenType font from `bytes`. Use the returned
+    /// `FontId` with `Font::Custom` to render text with it.
+    pub fn load_font(&mut self, bytes: &[u8]) -> Result<FontId, FontLoadError> {
+        self.font_data.load_font(bytes)
+    }
+
+    pub fn glyph_cache(&self) -> &GlyphCache {
+        &self.glyph_cache
     }
 
     /// Rasterizes and caches this character in the glyph texture.
@@ -273,73 +847,94 @@ impl TextSystem {
         c: StyledCharacter,
         frame_encoder: &mut FrameEncoder,
     ) -> Result<RasterizeResult, RasterizationError> {
-        let metadata = self.char_metadata.entry(c);
+        if self.glyph_cache.touch(&c) {
+            // Good to go, this character already exists
+            return Ok(RasterizeResult::Packed);
+        }
 
-        match metadata {
-            Entry::Occupied(_) => {
-                // Good to go, this character already exists
-                Ok(RasterizeResult::Packed)
-            },
-            Entry::Vacant(entry) => {
-                let styled_char = entry.key();
+        let character = c.character;
+        let font_size = c.font.size() as f32;
 
-                let character = styled_char.character;
-                let font_size = styled_char.font.size() as f32;
+        let rasterizer = self
+            .font_data
+            .rasterizer_for_font(&c.font)
+            .unwrap_or_else(|| panic!("Rasterizer should exist for Font: {:?}", c.font));
 
-                let rasterizer =
-                    self.font_data.rasterizer_for_font(&styled_char.font).unwrap_or_else(|| {
-                        panic!("Rasterizer should exist for Font: {:?}", styled_char.font)
-                    });
+        let (metrics, mut bitmap) = rasterizer.rasterize(character, font_size);
 
-                let (metrics, bitmap) = rasterizer.rasterize(character, font_size);
-                let can_rotate = false;
+        if metrics.width == 0 || metrics.height == 0 {
+            // This was likely a whitespace character which isn't missing from the font
+            // but does not have an actual bitmap. The rectangle packer would fail on
+            // this case so we return here as everything will still work.
+            return Ok(RasterizeResult::WhitespaceChar);
+        }
 
-                if metrics.width == 0 || metrics.height == 0 {
-                    // This was likely a whitespace character which isn't missing from the font
-                    // but does not have an actual bitmap. The rectangle packer would fail on
-                    // this case so we return here as everything will still work.
-                    return Ok(RasterizeResult::WhitespaceChar);
-                }
+        let padding = if self.glyph_painter.is_sdf() {
+            bitmap = sdf::distance_field(&bitmap, metrics.width, metrics.height, SDF_PADDING);
+            SDF_PADDING
+        } else {
+            0
+        };
+
+        if let Some(slot) = self.glyph_cache.pack(c, metrics, padding) {
+            // A slot taken over from an evicted glyph can be larger than this
+            // one, so the rest of it is cleared along with the upload.
+            let glyph_width = metrics.width + 2 * padding as usize;
+            let bitmap = fill_slot(&bitmap, glyph_width, slot);
+            self.glyph_painter.write_to_texture(
+                frame_encoder,
+                &bitmap,
+                slot.x as u32,
+                slot.y as u32,
+                slot.width as u32,
+                slot.height as u32,
+            );
+
+            let glyph_missing = rasterizer.lookup_glyph_index(character) == 0;
+
+            if glyph_missing {
+                Ok(RasterizeResult::GlyphMissing)
+            } else {
+                Ok(RasterizeResult::Packed)
+            }
+        } else {
+            Err(RasterizationError::NoTextureSpace)
+        }
+    }
+
+    /// The horizontal advance of `text` rendered in `font`, in pixels.
+    /// The rasterizer for `font` must already exist.
+    fn text_width(&self, text: &str, font: Font) -> f32 {
+        let rasterizer = self
+            .font_data
+            .rasterizer_for_font(&font)
+            .unwrap_or_else(|| panic!("Rasterizer should exist for Font: {:?}", font));
+
+        text.chars().map(|c| rasterizer.metrics(c, font.size() as f32).advance_width).sum()
+    }
 
-                if let Some(packed_rect) =
-                    self.glyph_packer.pack(metrics.width as i32, metrics.height as i32, can_rotate)
+    /// Makes sure every character in `text_elements` is in the glyph cache.
+    /// Returns false if the glyph texture ran out of space.
+    fn cache_text<'a, T: Borrow<StyledText<'a>>>(
+        &mut self,
+        text_elements: &[T],
+        frame_encoder: &mut FrameEncoder,
+    ) -> bool {
+        for text_element in text_elements {
+            let text_element = text_element.borrow();
+
+            for c in text_element.text.chars() {
+                let font = text_element.font.cached(self.glyph_painter.is_sdf());
+                let styled_char = StyledCharacter { character: c, font };
+                if let Err(RasterizationError::NoTextureSpace) =
+                    self.rasterize_and_cache(styled_char, frame_encoder)
                 {
-                    let float_width = BITMAP_WIDTH as f32;
-                    let float_height = BITMAP_HEIGHT as f32;
-
-                    let char_metadata = CharacterMetadata {
-                        metrics,
-                        texture_x: packed_rect.x as f32 / float_width,
-                        texture_y: packed_rect.y as f32 / float_height,
-                        texture_width: packed_rect.width as f32 / float_width,
-                        texture_height: packed_rect.height as f32 / float_height,
-                    };
-
-                    entry.insert(char_metadata);
-
-                    self.glyph_painter.write_to_texture(
-                        frame_encoder,
-                        &bitmap,
-                        packed_rect.x as u32,
-                        packed_rect.y as u32,
-                        packed_rect.width as u32,
-                        packed_rect.height as u32,
-                    );
-
-                    let glyph_missing = rasterizer.lookup_glyph_index(character) == 0;
-
-                    if glyph_missing {
-                        Ok(RasterizeResult::GlyphMissing)
-                    } else {
-                        Ok(RasterizeResult::Packed)
-                    }
-                } else {
-                    // Couldn't pack into texture, resize it
-                    println!("Couldn't pack char: {:?} into glyph texture", character);
-                    Err(RasterizationError::NoTextureSpace)
+                    return false;
                 }
-            },
+            }
         }
+
+        true
     }
 
     /// Call this for each "block" of text you want to render in a particular location.
@@ -350,39 +945,145 @@ impl TextSystem {
         text_alignment: TextAlignment,
         text_elements: &[T],
         frame_encoder: &mut FrameEncoder,
-        window_size: winit::dpi::PhysicalSize<u32>,
+        window_size: PhysicalSize<u32>,
     ) {
-        for text_element in text_elements {
-            let text_element = text_element.borrow();
+        let bounds = Rect::new(0.0, 0.0, window_size.width as f32, window_size.height as f32);
+        self.render_horizontal_in_rect(
+            text_alignment,
+            text_elements,
+            frame_encoder,
+            window_size,
+            bounds,
+        );
+    }
 
-            self.font_data.create_rasterizer(text_element.font);
+    /// Same as `render_horizontal`, but the alignment is relative to `bounds`
+    /// instead of the whole window. Pass `GraphicsDevice::safe_area_rect()`
+    /// to keep HUD text clear of display notches and rounded corners.
+    pub fn render_horizontal_in_rect<'a, T: Borrow<StyledText<'a>>>(
+        &mut self,
+        text_alignment: TextAlignment,
+        text_elements: &[T],
+        frame_encoder: &mut FrameEncoder,
+        window_size: PhysicalSize<u32>,
+        bounds: Rect,
+    ) {
+        self.flush(frame_encoder);
 
-            for c in text_element.text.chars() {
-                let styled_char = StyledCharacter { character: c, font: text_element.font };
-                if let Err(err) = self.rasterize_and_cache(styled_char, frame_encoder) {
-                    println!("Error rasterizing character: {:?} - {:?}", c, err);
-                }
+        let glyphs = self.layout_glyphs(text_alignment, text_elements, frame_encoder, bounds);
+        self.glyph_painter.render(&glyphs, frame_encoder, window_size);
+    }
+
+    /// Lays out a block of text like `render_horizontal`, but only queues its
+    /// glyphs. Everything queued is drawn with a single draw call by `flush`, so
+    /// many strings (e.g. a chat log or scoreboard) don't cost a draw call each.
+    pub fn queue_text<'a, T: Borrow<StyledText<'a>>>(
+        &mut self,
+        text_alignment: TextAlignment,
+        text_elements: &[T],
+        frame_encoder: &mut FrameEncoder,
+        window_size: PhysicalSize<u32>,
+    ) {
+        let bounds = Rect::new(0.0, 0.0, window_size.width as f32, window_size.height as f32);
+        self.queue_text_in_rect(text_alignment, text_elements, frame_encoder, window_size, bounds);
+    }
+
+    /// Same as `queue_text`, with the alignment relative to `bounds`.
+    pub fn queue_text_in_rect<'a, T: Borrow<StyledText<'a>>>(
+        &mut self,
+        text_alignment: TextAlignment,
+        text_elements: &[T],
+        frame_encoder: &mut FrameEncoder,
+        window_size: PhysicalSize<u32>,
+        bounds: Rect,
+    ) {
+        if self.queued.is_empty() {
+            self.queued_window_size = window_size;
+        }
+
+        let mut glyphs = self.layout_glyphs(text_alignment, text_elements, frame_encoder, bounds);
+        self.queued.append(&mut glyphs);
+    }
+
+    /// Draws all text queued with `queue_text` and clears the queue.
+    /// Call it once per frame, after queueing.
+    pub fn flush(&mut self, frame_encoder: &mut FrameEncoder) {
+        if self.queued.is_empty() {
+            return;
+        }
+
+        let queued = std::mem::take(&mut self.queued);
+        self.glyph_painter.render(&queued, frame_encoder, self.queued_window_size);
+
+        // Keep the allocation for the next frame.
+        self.queued = queued;
+        self.queued.clear();
+    }
+
+    /// Lays out and caches the glyphs of a block of text, returning them
+    /// in the order they're drawn.
+    fn layout_glyphs<'a, T: Borrow<StyledText<'a>>>(
+        &mut self,
+        text_alignment: TextAlignment,
+        text_elements: &[T],
+        frame_encoder: &mut FrameEncoder,
+        bounds: Rect,
+    ) -> Vec<PositionedGlyph> {
+        self.scale_factor = frame_encoder.scale_factor();
+
+        let scale_factor = self.scale_factor;
+        let text_alignment = text_alignment.scaled(scale_factor);
+        let mut text_elements: Vec<StyledText<'a>> = text_elements
+            .iter()
+            .map(|t| {
+                let t = *t.borrow();
+                StyledText { font: t.font.scaled(scale_factor), ..t }
+            })
+            .collect();
+
+        let font_data = &mut self.font_data;
+        let is_sdf = self.glyph_painter.is_sdf();
+        text_elements.retain(|text_element| {
+            let font = text_element.font;
+            let known_font = font_data.create_rasterizer(font)
+                && font_data.create_rasterizer(font.cached(is_sdf));
+            if !known_font {
+                println!(
+                    "Skipping text with a font of another text system: {:?}",
+                    text_element.font
+                );
             }
+
+            known_font
+        });
+
+        let max_width = text_alignment.max_width.map(|w| w as f32).unwrap_or(bounds.w);
+        truncate_to_width(&mut text_elements, max_width, text_alignment.overflow, |text, font| {
+            self.text_width(text, font)
+        });
+        let text_elements = &text_elements[..];
+
+        self.glyph_cache.begin_frame(frame_encoder.frame_index());
+        if !self.cache_text(text_elements, frame_encoder) {
+            println!("Glyph texture is full, leaving out glyphs until the next frame");
         }
 
         let styles: Vec<_> = text_elements
             .iter()
             .enumerate()
-            .map(|(i, t)| {
-                let t = t.borrow();
-                TextStyle {
-                    user_data: i,
-                    text: &t.text,
-                    px: t.font.size() as f32,
-                    font_index: self
-                        .font_data
-                        .font_index(&t.font)
-                        .unwrap_or_else(|| panic!("Missing font index for font: {:?}", t.font)),
-                }
+            .map(|(i, t)| LayoutStyle {
+                user_data: i,
+                text: &t.text,
+                px: t.font.size() as f32,
+                font_index: self
+                    .font_data
+                    .font_index(&t.font)
+                    .unwrap_or_else(|| panic!("Missing font index for font: {:?}", t.font)),
             })
             .collect();
 
-        let layout_settings = text_alignment.into_layout_settings(window_size);
+        let subpixel_offset = text_alignment.subpixel_offset(bounds);
+        let layout_settings = text_alignment.into_layout_settings(bounds);
 
         self.layout.reset(&layout_settings);
         let fonts = &self.font_data.rasterizers();
@@ -391,70 +1092,278 @@ impl TextSystem {
         }
 
         let glyphs = self.layout.glyphs();
-        let char_metadata = &self.char_metadata;
+        let glyph_offsets = glyph_offsets(subpixel_offset, glyphs, fonts);
+        let glyph_cache = &self.glyph_cache;
         let font_data = &self.font_data;
+        let scale = scale_factor as f32;
+
+        // Shadows are drawn first, then outlines and then the glyphs themselves,
+        // so effects never cover neighbouring characters.
+        let mut shadows = Vec::new();
+        let mut outlines = Vec::new();
 
-        let position_data: Vec<_> = glyphs
+        let positioned: Vec<_> = glyphs
             .iter()
-            .filter_map(|d| {
-                char_metadata
-                    .get(&StyledCharacter {
-                        character: d.key.c,
-                        font: *font_data.font(d.key.font_index).unwrap_or_else(|| {
-                            panic!(
-                                "Should have a font for the given font index: {}",
-                                d.key.font_index
-                            )
-                        }),
-                    })
-                    .map(|metadata| {
-                        let color = text_elements[d.user_data].borrow().color;
-
-                        PositionedGlyph {
-                            x: d.x,
-                            y: d.y,
-                            width: d.width,
-                            height: d.height,
+            .zip(glyph_offsets)
+            .filter_map(|(d, offset)| {
+                let font = *font_data.font(d.key.font_index).unwrap_or_else(|| {
+                    panic!("Should have a font for the given font index: {}", d.key.font_index)
+                });
+                let cached_font = font.cached(is_sdf);
+
+                glyph_cache.get(&StyledCharacter { character: d.key.c, font: cached_font }).map(
+                    |metadata| {
+                        let text_element = &text_elements[d.user_data];
+
+                        // How much larger the glyph is drawn than it was rasterized,
+                        // always 1.0 for bitmap text.
+                        let glyph_scale = font.size() as f32 / cached_font.size() as f32;
+                        let padding = metadata.padding as f32 * glyph_scale;
+                        let bitmap_padding = 2 * metadata.padding as usize;
+
+                        let outline = text_element.style.outline.map(|(c, w)| (c, w * scale));
+                        let sdf_outline = outline.map(|(c, w)| (c, w / glyph_scale));
+                        let glyph = PositionedGlyph {
+                            x: d.x + offset - padding,
+                            y: d.y - padding,
+                            width: (metadata.metrics.width + bitmap_padding) as f32 * glyph_scale,
+                            height: (metadata.metrics.height + bitmap_padding) as f32 * glyph_scale,
                             texture_x: metadata.texture_x,
                             texture_y: metadata.texture_y,
                             texture_width: metadata.texture_width,
                             texture_height: metadata.texture_height,
-                            color,
-                        }
-                    })
+                            color: text_element.color,
+                            outline: if is_sdf { sdf_outline } else { None },
+                        };
+
+                        (glyph, text_element.style.shadow, outline)
+                    },
+                )
             })
             .collect();
 
-        // TODO(bschwind) - Make an API for queueing up text to render, collect all
-        // the output from fontdue, and then render it all at once to reduce GPU draw calls.
-        self.glyph_painter.render(&position_data, frame_encoder, window_size);
+        for (glyph, shadow, outline) in &positioned {
+            if let Some((color, [dx, dy])) = *shadow {
+                let (x, y) = (glyph.x + dx * scale, glyph.y + dy * scale);
+                shadows.push(PositionedGlyph { x, y, color, outline: None, ..*glyph });
+            }
+
+            if let (Some((color, width)), false) = (*outline, is_sdf) {
+                let diagonal = width * std::f32::consts::FRAC_1_SQRT_2;
+                let offsets = [
+                    [width, 0.0],
+                    [-width, 0.0],
+                    [0.0, width],
+                    [0.0, -width],
+                    [diagonal, diagonal],
+                    [diagonal, -diagonal],
+                    [-diagonal, diagonal],
+                    [-diagonal, -diagonal],
+                ];
+
+                for [dx, dy] in &offsets {
+                    let (x, y) = (glyph.x + dx, glyph.y + dy);
+                    outlines.push(PositionedGlyph { x, y, color, ..*glyph });
+                }
+            }
+        }
+
+        let mut position_data = shadows;
+        position_data.append(&mut outlines);
+        position_data.extend(positioned.into_iter().map(|(glyph, _, _)| glyph));
+        position_data
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-pub struct Color {
-    pub red: u8,
-    pub green: u8,
-    pub blue: u8,
-    pub alpha: u8,
+    /// Lays out `text` like `render_horizontal_in_rect` would (without overflow
+    /// truncation) and returns the screen rect of each grapheme, keyed by its
+    /// byte range in `text`, so e.g. accented letters made of a base character and
+    /// a combining mark, or emoji sequences, get a single rect. Graphemes fontdue
+    /// emits no glyphs for (e.g. line breaks) are merged into the preceding range,
+    /// so the ranges always cover `text` without gaps or overlaps. Intended for hit
+    /// testing, text selection and handing text positions to accessibility APIs.
+    /// Fonts and alignments are scaled by the scale factor of the last frame text
+    /// was rendered to.
+    pub fn accessible_layout(
+        &mut self,
+        text: &str,
+        font: Font,
+        text_alignment: TextAlignment,
+        bounds: Rect,
+    ) -> Vec<(Range<usize>, Rect)> {
+        let font = font.scaled(self.scale_factor);
+        let text_alignment = text_alignment.scaled(self.scale_factor);
+        if !self.font_data.create_rasterizer(font) {
+            println!("Can't lay out text with a font of another text system: {:?}", font);
+            return Vec::new();
+        }
+
+        let px = font.size() as f32;
+        let font_index = self
+            .font_data
+            .font_index(&font)
+            .unwrap_or_else(|| panic!("Missing font index for font: {:?}", font));
+
+        // Offset like the rendered glyphs, so carets line up with them.
+        let subpixel_offset = text_alignment.subpixel_offset(bounds);
+        let layout_settings = text_alignment.into_layout_settings(bounds);
+        let origin_x = layout_settings.x + subpixel_offset.unwrap_or(0.0);
+        let origin = Rect::new(origin_x, layout_settings.y, 0.0, 0.0);
+
+        self.layout.reset(&layout_settings);
+        let fonts = self.font_data.rasterizers();
+        self.layout.append(fonts, &LayoutStyle { text, px, font_index, user_data: 0 });
+
+        let rasterizer = &fonts[font_index];
+        let (ascent, line_height) = rasterizer
+            .horizontal_line_metrics(px)
+            .map(|m| (m.ascent, m.ascent - m.descent))
+            .unwrap_or((px, px));
+
+        let glyph_offsets = glyph_offsets(subpixel_offset, self.layout.glyphs(), fonts);
+        let mut glyphs = self.layout.glyphs().iter().zip(glyph_offsets).peekable();
+        let char_rect = |c: char| match glyphs.peek() {
+            Some(&(glyph, offset)) if glyph.key.c == c => {
+                let metrics = rasterizer.metrics(c, px);
+                let baseline = glyph.y + glyph.height as f32 + metrics.ymin as f32;
+                glyphs.next();
+
+                Some(Rect::new(
+                    glyph.x + offset - metrics.xmin as f32,
+                    baseline - ascent,
+                    metrics.advance_width,
+                    line_height,
+                ))
+            },
+            _ => None,
+        };
+
+        grapheme_clusters(text, origin, char_rect)
+    }
 }
 
-impl Color {
-    pub const fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
-        Self { red, green, blue, alpha }
+mod sdf {
+    /// Offset from a pixel to the nearest seed pixel. Far away means "no seed found yet".
+    #[derive(Clone, Copy)]
+    struct Offset(i32, i32);
+
+    const FAR: Offset = Offset(9999, 9999);
+
+    impl Offset {
+        fn dist_sq(self) -> i32 {
+            self.0 * self.0 + self.1 * self.1
+        }
+    }
+
+    /// Converts a glyph coverage bitmap into a signed distance field with
+    /// `spread` pixels of padding on every side, using the 8-point sequential
+    /// Euclidean distance transform (8SSEDT). The output is one byte per pixel
+    /// where 128 is the glyph edge, larger values are inside the glyph and
+    /// values fall off to 0 at `spread` pixels outside of it.
+    pub fn distance_field(bitmap: &[u8], width: usize, height: usize, spread: u32) -> Vec<u8> {
+        let spread = spread as usize;
+        let out_width = width + 2 * spread;
+        let out_height = height + 2 * spread;
+
+        let is_inside = |x: usize, y: usize| {
+            if x < spread || y < spread || x >= width + spread || y >= height + spread {
+                false
+            } else {
+                bitmap[(y - spread) * width + (x - spread)] >= 128
+            }
+        };
+
+        // `inside` finds the distance to the glyph for pixels outside of it,
+        // `outside` finds the distance to empty space for pixels within it.
+        let mut inside = vec![FAR; out_width * out_height];
+        let mut outside = vec![FAR; out_width * out_height];
+
+        for y in 0..out_height {
+            for x in 0..out_width {
+                if is_inside(x, y) {
+                    inside[y * out_width + x] = Offset(0, 0);
+                } else {
+                    outside[y * out_width + x] = Offset(0, 0);
+                }
+            }
+        }
+
+        sweep(&mut inside, out_width, out_height);
+        sweep(&mut outside, out_width, out_height);
+
+        inside
+            .iter()
+            .zip(outside.iter())
+            .map(|(to_glyph, to_empty)| {
+                let signed_dist =
+                    (to_empty.dist_sq() as f32).sqrt() - (to_glyph.dist_sq() as f32).sqrt();
+                let normalized = 0.5 + signed_dist / (2.0 * spread as f32);
+
+                (normalized.max(0.0).min(1.0) * 255.0) as u8
+            })
+            .collect()
+    }
+
+    fn sweep(grid: &mut [Offset], width: usize, height: usize) {
+        let w = width as i32;
+        let h = height as i32;
+
+        for y in 0..h {
+            for x in 0..w {
+                compare(grid, w, h, x, y, -1, 0);
+                compare(grid, w, h, x, y, 0, -1);
+                compare(grid, w, h, x, y, -1, -1);
+                compare(grid, w, h, x, y, 1, -1);
+            }
+
+            for x in (0..w).rev() {
+                compare(grid, w, h, x, y, 1, 0);
+            }
+        }
+
+        for y in (0..h).rev() {
+            for x in (0..w).rev() {
+                compare(grid, w, h, x, y, 1, 0);
+                compare(grid, w, h, x, y, 0, 1);
+                compare(grid, w, h, x, y, -1, 1);
+                compare(grid, w, h, x, y, 1, 1);
+            }
+
+            for x in 0..w {
+                compare(grid, w, h, x, y, -1, 0);
+            }
+        }
+    }
+
+    fn compare(grid: &mut [Offset], w: i32, h: i32, x: i32, y: i32, dx: i32, dy: i32) {
+        let (nx, ny) = (x + dx, y + dy);
+        if nx < 0 || ny < 0 || nx >= w || ny >= h {
+            return;
+        }
+
+        let neighbor = grid[(ny * w + nx) as usize];
+        let candidate = Offset(neighbor.0 + dx, neighbor.1 + dy);
+        let current = &mut grid[(y * w + x) as usize];
+
+        if candidate.dist_sq() < current.dist_sq() {
+            *current = candidate;
+        }
     }
 }
 
 mod gpu {
-    use super::{BITMAP_HEIGHT, BITMAP_WIDTH};
+    use super::{Color, BITMAP_HEIGHT, BITMAP_WIDTH, SDF_PADDING};
     use crate::{
-        graphics::{text::PositionedGlyph, FrameEncoder},
+        graphics::{
+            apply_scissor_rect, camera::screen_projection_matrix, color_target, debug_marker,
+            text::PositionedGlyph, FrameEncoder,
+        },
         GraphicsDevice,
     };
     use bytemuck::{Pod, Zeroable};
     use wgpu::{util::DeviceExt, BindGroup, Buffer, RenderPipeline, Texture};
 
     const MAX_INSTANCE_COUNT: usize = 40_000;
+    const SDF_UNIFORMS_SIZE: u64 = std::mem::size_of::<SdfUniforms>() as u64;
 
     /// Vertex attributes for instanced glyph data.
     #[repr(C)]
@@ -472,6 +1381,12 @@ mod gpu {
 
         /// The color of the glyph, including alpha.
         color: [f32; 4],
+
+        /// Overrides the outline of `SdfParams` when the width is above 0.
+        outline_color: [f32; 4],
+
+        /// In distance field units, like `SdfUniforms::outline_width`.
+        outline_width: f32,
     }
 
     impl Default for GlyphInstanceData {
@@ -481,6 +1396,59 @@ mod gpu {
                 size: [0.0, 0.0],
                 uv_extents: [0.0, 0.0, 0.0, 0.0],
                 color: [1.0, 1.0, 1.0, 1.0],
+                outline_color: [0.0, 0.0, 0.0, 0.0],
+                outline_width: 0.0,
+            }
+        }
+    }
+
+    /// Outline and glow settings for SDF text. Widths are in pixels of glyphs
+    /// rasterized at `SDF_FONT_SIZE`, so they scale with the text, and are limited
+    /// by how far the distance field extends past the glyph.
+    #[derive(Debug, Clone, Copy)]
+    pub struct SdfParams {
+        pub outline_color: Color,
+        pub outline_width: f32,
+        pub glow_color: Color,
+        pub glow_width: f32,
+    }
+
+    impl Default for SdfParams {
+        fn default() -> Self {
+            SdfParams {
+                outline_color: Color::TRANSPARENT,
+                outline_width: 0.0,
+                glow_color: Color::TRANSPARENT,
+                glow_width: 0.0,
+            }
+        }
+    }
+
+    /// SdfParams laid out for the SDF fragment shader's uniform block.
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, Pod, Zeroable)]
+    struct SdfUniforms {
+        outline_color: [f32; 4],
+        glow_color: [f32; 4],
+
+        /// Widths in distance field units, where the glyph edge is at 0.5.
+        outline_width: f32,
+        glow_width: f32,
+
+        _padding: [f32; 2],
+    }
+
+    impl From<SdfParams> for SdfUniforms {
+        fn from(params: SdfParams) -> Self {
+            // One pixel of distance is this many distance field units.
+            let pixel = 1.0 / (2.0 * SDF_PADDING as f32);
+
+            SdfUniforms {
+                outline_color: params.outline_color.to_f32_array(),
+                glow_color: params.glow_color.to_f32_array(),
+                outline_width: params.outline_width * pixel,
+                glow_width: params.glow_width * pixel,
+                _padding: [0.0; 2],
             }
         }
     }
@@ -501,23 +1469,32 @@ mod gpu {
     /// There is also a dynamic vertex buffer. Each element in this buffer stores
     /// the data required to render one glyph. We update this buffer when the font
     /// system tells us where and how many glyphs to render.
+    /// In SDF mode the glyph texture holds distance fields instead of coverage,
+    /// and a separate fragment shader turns them into sharp edges.
     pub struct GlyphPainter {
         glyph_texture: Texture,
         glyph_vertex_buffer: Buffer,
         index_buffer: Buffer,
         instance_buffer: Buffer,
         uniform_buffer: wgpu::Buffer,
+        sdf_uniform_buffer: wgpu::Buffer,
         pub bind_group: BindGroup,
         pipeline: RenderPipeline,
+
+        /// None when rendering plain bitmap glyphs.
+        sdf_params: Option<SdfParams>,
+        sdf_params_dirty: bool,
     }
 
     impl GlyphPainter {
-        pub fn new(graphics_device: &GraphicsDevice) -> Self {
+        pub fn new(graphics_device: &GraphicsDevice, sdf_params: Option<SdfParams>) -> Self {
             let glyph_texture = Self::build_glyph_texture(graphics_device);
             let glyph_vertex_buffer = Self::build_vertex_buffer(graphics_device);
             let index_buffer = Self::build_index_buffer(graphics_device);
             let instance_buffer = Self::build_instance_buffer(graphics_device);
             let uniform_buffer = Self::build_uniform_buffer(graphics_device);
+            let sdf_uniform_buffer =
+                Self::build_sdf_uniform_buffer(graphics_device, sdf_params.unwrap_or_default());
 
             let device = graphics_device.device();
 
@@ -551,6 +1528,16 @@ mod gpu {
                             ty: wgpu::BindingType::Sampler { filtering: true, comparison: false },
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(SDF_UNIFORMS_SIZE),
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -592,6 +1579,14 @@ mod gpu {
                         binding: 2,
                         resource: wgpu::BindingResource::Sampler(&sampler),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer {
+                            buffer: &sdf_uniform_buffer,
+                            offset: 0,
+                            size: None,
+                        },
+                    },
                 ],
                 label: None,
             });
@@ -675,6 +1670,8 @@ mod gpu {
                         2 => Float32x2,
                         3 => Float32x4,
                         4 => Float32x4,
+                        5 => Float32x4,
+                        6 => Float32,
                     ]
                     // attributes: &[
                     //     // pos (vec2)
@@ -705,14 +1702,14 @@ mod gpu {
                 },
             ];
 
-            let vs_module = device.create_shader_module(&wgpu::include_spirv!(
-                "../../../resources/shaders/glyph.vert.spv"
-            ));
-            let fs_module = device.create_shader_module(&wgpu::include_spirv!(
-                "../../../resources/shaders/glyph.frag.spv"
-            ));
+            let vs_module = device.create_shader_module(&include_shader!("glyph.vert"));
+            let fs_module = if sdf_params.is_some() {
+                device.create_shader_module(&include_shader!("glyph_sdf.frag"))
+            } else {
+                device.create_shader_module(&include_shader!("glyph.frag"))
+            };
 
-            let format = graphics_device.swap_chain_descriptor().format;
+            let format = graphics_device.color_format();
             // let format = wgpu::TextureFormat::Bgra8Unorm;
             let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some("text"),
@@ -725,7 +1722,9 @@ mod gpu {
                 fragment: Some(wgpu::FragmentState {
                     module: &fs_module,
                     entry_point: "main",
-                    targets: &[format.into()],
+                    // Blended, so the transparent texels around each glyph don't
+                    // cover the shadows and outlines drawn before it.
+                    targets: &[color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING))],
                 }),
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleStrip,
@@ -769,8 +1768,22 @@ mod gpu {
                 index_buffer,
                 instance_buffer,
                 uniform_buffer,
+                sdf_uniform_buffer,
                 bind_group,
                 pipeline,
+                sdf_params,
+                sdf_params_dirty: false,
+            }
+        }
+
+        pub fn is_sdf(&self) -> bool {
+            self.sdf_params.is_some()
+        }
+
+        pub fn set_sdf_params(&mut self, params: SdfParams) {
+            if self.is_sdf() {
+                self.sdf_params = Some(params);
+                self.sdf_params_dirty = true;
             }
         }
 
@@ -787,34 +1800,46 @@ mod gpu {
 
             let instance_data: Vec<_> = glyph_positions
                 .iter()
-                .map(|g| GlyphInstanceData {
-                    pos: [g.x, g.y],
-                    size: [g.width as f32, g.height as f32],
-                    uv_extents: [g.texture_x, g.texture_y, g.texture_width, g.texture_height],
-                    color: [
-                        g.color.red as f32 / 255.0,
-                        g.color.green as f32 / 255.0,
-                        g.color.blue as f32 / 255.0,
-                        g.color.alpha as f32 / 255.0,
-                    ],
+                .map(|g| {
+                    let (outline_color, outline_width) =
+                        g.outline.unwrap_or((Color::TRANSPARENT, 0.0));
+
+                    GlyphInstanceData {
+                        pos: [g.x, g.y],
+                        size: [g.width, g.height],
+                        uv_extents: [g.texture_x, g.texture_y, g.texture_width, g.texture_height],
+                        color: g.color.to_f32_array(),
+                        outline_color: outline_color.to_f32_array(),
+                        outline_width: outline_width / (2.0 * SDF_PADDING as f32),
+                    }
                 })
                 .collect();
 
             let queue = frame_encoder.queue();
             queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
 
+            if let (Some(params), true) = (self.sdf_params, self.sdf_params_dirty) {
+                let uniforms = SdfUniforms::from(params);
+                queue.write_buffer(&self.sdf_uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+                self.sdf_params_dirty = false;
+            }
+
             // TODO(bschwind) - Only write to the uniform buffer when the window resizes.
             let proj =
                 screen_projection_matrix(window_size.width as f32, window_size.height as f32);
             queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&proj));
 
-            let frame = &frame_encoder.frame;
+            frame_encoder.stats.record_upload(std::mem::size_of_val(&instance_data[..]));
+
+            let scissor_rect = frame_encoder.scissor_rect();
+            let view = frame_encoder.frame.view();
             let encoder = &mut frame_encoder.encoder;
+            let stats = &mut frame_encoder.stats;
 
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
+                label: Some("text"),
                 color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &frame.view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
                 }],
@@ -827,10 +1852,16 @@ mod gpu {
             rpass.set_vertex_buffer(0, self.glyph_vertex_buffer.slice(..));
             rpass.set_vertex_buffer(
                 1,
-                self.instance_buffer
-                    .slice(..(glyph_positions.len() * std::mem::size_of::<PositionedGlyph>()) as u64),
+                self.instance_buffer.slice(
+                    ..(glyph_positions.len() * std::mem::size_of::<GlyphInstanceData>()) as u64,
+                ),
             );
-            rpass.draw_indexed(0..4 as u32, 0, 0..glyph_positions.len() as u32);
+
+            debug_marker(&mut rpass, "text");
+            if apply_scissor_rect(&mut rpass, scissor_rect) {
+                rpass.draw_indexed(0..4 as u32, 0, 0..glyph_positions.len() as u32);
+                stats.record_draw(2 * glyph_positions.len() as u32);
+            }
         }
 
         pub fn write_to_texture(
@@ -920,12 +1951,21 @@ mod gpu {
             let device = graphics_device.device();
             device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("Glyph Instance Buffer"),
-                size: MAX_INSTANCE_COUNT as u64, // TODO - multiply by instance size?
+                size: (MAX_INSTANCE_COUNT * std::mem::size_of::<GlyphInstanceData>()) as u64,
                 usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
                 mapped_at_creation: false,
             })
         }
 
+        fn build_sdf_uniform_buffer(graphics_device: &GraphicsDevice, params: SdfParams) -> Buffer {
+            let device = graphics_device.device();
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Glyph SDF Uniform Buffer"),
+                contents: bytemuck::bytes_of(&SdfUniforms::from(params)),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            })
+        }
+
         fn build_uniform_buffer(graphics_device: &GraphicsDevice) -> Buffer {
             let device = graphics_device.device();
             device.create_buffer(&wgpu::BufferDescriptor {
@@ -937,32 +1977,543 @@ mod gpu {
         }
     }
 
-    // Creates a matrix that projects screen coordinates defined by width and
-    // height orthographically onto the OpenGL vertex coordinates.
-    fn screen_projection_matrix(width: f32, height: f32) -> [[f32; 4]; 4] {
-        ortho_projection_matrix(0.0, width, height, 0.0, -1.0, 1.0)
-    }
-
-    // Creates a matrix that projects a cube defined by the arguments
-    // orthographically onto the OpenGL vertex coordinates.
-    // TODO(bschwind) - Double check this works outside of OpenGL/Metal
-    fn ortho_projection_matrix(
-        left: f32,
-        right: f32,
-        bottom: f32,
-        top: f32,
-        near: f32,
-        far: f32,
-    ) -> [[f32; 4]; 4] {
-        let lr = 1.0 / (left - right);
-        let bt = 1.0 / (bottom - top);
-        let nf = 1.0 / (near - far);
-
-        [
-            [-2.0 * lr, 0.0, 0.0, 0.0],
-            [0.0, -2.0 * bt, 0.0, 0.0],
-            [0.0, 0.0, 2.0 * nf, 0.0],
-            [(left + right) * lr, (top + bottom) * bt, (far + near) * nf, 1.0],
-        ]
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn glyph_vertices_match_attribute_offsets() {
+            let instance = GlyphInstanceData::zeroed();
+
+            assert_eq!(std::mem::size_of::<GlyphQuadVertex>(), 8);
+            assert_eq!(std::mem::size_of::<GlyphInstanceData>(), 68);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, pos), 0);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, size), 8);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, uv_extents), 16);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, color), 32);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, outline_color), 48);
+            assert_eq!(bytemuck::offset_of!(instance, GlyphInstanceData, outline_width), 64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{tests::headless_device, RenderTarget, SafeAreaInsets};
+    use futures::executor::block_on;
+
+    #[test]
+    fn hud_anchored_top_right_stays_in_safe_area() {
+        let insets = SafeAreaInsets { top: 40.0, right: 10.0, bottom: 20.0, left: 30.0 };
+        let safe_area = insets.shrink(Rect::new(0.0, 0.0, 800.0, 600.0));
+
+        let alignment = TextAlignment {
+            max_width: Some(100),
+            max_height: Some(50),
+            ..TextAlignment::new(AxisAlign::End(0), AxisAlign::Start(0))
+        };
+        let settings = alignment.into_layout_settings(safe_area);
+
+        assert!(settings.x >= safe_area.x);
+        assert!(settings.x + 100.0 <= safe_area.x + safe_area.w);
+        assert!(settings.y >= safe_area.y);
+        assert!(settings.y + 50.0 <= safe_area.y + safe_area.h);
+        assert_eq!(settings.x + 100.0, safe_area.x + safe_area.w);
+        assert_eq!(settings.y, safe_area.y);
+    }
+
+    #[test]
+    fn alignment_offsets_and_max_sizes_are_scaled() {
+        let alignment = TextAlignment {
+            max_width: Some(100),
+            max_height: Some(50),
+            ..TextAlignment::new(AxisAlign::End(10), AxisAlign::Start(5))
+        };
+        let bounds = Rect::new(0.0, 0.0, 800.0, 600.0);
+        let settings = alignment.scaled(2.0).into_layout_settings(bounds);
+
+        assert_eq!(settings.x, 800.0 - 20.0 - 200.0);
+        assert_eq!(settings.y, 10.0);
+        assert_eq!(settings.max_width, Some(200.0));
+        assert_eq!(settings.max_height, Some(100.0));
+    }
+
+    fn glyph(character: char) -> StyledCharacter {
+        StyledCharacter { character, font: Font::SpaceMono400(16) }
+    }
+
+    /// Packs glyph sized rects until the atlas is full, returns how many fit.
+    fn fill(glyph_cache: &mut GlyphCache) -> usize {
+        let metrics = Metrics { width: 1000, height: 1000, ..Metrics::default() };
+        let characters = (0..).filter_map(std::char::from_u32);
+
+        characters.take_while(|c| glyph_cache.pack(glyph(*c), metrics, 0).is_some()).count()
+    }
+
+    /// Packs as many glyphs as `fill` does without running out of space,
+    /// returns their characters.
+    fn pack_to_capacity(glyph_cache: &mut GlyphCache) -> Vec<char> {
+        let metrics = Metrics { width: 1000, height: 1000, ..Metrics::default() };
+        let capacity = fill(&mut GlyphCache::new());
+        let characters: Vec<_> = (0..).filter_map(std::char::from_u32).take(capacity).collect();
+
+        for c in &characters {
+            glyph_cache.pack(glyph(*c), metrics, 0).unwrap();
+        }
+
+        characters
+    }
+
+    /// Character rects in a row, 10 pixels each, with no glyph for line breaks.
+    fn char_rects() -> impl FnMut(char) -> Option<Rect> {
+        let mut x = 0.0;
+        move |c| {
+            if c == '\n' {
+                return None;
+            }
+
+            x += 10.0;
+            Some(Rect::new(x - 10.0, 0.0, 10.0, 20.0))
+        }
+    }
+
+    #[test]
+    fn combining_marks_are_one_grapheme() {
+        let origin = Rect::new(0.0, 0.0, 0.0, 0.0);
+        let clusters = grapheme_clusters("e\u{301}x", origin, char_rects());
+
+        assert_eq!(
+            clusters,
+            vec![(0..3, Rect::new(0.0, 0.0, 20.0, 20.0)), (3..4, Rect::new(20.0, 0.0, 10.0, 20.0))]
+        );
+    }
+
+    #[test]
+    fn emoji_sequences_are_one_grapheme() {
+        let family = "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        let text = format!("a{}", family);
+        let clusters = grapheme_clusters(&text, Rect::new(0.0, 0.0, 0.0, 0.0), char_rects());
+
+        let ranges: Vec<_> = clusters.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..1, 1..text.len()]);
+        assert_eq!(clusters[1].1, Rect::new(10.0, 0.0, 50.0, 20.0));
+    }
+
+    #[test]
+    fn graphemes_without_glyphs_are_merged_into_the_previous_one() {
+        let origin = Rect::new(5.0, 5.0, 0.0, 0.0);
+
+        let clusters = grapheme_clusters("a\nb", origin, char_rects());
+        let ranges: Vec<_> = clusters.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..2, 2..3]);
+
+        assert_eq!(grapheme_clusters("\n", origin, char_rects()), vec![(0..1, origin)]);
+    }
+
+    #[test]
+    fn glyph_cache_reuses_packed_glyphs() {
+        let mut glyph_cache = GlyphCache::new();
+        let metrics = Metrics { width: 10, height: 20, ..Metrics::default() };
+
+        assert!(glyph_cache.get(&glyph('a')).is_none());
+        glyph_cache.pack(glyph('a'), metrics, 0).unwrap();
+
+        assert!(glyph_cache.get(&glyph('a')).is_some());
+        assert_eq!(glyph_cache.stats().0, 1);
+        assert!(glyph_cache.stats().1 > 0.0);
+    }
+
+    #[test]
+    fn full_glyph_cache_is_evicted_on_the_next_frame() {
+        let mut glyph_cache = GlyphCache::new();
+        glyph_cache.begin_frame(1);
+
+        let packed = fill(&mut glyph_cache);
+        assert!(packed > 0);
+
+        // Glyphs drawn this frame keep their place in the atlas.
+        glyph_cache.begin_frame(1);
+        assert_eq!(glyph_cache.stats().0, packed);
+        assert!(glyph_cache.get(&glyph('\0')).is_some());
+
+        glyph_cache.begin_frame(2);
+        assert_eq!(glyph_cache.stats(), (0, 0.0));
+        assert_eq!(fill(&mut glyph_cache), packed);
+    }
+
+    #[test]
+    fn glyph_cache_is_kept_across_frames_while_not_full() {
+        let mut glyph_cache = GlyphCache::new();
+        let metrics = Metrics { width: 10, height: 20, ..Metrics::default() };
+
+        glyph_cache.begin_frame(1);
+        glyph_cache.pack(glyph('a'), metrics, 0).unwrap();
+        glyph_cache.begin_frame(2);
+
+        assert!(glyph_cache.get(&glyph('a')).is_some());
+    }
+
+    #[test]
+    fn least_recently_used_glyph_is_evicted_when_the_atlas_is_full() {
+        let mut glyph_cache = GlyphCache::new();
+        let metrics = Metrics { width: 1000, height: 1000, ..Metrics::default() };
+
+        glyph_cache.begin_frame(1);
+        let characters = pack_to_capacity(&mut glyph_cache);
+
+        // Every glyph but the second one is used again in frame 2.
+        glyph_cache.begin_frame(2);
+        for (i, c) in characters.iter().enumerate() {
+            if i != 1 {
+                assert!(glyph_cache.touch(&glyph(*c)));
+            }
+        }
+
+        glyph_cache.begin_frame(3);
+        glyph_cache.pack(glyph('a'), metrics, 0).unwrap();
+
+        assert!(glyph_cache.get(&glyph(characters[1])).is_none());
+        assert!(glyph_cache.get(&glyph('a')).is_some());
+        assert_eq!(glyph_cache.stats().0, characters.len());
+    }
+
+    #[test]
+    fn glyphs_used_this_frame_survive_eviction() {
+        let mut glyph_cache = GlyphCache::new();
+        let metrics = Metrics { width: 1000, height: 1000, ..Metrics::default() };
+
+        glyph_cache.begin_frame(1);
+        let capacity = pack_to_capacity(&mut glyph_cache).len();
+        let new_glyphs = (0x1000..).filter_map(std::char::from_u32);
+
+        glyph_cache.begin_frame(2);
+        assert!(glyph_cache.touch(&glyph('\0')));
+        let packed =
+            new_glyphs.take_while(|c| glyph_cache.pack(glyph(*c), metrics, 0).is_some()).count();
+
+        // Every other slot was taken over, then the glyph didn't fit.
+        assert_eq!(packed, capacity - 1);
+        assert!(glyph_cache.get(&glyph('\0')).is_some());
+        assert_eq!(glyph_cache.stats().0, capacity);
+    }
+
+    #[test]
+    fn evicted_slots_fit_smaller_glyphs() {
+        let mut glyph_cache = GlyphCache::new();
+        let small = Metrics { width: 600, height: 900, ..Metrics::default() };
+
+        glyph_cache.begin_frame(1);
+        pack_to_capacity(&mut glyph_cache);
+        glyph_cache.begin_frame(2);
+        let slot = glyph_cache.pack(glyph('a'), small, 0).unwrap();
+        let metadata = glyph_cache.get(&glyph('a')).unwrap();
+
+        assert_eq!((slot.width, slot.height), (1000, 1000));
+        assert_eq!(metadata.texture_width, 600.0 / BITMAP_WIDTH as f32);
+        assert_eq!(metadata.texture_height, 900.0 / BITMAP_HEIGHT as f32);
+    }
+
+    #[test]
+    fn glyphs_fill_the_top_left_of_a_cleared_slot() {
+        let slot = rect_packer::Rect { x: 8, y: 8, width: 3, height: 2 };
+        assert_eq!(&fill_slot(&[1, 2], 2, slot)[..], [1, 2, 0, 0, 0, 0]);
+        assert_eq!(&fill_slot(&[1, 2, 3, 4, 5, 6], 3, slot)[..], [1, 2, 3, 4, 5, 6]);
+    }
+
+    /// A `size` x `size` coverage bitmap with a filled square from `start` to `end`.
+    fn square(size: usize, start: usize, end: usize) -> Vec<u8> {
+        let inside = |i: usize| (start..end).contains(&i);
+        (0..size * size)
+            .map(|i| if inside(i % size) && inside(i / size) { 255 } else { 0 })
+            .collect()
+    }
+
+    #[test]
+    fn distance_field_is_signed_and_scaled_by_the_spread() {
+        let spread = 4;
+        let field = sdf::distance_field(&square(10, 2, 8), 10, 10, spread);
+        let width = 10 + 2 * spread as usize;
+        assert_eq!(field.len(), width * width);
+
+        // The distance in pixels encoded at `x`, `y`, positive inside the square,
+        // which covers 6..12 on both axes of the field.
+        let distance =
+            |x: usize, y: usize| (field[y * width + x] as f32 / 255.0 - 0.5) * 2.0 * spread as f32;
+
+        // Inside, three pixels from empty space at the center.
+        assert!((distance(8, 8) - 3.0).abs() < 0.1, "{}", distance(8, 8));
+        // Either side of the edge.
+        assert!((distance(11, 9) - 1.0).abs() < 0.1, "{}", distance(11, 9));
+        assert!((distance(12, 9) + 1.0).abs() < 0.1, "{}", distance(12, 9));
+        // Outside, three pixels from the square.
+        assert!((distance(14, 9) + 3.0).abs() < 0.1, "{}", distance(14, 9));
+        // Diagonally off the corner, the distance is Euclidean.
+        assert!((distance(13, 13) + 8f32.sqrt()).abs() < 0.1, "{}", distance(13, 13));
+        // Further away than the spread, in the corner.
+        assert_eq!(field[0], 0);
+    }
+
+    const SPACE_MONO: &[u8] = include_bytes!("../../../resources/fonts/space_mono_400.ttf");
+
+    #[test]
+    fn malformed_font_data_is_an_error() {
+        let mut font_data = FontData::new();
+
+        assert!(font_data.load_font(&[0, 1, 2, 3]).is_err());
+        assert!(font_data.load_font(&[]).is_err());
+    }
+
+    #[test]
+    fn fonts_are_only_known_to_the_text_system_which_loaded_them() {
+        let mut font_data = FontData::new();
+        let mut other_font_data = FontData::new();
+
+        let font_id = font_data.load_font(SPACE_MONO).unwrap();
+        let other_font_id = other_font_data.load_font(SPACE_MONO).unwrap();
+        assert_ne!(font_id, other_font_id);
+
+        assert!(font_data.create_rasterizer(Font::Custom(font_id, 16)));
+        assert!(!font_data.create_rasterizer(Font::Custom(other_font_id, 16)));
+        assert!(!other_font_data.create_rasterizer(Font::Custom(font_id, 16)));
+        assert!(other_font_data.create_rasterizer(Font::SpaceMono400(16)));
+    }
+
+    #[test]
+    fn glyphs_are_cached_per_font() {
+        let mut font_data = FontData::new();
+        let font_id = font_data.load_font(SPACE_MONO).unwrap();
+        let custom = Font::Custom(font_id, 16);
+
+        let mut glyph_cache = GlyphCache::new();
+        let metrics = Metrics { width: 10, height: 20, ..Metrics::default() };
+        let larger = StyledCharacter { character: 'a', font: Font::SpaceMono400(32) };
+        glyph_cache.pack(glyph('a'), metrics, 0).unwrap();
+        glyph_cache.pack(larger, metrics, 0).unwrap();
+
+        assert!(glyph_cache.get(&StyledCharacter { character: 'a', font: custom }).is_none());
+        assert_eq!(glyph_cache.font_glyph_count(Font::SpaceMono400(16)), 2);
+        assert_eq!(glyph_cache.font_glyph_count(custom), 0);
+
+        glyph_cache.pack(StyledCharacter { character: 'a', font: custom }, metrics, 0).unwrap();
+        assert_eq!(glyph_cache.font_glyph_count(custom), 1);
+        assert_eq!(glyph_cache.stats().0, 3);
+    }
+
+    /// Every character, the ellipsis included, is as wide as the font size.
+    fn monospace_width(text: &str, font: Font) -> f32 {
+        text.chars().count() as f32 * font.size() as f32
+    }
+
+    fn line_width(text_elements: &[StyledText]) -> f32 {
+        text_elements.iter().map(|t| monospace_width(t.text, t.font)).sum()
+    }
+
+    fn styled(text: &str, size: u32) -> StyledText {
+        StyledText { font: Font::SpaceMono400(size), ..StyledText::default_styling(text) }
+    }
+
+    #[test]
+    fn ellipsis_truncation_fits_max_width() {
+        let mut text_elements = vec![styled("Some long label", 10)];
+        truncate_to_width(&mut text_elements, 75.0, Overflow::Ellipsis, monospace_width);
+
+        let texts: Vec<_> = text_elements.iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["Some l", ELLIPSIS]);
+        assert!(line_width(&text_elements) <= 75.0);
+    }
+
+    #[test]
+    fn ellipsis_takes_on_the_font_of_the_last_kept_character() {
+        let mut text_elements = vec![styled("abc", 10), styled("def", 20)];
+        truncate_to_width(&mut text_elements, 55.0, Overflow::Ellipsis, monospace_width);
+
+        let texts: Vec<_> = text_elements.iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["abc", ELLIPSIS]);
+        assert_eq!(text_elements[1].font, Font::SpaceMono400(10));
+        assert!(line_width(&text_elements) <= 55.0);
+    }
+
+    #[test]
+    fn text_which_fits_is_not_truncated() {
+        let mut text_elements = vec![styled("abc", 10), styled("def", 20)];
+        truncate_to_width(&mut text_elements, 90.0, Overflow::Ellipsis, monospace_width);
+
+        let texts: Vec<_> = text_elements.iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["abc", "def"]);
+    }
+
+    #[test]
+    fn nothing_is_drawn_when_the_ellipsis_does_not_fit() {
+        let mut text_elements = vec![styled("abc", 10)];
+        truncate_to_width(&mut text_elements, 5.0, Overflow::Ellipsis, monospace_width);
+
+        assert!(text_elements.is_empty());
+    }
+
+    #[test]
+    fn clipping_keeps_what_fits() {
+        let mut text_elements = vec![styled("abc", 10), styled("def", 20)];
+        truncate_to_width(&mut text_elements, 55.0, Overflow::Clip, monospace_width);
+
+        let texts: Vec<_> = text_elements.iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["abc", "d"]);
+        assert!(line_width(&text_elements) <= 55.0);
+    }
+
+    /// Renders `text` onto black at the top left of a frame of `size`.
+    fn render_text(
+        graphics_device: &mut GraphicsDevice,
+        text_system: &mut TextSystem,
+        text: StyledText,
+        size: PhysicalSize<u32>,
+    ) -> image::RgbaImage {
+        let target = RenderTarget::new(graphics_device);
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        frame_encoder.clear(Color::BLACK);
+        text_system.render_horizontal(
+            TextAlignment::left_top(8, 8),
+            &[text],
+            &mut frame_encoder,
+            size,
+        );
+        frame_encoder.finish();
+
+        block_on(graphics_device.capture_frame(&target)).unwrap()
+    }
+
+    /// Lays out `text` for a frame rendered to a render target, returns the
+    /// glyphs it's drawn with.
+    fn rendered_glyphs(
+        graphics_device: &mut GraphicsDevice,
+        text_system: &mut TextSystem,
+        text: StyledText,
+        text_alignment: TextAlignment,
+        bounds: Rect,
+    ) -> Vec<PositionedGlyph> {
+        let target = RenderTarget::new(graphics_device);
+        let mut frame_encoder = graphics_device.begin_frame_to_render_target(&target);
+        let glyphs = text_system.layout_glyphs(text_alignment, &[text], &mut frame_encoder, bounds);
+        frame_encoder.finish();
+
+        glyphs
+    }
+
+    /// Checks that every grapheme rect of `text` starts at the pen position its
+    /// glyph is drawn from, and overlaps the glyph vertically.
+    fn assert_layout_matches_glyphs(
+        text_system: &TextSystem,
+        text: &str,
+        font: Font,
+        layout: &[(Range<usize>, Rect)],
+        glyphs: &[PositionedGlyph],
+    ) {
+        let rasterizer = text_system.font_data.rasterizer_for_font(&font).unwrap();
+        assert_eq!(layout.len(), glyphs.len());
+
+        for ((c, (_, rect)), glyph) in text.chars().zip(layout).zip(glyphs) {
+            let xmin = rasterizer.metrics(c, font.size() as f32).xmin as f32;
+            assert!((rect.x + xmin - glyph.x).abs() < 1e-3, "{:?} at {:?}, {:?}", c, rect, glyph);
+            assert!(glyph.y < rect.y + rect.h && rect.y < glyph.y + glyph.height, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn accessible_layout_covers_multi_byte_text_where_it_is_drawn() {
+        let mut graphics_device = match headless_device(128, 32) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+        let mut text_system = TextSystem::new(&graphics_device);
+        let bounds = Rect::new(0.0, 0.0, 128.0, 32.0);
+        let text = StyledText {
+            font: Font::SpaceMono400(16),
+            ..StyledText::default_styling("h\u{e9}llo")
+        };
+
+        let glyphs = rendered_glyphs(
+            &mut graphics_device,
+            &mut text_system,
+            text,
+            TextAlignment::left_top(4, 4),
+            bounds,
+        );
+        let layout = text_system.accessible_layout(
+            text.text,
+            text.font,
+            TextAlignment::left_top(4, 4),
+            bounds,
+        );
+
+        // The ranges cover the text without gaps or overlaps.
+        let ranges: Vec<_> = layout.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..1, 1..3, 3..4, 4..5, 5..6]);
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(ranges.last().unwrap().end, text.text.len());
+
+        assert_layout_matches_glyphs(&text_system, text.text, text.font, &layout, &glyphs);
+    }
+
+    #[test]
+    fn carets_match_subpixel_glyphs_at_fractional_origins() {
+        let mut graphics_device = match headless_device(160, 32) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+        let mut text_system = TextSystem::new(&graphics_device);
+        let text =
+            StyledText { font: Font::SpaceMono400(16), ..StyledText::default_styling("hello") };
+        let alignment = || TextAlignment { subpixel: true, ..TextAlignment::left_top(4, 4) };
+
+        for &origin_x in &[10.3, 10.75] {
+            let bounds = Rect::new(origin_x, 0.0, 128.0, 32.0);
+            let glyphs =
+                rendered_glyphs(&mut graphics_device, &mut text_system, text, alignment(), bounds);
+            let layout = text_system.accessible_layout(text.text, text.font, alignment(), bounds);
+
+            assert_layout_matches_glyphs(&text_system, text.text, text.font, &layout, &glyphs);
+            assert!((layout[0].1.x - (origin_x + 4.0)).abs() < 1e-3, "{:?}", layout[0]);
+
+            // Carets are an unrounded advance apart.
+            let rasterizer = text_system.font_data.rasterizer_for_font(&text.font).unwrap();
+            for (c, pair) in text.text.chars().zip(layout.windows(2)) {
+                let advance = rasterizer.metrics(c, 16.0).advance_width;
+                assert!((pair[1].1.x - pair[0].1.x - advance).abs() < 1e-3, "After {:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn shadows_show_through_the_glyphs_drawn_over_them() {
+        let size = PhysicalSize::new(64, 64);
+        let mut graphics_device = match headless_device(size.width, size.height) {
+            Some(graphics_device) => graphics_device,
+            None => return,
+        };
+        let mut text_system = TextSystem::new(&graphics_device);
+
+        // The shadow's left stroke lands in the hole of the "O" drawn over it.
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let style = TextStyle { shadow: Some((red, [4.0, 0.0])), outline: None };
+        let text =
+            StyledText { text: "O", font: Font::SpaceMono400(40), color: Color::WHITE, style };
+        let image = render_text(&mut graphics_device, &mut text_system, text, size);
+
+        let is_white = |pixel: &image::Rgba<u8>| pixel.0[..3].iter().all(|&c| c > 200);
+        let is_red = |pixel: &image::Rgba<u8>| pixel.0[0] > 200 && pixel.0[1] < 60;
+
+        let white: Vec<_> = image.enumerate_pixels().filter(|(_, _, p)| is_white(p)).collect();
+        assert!(!white.is_empty(), "The glyph wasn't drawn");
+        let min_x = white.iter().map(|(x, _, _)| *x).min().unwrap();
+        let max_x = white.iter().map(|(x, _, _)| *x).max().unwrap();
+        let min_y = white.iter().map(|(_, y, _)| *y).min().unwrap();
+        let max_y = white.iter().map(|(_, y, _)| *y).max().unwrap();
+
+        // The glyph's quad covers at least the box around its white pixels.
+        let shadow_under_glyph = image.enumerate_pixels().any(|(x, y, pixel)| {
+            (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y) && is_red(pixel)
+        });
+        assert!(shadow_under_glyph, "The glyph's quad covered its shadow");
     }
 }
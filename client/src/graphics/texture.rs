@@ -0,0 +1,470 @@
+use crate::graphics::{
+    color_target,
+    ktx2::Ktx2,
+    resource_tracker::{ResourceKind, Tracked},
+    GraphicsDevice, PipelineBuilder, PipelineKey, ResourceId, SamplerConfig, ShaderFeatures,
+    TargetKey,
+};
+use std::{borrow::Cow, num::NonZeroU32};
+use wgpu::{Sampler, TextureView};
+
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+#[derive(Debug)]
+pub enum TextureError {
+    DecodeFailed(image::ImageError),
+    InvalidKtx2(&'static str),
+
+    /// A KTX2 file's `VkFormat` which isn't one of the formats this can load.
+    UnsupportedFormat(u32),
+
+    /// The adapter can't sample the format, e.g. BC textures on most phones.
+    FormatNotSupported(wgpu::TextureFormat),
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(err: image::ImageError) -> Self {
+        TextureError::DecodeFailed(err)
+    }
+}
+
+/// How to create a `Texture`.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureConfig {
+    /// How many mip levels to create, `None` for a full chain down to 1x1.
+    pub mip_level_count: Option<u32>,
+
+    /// Fill mip levels 1 and up by repeatedly downsampling level 0.
+    /// Otherwise they are left empty.
+    pub generate_mips: bool,
+
+    pub sampler: SamplerConfig,
+
+    /// Multiply RGB by alpha while uploading, which stops straight alpha images
+    /// from getting dark fringes where filtering mixes in transparent texels.
+    /// Premultiplied textures have to be drawn with `PREMULTIPLIED_ALPHA_BLENDING`.
+    pub premultiply_alpha: bool,
+}
+
+impl Default for TextureConfig {
+    fn default() -> Self {
+        Self {
+            mip_level_count: Some(1),
+            generate_mips: false,
+            sampler: SamplerConfig::smooth(),
+            premultiply_alpha: false,
+        }
+    }
+}
+
+impl TextureConfig {
+    /// A full, generated mip chain, for textures which are drawn smaller than their size.
+    pub fn mipmapped() -> Self {
+        Self { mip_level_count: None, generate_mips: true, ..Self::default() }
+    }
+
+    /// Nearest neighbor sampling without mips.
+    pub fn pixel_art() -> Self {
+        Self { sampler: SamplerConfig::pixel_art(), ..Self::default() }
+    }
+}
+
+/// An RGBA texture loaded from an image, with a view and sampler ready for binding.
+pub struct Texture {
+    id: ResourceId,
+    texture: wgpu::Texture,
+    view: TextureView,
+    sampler: Sampler,
+    sampler_config: SamplerConfig,
+    has_mips: bool,
+    width: u32,
+    height: u32,
+    _tracked: Tracked,
+}
+
+impl Texture {
+    /// Decodes an encoded image (e.g. the contents of a PNG file) into a texture.
+    pub fn from_image_bytes(
+        graphics_device: &GraphicsDevice,
+        bytes: &[u8],
+    ) -> Result<Self, TextureError> {
+        Self::from_image_bytes_with_config(graphics_device, bytes, TextureConfig::default())
+    }
+
+    pub fn from_image_bytes_with_config(
+        graphics_device: &GraphicsDevice,
+        bytes: &[u8],
+        config: TextureConfig,
+    ) -> Result<Self, TextureError> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        let (width, height) = image.dimensions();
+
+        Ok(Self::from_rgba_with_config(graphics_device, width, height, &image, config))
+    }
+
+    /// Creates a texture from tightly packed 8 bit RGBA pixels.
+    pub fn from_rgba(
+        graphics_device: &GraphicsDevice,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Self {
+        Self::from_rgba_with_config(
+            graphics_device,
+            width,
+            height,
+            pixels,
+            TextureConfig::default(),
+        )
+    }
+
+    pub fn from_rgba_with_config(
+        graphics_device: &GraphicsDevice,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        config: TextureConfig,
+    ) -> Self {
+        let device = graphics_device.device();
+        let extent = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+
+        let full_chain_length = 32 - width.max(height).max(1).leading_zeros();
+        let mip_level_count =
+            config.mip_level_count.unwrap_or(full_chain_length).max(1).min(full_chain_length);
+        let generate_mips = config.generate_mips && mip_level_count > 1;
+
+        let mut usage = wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST;
+        if generate_mips {
+            usage |= wgpu::TextureUsage::RENDER_ATTACHMENT;
+        }
+
+        let pixels = if config.premultiply_alpha {
+            Cow::Owned(premultiply_alpha(pixels))
+        } else {
+            Cow::Borrowed(pixels)
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture"),
+            size: extent,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage,
+        });
+
+        graphics_device.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * width),
+                rows_per_image: None,
+            },
+            extent,
+        );
+        graphics_device.mark_upload_pending();
+
+        if generate_mips {
+            Self::generate_mips(graphics_device, &texture, mip_level_count);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = config.sampler.create_sampler(device, mip_level_count > 1);
+
+        Self {
+            id: ResourceId::new(),
+            texture,
+            view,
+            sampler,
+            sampler_config: config.sampler,
+            has_mips: mip_level_count > 1,
+            width,
+            height,
+            _tracked: Tracked::new(ResourceKind::Texture),
+        }
+    }
+
+    /// Loads a KTX2 file with its mip levels, uploading them as they are without
+    /// decompressing. BC1/3/7, ETC2 and ASTC 4x4 compressed files are supported,
+    /// as well as uncompressed RGBA8. Fails with `FormatNotSupported` if the
+    /// adapter can't use the format, see `from_ktx2_or_image_bytes`.
+    pub fn from_ktx2(
+        graphics_device: &GraphicsDevice,
+        bytes: &[u8],
+        sampler_config: SamplerConfig,
+    ) -> Result<Self, TextureError> {
+        let ktx2 = Ktx2::parse(bytes)?;
+        let device = graphics_device.device();
+
+        let format_info = ktx2.format.describe();
+        if !device.features().contains(format_info.required_features) {
+            return Err(TextureError::FormatNotSupported(ktx2.format));
+        }
+
+        let (block_width, block_height) =
+            (format_info.block_dimensions.0 as u32, format_info.block_dimensions.1 as u32);
+        let block_size = format_info.block_size as u32;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture"),
+            size: wgpu::Extent3d {
+                width: ktx2.width,
+                height: ktx2.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: ktx2.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ktx2.format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        for (mip_level, data) in ktx2.levels.iter().enumerate() {
+            // Copies of compressed levels are in whole blocks, even when the
+            // level is smaller than a block.
+            let blocks_wide = ((ktx2.width >> mip_level).max(1) + block_width - 1) / block_width;
+            let blocks_high = ((ktx2.height >> mip_level).max(1) + block_height - 1) / block_height;
+
+            if data.len() < (blocks_wide * blocks_high * block_size) as usize {
+                return Err(TextureError::InvalidKtx2("level data is too small"));
+            }
+
+            graphics_device.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(blocks_wide * block_size),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: blocks_wide * block_width,
+                    height: blocks_high * block_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        graphics_device.mark_upload_pending();
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = sampler_config.create_sampler(device, ktx2.levels.len() > 1);
+
+        Ok(Self {
+            id: ResourceId::new(),
+            texture,
+            view,
+            sampler,
+            sampler_config,
+            has_mips: ktx2.levels.len() > 1,
+            width: ktx2.width,
+            height: ktx2.height,
+            _tracked: Tracked::new(ResourceKind::Texture),
+        })
+    }
+
+    /// Loads `ktx2_bytes` if the adapter supports its format, and otherwise
+    /// decodes the uncompressed `image_bytes` (e.g. a PNG of the same image),
+    /// so compressed textures can be shipped without breaking other GPUs.
+    /// Only `config.sampler` applies to the KTX2 file, its mips come from the file.
+    pub fn from_ktx2_or_image_bytes(
+        graphics_device: &GraphicsDevice,
+        ktx2_bytes: &[u8],
+        image_bytes: &[u8],
+        config: TextureConfig,
+    ) -> Result<Self, TextureError> {
+        match Self::from_ktx2(graphics_device, ktx2_bytes, config.sampler) {
+            Err(TextureError::FormatNotSupported(format)) => {
+                println!("{:?} textures aren't supported, using the uncompressed image", format);
+                Self::from_image_bytes_with_config(graphics_device, image_bytes, config)
+            },
+            result => result,
+        }
+    }
+
+    /// wgpu can't generate mips itself, so each level is rendered
+    /// by sampling the one above it with a linear filter.
+    fn generate_mips(
+        graphics_device: &GraphicsDevice,
+        texture: &wgpu::Texture,
+        mip_level_count: u32,
+    ) {
+        let device = graphics_device.device();
+
+        let key = PipelineKey {
+            shader: "mip_blit",
+            targets: vec![TargetKey::new(TEXTURE_FORMAT, Some(wgpu::BlendState::REPLACE))],
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            depth: None,
+            features: ShaderFeatures::NONE,
+        };
+
+        // The layout is only created along with the pipeline, the bind groups
+        // use the cached pipeline's.
+        let pipeline = graphics_device.pipeline_cache().get_or_create(key, || {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("mip blit"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler { filtering: true, comparison: false },
+                            count: None,
+                        },
+                    ],
+                });
+
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let vs_module = device.create_shader_module(&include_shader!("fullscreen.vert"));
+            let fs_module = device.create_shader_module(&include_shader!("post_passthrough.frag"));
+
+            PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+                .label("mip blit")
+                .color_target(color_target(TEXTURE_FORMAT, Some(wgpu::BlendState::REPLACE)))
+                .build(device)
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let mip_views: Vec<_> = (0..mip_level_count)
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Mip level"),
+                    base_mip_level: level,
+                    mip_level_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("mip generation"),
+        });
+
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+        for views in mip_views.windows(2) {
+            let (source, target) = (&views[0], &views[1]);
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: None,
+            });
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("mip blit"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        graphics_device.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Identifies this texture (with its view and sampler) in a `BindGroupCache`.
+    pub fn id(&self) -> ResourceId {
+        self.id
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Use this to build the layout entries for binding `view()` and `sampler()`.
+    pub fn sampler_config(&self) -> SamplerConfig {
+        self.sampler_config
+    }
+
+    /// Recreates the sampler with `config`, e.g. with the mip level range from
+    /// `Camera2D::pixel_perfect_sampler` after zooming. It gets a new `id`, so
+    /// bind groups from a `BindGroupCache` pick up the new sampler. The filters
+    /// should stay the same, or bind group layouts built from the old config won't match.
+    pub fn set_sampler_config(&mut self, graphics_device: &GraphicsDevice, config: SamplerConfig) {
+        if config == self.sampler_config {
+            return;
+        }
+
+        self.sampler = config.create_sampler(graphics_device.device(), self.has_mips);
+        self.sampler_config = config;
+        self.id = ResourceId::new();
+    }
+
+    /// Width and height in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+}
+
+/// Multiplies the RGB of each RGBA pixel by its alpha, rounding to the nearest value.
+fn premultiply_alpha(pixels: &[u8]) -> Vec<u8> {
+    let mut premultiplied = pixels.to_vec();
+
+    for pixel in premultiplied.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+
+    premultiplied
+}
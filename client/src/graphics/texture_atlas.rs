@@ -0,0 +1,193 @@
+use crate::graphics::{Rect, Texture};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    InvalidJson(serde_json::Error),
+    /// A line (counting from 1) with malformed numbers.
+    InvalidLine(usize),
+}
+
+#[derive(Deserialize)]
+struct PackerRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct PackerFrame {
+    frame: PackerRect,
+    #[serde(default)]
+    rotated: bool,
+}
+
+#[derive(Deserialize)]
+struct NamedPackerFrame {
+    filename: String,
+    #[serde(flatten)]
+    frame: PackerFrame,
+}
+
+/// TexturePacker writes frames either as a map from name to frame ("JSON (Hash)")
+/// or as a list of frames with their names inside ("JSON (Array)").
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PackerFrames {
+    Hash(HashMap<String, PackerFrame>),
+    Array(Vec<NamedPackerFrame>),
+}
+
+#[derive(Deserialize)]
+struct PackerAtlas {
+    frames: PackerFrames,
+}
+
+/// A texture with many images packed into it, each addressed by name.
+/// Drawing sub-regions of one atlas instead of many small textures
+/// avoids switching bind groups between sprites.
+pub struct TextureAtlas {
+    texture: Texture,
+    regions: HashMap<String, Rect>,
+}
+
+impl TextureAtlas {
+    /// Creates an atlas from regions in texture pixels.
+    pub fn new(texture: Texture, regions: HashMap<String, Rect>) -> Self {
+        Self { texture, regions }
+    }
+
+    /// Loads the regions from a TexturePacker JSON sidecar, in either
+    /// the hash or array format. Rotated sprites aren't supported and are skipped.
+    pub fn from_texture_packer_json(texture: Texture, json: &str) -> Result<Self, AtlasError> {
+        let atlas: PackerAtlas = serde_json::from_str(json).map_err(AtlasError::InvalidJson)?;
+
+        let frames: Vec<(String, PackerFrame)> = match atlas.frames {
+            PackerFrames::Hash(frames) => frames.into_iter().collect(),
+            PackerFrames::Array(frames) => {
+                frames.into_iter().map(|f| (f.filename, f.frame)).collect()
+            },
+        };
+
+        let mut regions = HashMap::new();
+
+        for (name, frame) in frames {
+            if frame.rotated {
+                println!("Skipping rotated atlas region {:?}, rotation isn't supported", name);
+                continue;
+            }
+
+            let PackerRect { x, y, w, h } = frame.frame;
+            regions.insert(name, Rect::new(x, y, w, h));
+        }
+
+        Ok(Self::new(texture, regions))
+    }
+
+    /// Loads the regions from a libGDX style `.atlas` sidecar (which TexturePacker
+    /// can also export). Only a single page is supported, and rotated regions are skipped.
+    pub fn from_libgdx_atlas(texture: Texture, atlas: &str) -> Result<Self, AtlasError> {
+        let mut regions = HashMap::new();
+
+        // The first name after a blank line is a page, the names after it are regions.
+        let mut expect_page = true;
+        let mut region: Option<(String, Rect, bool)> = None;
+
+        let mut finish_region = |region: &mut Option<(String, Rect, bool)>| {
+            if let Some((name, rect, rotated)) = region.take() {
+                if rotated {
+                    println!("Skipping rotated atlas region {:?}, rotation isn't supported", name);
+                } else {
+                    regions.insert(name, rect);
+                }
+            }
+        };
+
+        for (line_number, line) in (1..).zip(atlas.lines()) {
+            let line = line.trim();
+
+            if line.is_empty() {
+                finish_region(&mut region);
+                expect_page = true;
+                continue;
+            }
+
+            let colon = match line.find(':') {
+                Some(colon) => colon,
+                None => {
+                    finish_region(&mut region);
+
+                    if expect_page {
+                        expect_page = false;
+                    } else {
+                        region = Some((line.to_string(), Rect::new(0.0, 0.0, 0.0, 0.0), false));
+                    }
+
+                    continue;
+                },
+            };
+
+            // Keys before the first region belong to the page.
+            let (_, rect, rotated) = match region.as_mut() {
+                Some(region) => region,
+                None => continue,
+            };
+
+            let key = line[..colon].trim();
+            let value = line[colon + 1..].trim();
+            let numbers = || -> Result<Vec<f32>, AtlasError> {
+                value
+                    .split(',')
+                    .map(|n| {
+                        n.trim().parse::<f32>().map_err(|_| AtlasError::InvalidLine(line_number))
+                    })
+                    .collect()
+            };
+
+            match key {
+                "xy" => {
+                    if let [x, y] = numbers()?[..] {
+                        rect.x = x;
+                        rect.y = y;
+                    }
+                },
+                "size" => {
+                    if let [w, h] = numbers()?[..] {
+                        rect.w = w;
+                        rect.h = h;
+                    }
+                },
+                "bounds" => {
+                    if let [x, y, w, h] = numbers()?[..] {
+                        *rect = Rect::new(x, y, w, h);
+                    }
+                },
+                "rotate" => *rotated = value != "false" && value != "0",
+                _ => {},
+            }
+        }
+
+        finish_region(&mut region);
+
+        Ok(Self::new(texture, regions))
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// The region called `name`, in texture pixels.
+    pub fn region(&self, name: &str) -> Option<Rect> {
+        self.regions.get(name).copied()
+    }
+
+    /// The region called `name` in normalized texture coordinates.
+    pub fn uv_rect(&self, name: &str) -> Option<Rect> {
+        let [width, height] = self.texture.size();
+        let (width, height) = (width as f32, height as f32);
+
+        self.region(name).map(|r| Rect::new(r.x / width, r.y / height, r.w / width, r.h / height))
+    }
+}
@@ -0,0 +1,324 @@
+use crate::graphics::{
+    apply_scissor_rect, color_target, debug_marker, Camera2D, FrameEncoder, GraphicsDevice,
+    PipelineBuilder, Rect, TextureAtlas,
+};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{BindGroup, Buffer, RenderPipeline};
+use winit::dpi::PhysicalSize;
+
+/// A 4K screen full of 16x16 tiles is about 32,000 tiles.
+const MAX_VISIBLE_TILES: usize = 65_536;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct TileInstance {
+    /// `[x, y, w, h]` in world space.
+    rect: [f32; 4],
+
+    /// `[u_min, v_min, u_max, v_max]`.
+    uv_rect: [f32; 4],
+}
+
+/// A grid of tiles drawn from a `TextureAtlas`, with tile (0, 0) at the
+/// world origin and Y pointing down like `Camera2D`. Each cell holds an
+/// index into the tile names the map was created with, or `None` for an
+/// empty cell. Only the tiles visible to the camera are drawn, all of them
+/// with a single instanced draw call, so large maps cost about as much
+/// as one screen of tiles.
+pub struct TileMap {
+    width: usize,
+    height: usize,
+    tile_size: [f32; 2],
+    tiles: Vec<Option<u32>>,
+
+    /// The UV rect of each tile index, `None` if the atlas had no region for it.
+    tile_uvs: Vec<Option<Rect>>,
+
+    instances: Vec<TileInstance>,
+    instance_buffer: Buffer,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl TileMap {
+    /// Creates an empty `width` x `height` map. Tile index `i` draws the
+    /// atlas region named `tile_names[i]`, and each tile covers `tile_size`
+    /// world units.
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        atlas: &TextureAtlas,
+        tile_names: &[&str],
+        tile_size: [f32; 2],
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let device = graphics_device.device();
+        let texture = atlas.texture();
+        let sampler_config = texture.sampler_config();
+
+        let tile_uvs = tile_names
+            .iter()
+            .map(|name| {
+                let uv_rect = atlas.uv_rect(name);
+                if uv_rect.is_none() {
+                    println!("Tile {:?} isn't in the atlas, it won't be drawn", name);
+                }
+
+                uv_rect
+            })
+            .collect();
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile Map Instance Buffer"),
+            size: (MAX_VISIBLE_TILES * std::mem::size_of::<TileInstance>()) as u64,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile Map Uniform Buffer"),
+            size: 4 * 4 * 4,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tile map"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64), // Size of a 4x4 f32 matrix
+                    },
+                    count: None,
+                },
+                sampler_config.texture_layout_entry(1, wgpu::ShaderStage::FRAGMENT),
+                sampler_config.sampler_layout_entry(2, wgpu::ShaderStage::FRAGMENT),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(64),
+                    },
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(texture.sampler()),
+                },
+            ],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffers = &[wgpu::VertexBufferLayout {
+            array_stride: (std::mem::size_of::<TileInstance>()) as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![
+                0 => Float32x4,
+                1 => Float32x4,
+            ],
+        }];
+
+        let vs_module = device.create_shader_module(&include_shader!("tilemap.vert"));
+        let fs_module = device.create_shader_module(&include_shader!("tilemap.frag"));
+
+        let format = graphics_device.color_format();
+        let pipeline = PipelineBuilder::new(&pipeline_layout, &vs_module, &fs_module)
+            .label("tile map")
+            .vertex_buffers(vertex_buffers)
+            .color_target(color_target(format, Some(wgpu::BlendState::ALPHA_BLENDING)))
+            .topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .build(device);
+
+        Self {
+            width,
+            height,
+            tile_size,
+            tiles: vec![None; width * height],
+            tile_uvs,
+            instances: Vec::new(),
+            instance_buffer,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> [f32; 2] {
+        self.tile_size
+    }
+
+    /// The tile index at `(x, y)`, `None` for empty or out of bounds cells.
+    pub fn tile(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        self.tiles[y * self.width + x]
+    }
+
+    /// Sets the tile at `(x, y)`, or clears it with `None`.
+    /// Out of bounds cells are ignored.
+    pub fn set_tile(&mut self, x: usize, y: usize, index: Option<u32>) {
+        if x >= self.width || y >= self.height {
+            println!(
+                "Trying to set tile ({}, {}) outside of the {}x{} map",
+                x, y, self.width, self.height
+            );
+            return;
+        }
+
+        self.tiles[y * self.width + x] = index;
+    }
+
+    /// Changes the grid size, keeping the tiles which are still inside
+    /// of it. New cells are empty.
+    pub fn resize_grid(&mut self, width: usize, height: usize) {
+        let mut tiles = vec![None; width * height];
+
+        for y in 0..self.height.min(height) {
+            for x in 0..self.width.min(width) {
+                tiles[y * width + x] = self.tiles[y * self.width + x];
+            }
+        }
+
+        self.tiles = tiles;
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Draws the tiles inside of `camera`'s view. At most `MAX_VISIBLE_TILES`
+    /// are drawn, which only matters when zoomed out very far.
+    pub fn draw(
+        &mut self,
+        frame_encoder: &mut FrameEncoder,
+        camera: &Camera2D,
+        window_size: PhysicalSize<u32>,
+    ) {
+        let (width, height) = (window_size.width as f32, window_size.height as f32);
+        let bounds = camera.visible_bounds(width, height);
+        let [tile_width, tile_height] = self.tile_size;
+
+        // The range of cells overlapping the camera bounds.
+        let cell_range = |min: f32, max: f32, size: f32, count: usize| {
+            let start = (min / size).floor().max(0.0) as usize;
+            let end = ((max / size).ceil().max(0.0) as usize).min(count);
+            start..end.max(start)
+        };
+        let xs = cell_range(bounds.x, bounds.x + bounds.w, tile_width, self.width);
+        let ys = cell_range(bounds.y, bounds.y + bounds.h, tile_height, self.height);
+
+        self.instances.clear();
+
+        for y in ys {
+            for x in xs.clone() {
+                let uv_rect = self.tiles[y * self.width + x]
+                    .and_then(|index| self.tile_uvs.get(index as usize).copied().flatten());
+
+                if let Some(uv_rect) = uv_rect {
+                    let ([u_min, v_min], [u_max, v_max]) = (uv_rect.min(), uv_rect.max());
+
+                    self.instances.push(TileInstance {
+                        rect: [
+                            x as f32 * tile_width,
+                            y as f32 * tile_height,
+                            tile_width,
+                            tile_height,
+                        ],
+                        uv_rect: [u_min, v_min, u_max, v_max],
+                    });
+                }
+            }
+        }
+
+        if self.instances.is_empty() {
+            return;
+        }
+
+        if self.instances.len() > MAX_VISIBLE_TILES {
+            println!(
+                "Trying to render more tiles than the maximum. Max = {}, attempted render count = {}",
+                MAX_VISIBLE_TILES,
+                self.instances.len()
+            );
+            self.instances.truncate(MAX_VISIBLE_TILES);
+        }
+
+        let queue = frame_encoder.queue();
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+
+        let view_proj = camera.view_projection_matrix(width, height);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&view_proj));
+
+        frame_encoder.stats.record_upload(std::mem::size_of_val(&self.instances[..]));
+
+        let scissor_rect = frame_encoder.scissor_rect();
+        let view = frame_encoder.frame.view();
+        let encoder = &mut frame_encoder.encoder;
+        let stats = &mut frame_encoder.stats;
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("tile map"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let instance_bytes = (self.instances.len() * std::mem::size_of::<TileInstance>()) as u64;
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instance_buffer.slice(..instance_bytes));
+
+        debug_marker(&mut rpass, "tile map");
+        if apply_scissor_rect(&mut rpass, scissor_rect) {
+            rpass.draw(0..4, 0..self.instances.len() as u32);
+            stats.record_draw(2 * self.instances.len() as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_instance_matches_attribute_offsets() {
+        let instance = TileInstance::zeroed();
+
+        assert_eq!(std::mem::size_of::<TileInstance>(), 32);
+        assert_eq!(bytemuck::offset_of!(instance, TileInstance, rect), 0);
+        assert_eq!(bytemuck::offset_of!(instance, TileInstance, uv_rect), 16);
+    }
+}
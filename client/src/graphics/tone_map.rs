@@ -0,0 +1,94 @@
+use crate::graphics::{FrameEncoder, GraphicsDevice, PostProcess, RenderTarget};
+use bytemuck::{Pod, Zeroable};
+
+/// How `ToneMap` compresses HDR colors into the 0.0 - 1.0 range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// `c / (1 + c)`, soft and never clips, but desaturates highlights.
+    Reinhard,
+
+    /// An approximation of the ACES filmic curve, with more contrast.
+    Aces,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct ToneMapUniforms {
+    exposure: f32,
+    operator: u32,
+    _padding: [u32; 2],
+}
+
+/// A `PostProcess` drawing an HDR render target to the swap chain, mapping
+/// its colors into the range the swap chain can show. Create the source
+/// with `RenderTarget::new` while HDR is enabled in the `GraphicsDeviceConfig`.
+pub struct ToneMap {
+    post_process: PostProcess,
+    operator: ToneMapOperator,
+    exposure: f32,
+    uniforms_dirty: bool,
+}
+
+impl ToneMap {
+    pub fn new(
+        graphics_device: &GraphicsDevice,
+        source: &RenderTarget,
+        operator: ToneMapOperator,
+    ) -> Self {
+        if !source.is_hdr() {
+            println!("Tone mapping a {:?} render target, which isn't HDR", source.format());
+        }
+
+        let post_process = PostProcess::with_uniforms(
+            graphics_device,
+            source,
+            &include_shader!("tone_map.frag"),
+            std::mem::size_of::<ToneMapUniforms>() as u64,
+        );
+
+        Self { post_process, operator, exposure: 1.0, uniforms_dirty: true }
+    }
+
+    pub fn operator(&self) -> ToneMapOperator {
+        self.operator
+    }
+
+    pub fn set_operator(&mut self, operator: ToneMapOperator) {
+        if operator != self.operator {
+            self.operator = operator;
+            self.uniforms_dirty = true;
+        }
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Scales colors before tone mapping, 1.0 for no change. It's uploaded with the next `render`.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        if exposure != self.exposure {
+            self.exposure = exposure;
+            self.uniforms_dirty = true;
+        }
+    }
+
+    /// See `PostProcess::set_source`.
+    pub fn set_source(&mut self, graphics_device: &GraphicsDevice, source: &RenderTarget) {
+        self.post_process.set_source(graphics_device, source);
+    }
+
+    /// Draws the tone mapped source render target over the whole frame target.
+    pub fn render(&mut self, frame_encoder: &mut FrameEncoder) {
+        if self.uniforms_dirty {
+            let uniforms = ToneMapUniforms {
+                exposure: self.exposure,
+                operator: self.operator as u32,
+                _padding: [0; 2],
+            };
+            self.post_process.write_uniforms(frame_encoder.queue(), bytemuck::bytes_of(&uniforms));
+            self.uniforms_dirty = false;
+        }
+
+        self.post_process.render(frame_encoder);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::graphics::GraphicsDevice;
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferAddress, PushConstantRange, Queue, RenderPass,
+};
+
+/// Size of a 4x4 f32 matrix.
+pub(crate) const TRANSFORM_PUSH_CONSTANT_SIZE: u32 = 64;
+
+const TRANSFORM_PUSH_CONSTANT_RANGES: &[PushConstantRange] = &[PushConstantRange {
+    stages: wgpu::ShaderStage::VERTEX,
+    range: 0..TRANSFORM_PUSH_CONSTANT_SIZE,
+}];
+
+/// How a drawable passes its transform matrix to the vertex shader. With
+/// push constants the matrix is recorded straight into the render pass,
+/// so it can be changed per draw call without buffer writes or bind group
+/// changes. Otherwise it falls back to a uniform buffer in bind group 0,
+/// binding 0.
+///
+/// The two paths need different vertex shaders: the push constant one
+/// declares `layout(push_constant) uniform Locals { mat4 ... };` and the
+/// fallback `layout(set = 0, binding = 0) uniform Locals { mat4 ... };`.
+pub(crate) enum TransformBinding {
+    PushConstants,
+    Uniform { buffer: Buffer, bind_group_layout: BindGroupLayout, bind_group: BindGroup },
+}
+
+impl TransformBinding {
+    pub fn new(graphics_device: &GraphicsDevice, label: &str) -> Self {
+        if graphics_device.supports_push_constants() {
+            return TransformBinding::PushConstants;
+        }
+
+        let device = graphics_device.device();
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: TRANSFORM_PUSH_CONSTANT_SIZE as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(TRANSFORM_PUSH_CONSTANT_SIZE as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(TRANSFORM_PUSH_CONSTANT_SIZE as u64),
+                },
+            }],
+            label: Some(label),
+        });
+
+        TransformBinding::Uniform { buffer, bind_group_layout, bind_group }
+    }
+
+    pub fn uses_push_constants(&self) -> bool {
+        matches!(self, TransformBinding::PushConstants)
+    }
+
+    /// The bind group layouts to put in the pipeline layout.
+    pub fn bind_group_layouts(&self) -> Vec<&BindGroupLayout> {
+        match self {
+            TransformBinding::PushConstants => Vec::new(),
+            TransformBinding::Uniform { bind_group_layout, .. } => vec![bind_group_layout],
+        }
+    }
+
+    /// The push constant ranges to put in the pipeline layout.
+    pub fn push_constant_ranges(&self) -> &'static [PushConstantRange] {
+        match self {
+            TransformBinding::PushConstants => TRANSFORM_PUSH_CONSTANT_RANGES,
+            TransformBinding::Uniform { .. } => &[],
+        }
+    }
+
+    /// Uploads `transform` for the uniform buffer path. This has to happen
+    /// before the render pass begins, and does nothing with push constants.
+    pub fn write(&self, queue: &Queue, transform: &[[f32; 4]; 4]) {
+        if let TransformBinding::Uniform { buffer, .. } = self {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(transform));
+        }
+    }
+
+    /// Makes `transform` available to subsequent draws in `render_pass`. For
+    /// the uniform buffer path this binds the transform last passed to `write`.
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>, transform: &[[f32; 4]; 4]) {
+        match self {
+            TransformBinding::PushConstants => {
+                render_pass.set_push_constants(
+                    wgpu::ShaderStage::VERTEX,
+                    0,
+                    bytemuck::cast_slice(transform),
+                );
+            },
+            TransformBinding::Uniform { bind_group, .. } => {
+                render_pass.set_bind_group(0, bind_group, &[]);
+            },
+        }
+    }
+}
+
+/// Packs many transform matrices into one uniform buffer, so a distinct
+/// transform can be used for each draw call by rebinding a single bind group
+/// with a different dynamic offset, instead of allocating a bind group per draw.
+/// Each transform is placed at a multiple of `wgpu::BIND_BUFFER_ALIGNMENT`
+/// (256 bytes), which is the alignment wgpu requires for dynamic offsets.
+///
+/// Per frame: `clear`, `push` every transform (keeping the returned offsets),
+/// `upload` before the render pass begins, and then `bind` with each offset
+/// before the corresponding draw call. The bind group layout puts the
+/// transform at binding 0 of whichever group index the pipeline uses.
+///
+/// When the adapter supports push constants there's no buffer, `bind` records
+/// the transform at the offset straight into the render pass like
+/// `TransformBinding` does. Lay out pipelines with `bind_group_layouts` and
+/// `push_constant_ranges` so they fit either way.
+pub struct TransformBufferPool {
+    staging: Vec<u8>,
+    capacity: usize,
+
+    /// `None` when using push constants.
+    uniforms: Option<PoolUniforms>,
+}
+
+struct PoolUniforms {
+    buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl TransformBufferPool {
+    const STRIDE: BufferAddress = wgpu::BIND_BUFFER_ALIGNMENT;
+
+    /// Creates a pool with room for `capacity` transforms per frame.
+    pub fn new(graphics_device: &GraphicsDevice, capacity: usize) -> Self {
+        let uniforms = if graphics_device.supports_push_constants() {
+            None
+        } else {
+            Some(Self::create_uniforms(graphics_device, capacity))
+        };
+
+        Self { staging: Vec::with_capacity(capacity * Self::STRIDE as usize), capacity, uniforms }
+    }
+
+    fn create_uniforms(graphics_device: &GraphicsDevice, capacity: usize) -> PoolUniforms {
+        let device = graphics_device.device();
+        let transform_size = wgpu::BufferSize::new(TRANSFORM_PUSH_CONSTANT_SIZE as u64);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Transform Buffer Pool"),
+            size: capacity.max(1) as u64 * Self::STRIDE,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Transform Buffer Pool"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: transform_size,
+                },
+                count: None,
+            }],
+        });
+
+        // The binding only covers one transform, the dynamic offset picks which one.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: transform_size,
+                },
+            }],
+            label: Some("Transform Buffer Pool"),
+        });
+
+        PoolUniforms { buffer, bind_group_layout, bind_group }
+    }
+
+    pub fn uses_push_constants(&self) -> bool {
+        self.uniforms.is_none()
+    }
+
+    /// The bind group layouts to put in the pipeline layout, at the group index
+    /// passed to `bind`. Empty with push constants.
+    pub fn bind_group_layouts(&self) -> Vec<&BindGroupLayout> {
+        self.uniforms.iter().map(|uniforms| &uniforms.bind_group_layout).collect()
+    }
+
+    /// The push constant ranges to put in the pipeline layout.
+    pub fn push_constant_ranges(&self) -> &'static [PushConstantRange] {
+        match self.uniforms {
+            Some(_) => &[],
+            None => TRANSFORM_PUSH_CONSTANT_RANGES,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.staging.len() / Self::STRIDE as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.staging.is_empty()
+    }
+
+    /// Removes all transforms, call this at the start of each frame.
+    pub fn clear(&mut self) {
+        self.staging.clear();
+    }
+
+    /// Adds a transform and returns its dynamic offset, or `None` if the pool is full.
+    pub fn push(&mut self, transform: &[[f32; 4]; 4]) -> Option<u32> {
+        if self.len() >= self.capacity {
+            println!(
+                "Trying to push more transforms than the pool can hold. Max = {}",
+                self.capacity
+            );
+            return None;
+        }
+
+        let offset = self.staging.len();
+        self.staging.extend_from_slice(bytemuck::cast_slice(transform));
+        self.staging.resize(offset + Self::STRIDE as usize, 0);
+
+        Some(offset as u32)
+    }
+
+    /// Uploads all pushed transforms. Call this before the render pass begins.
+    pub fn upload(&self, queue: &Queue) {
+        if let (Some(uniforms), false) = (&self.uniforms, self.staging.is_empty()) {
+            queue.write_buffer(&uniforms.buffer, 0, &self.staging);
+        }
+    }
+
+    /// Binds the transform at `offset` (as returned by `push`) to bind group `index`,
+    /// or sets it as the push constants.
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>, index: u32, offset: u32) {
+        match &self.uniforms {
+            Some(uniforms) => render_pass.set_bind_group(index, &uniforms.bind_group, &[offset]),
+            None => {
+                let offset = offset as usize;
+                let transform =
+                    &self.staging[offset..offset + TRANSFORM_PUSH_CONSTANT_SIZE as usize];
+                render_pass.set_push_constants(wgpu::ShaderStage::VERTEX, 0, transform);
+            },
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::graphics::Color;
+use std::{f32::consts::PI, time::Duration};
+
+/// Values a `Tween` can animate.
+pub trait Lerp: Copy {
+    /// `self` at `t` = 0.0 and `to` at `t` = 1.0. `t` can go past either
+    /// end for easings which overshoot, like `Easing::EaseOutBack`.
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+/// Positions and sizes.
+impl Lerp for [f32; 2] {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        [self[0].lerp(to[0], t), self[1].lerp(to[1], t)]
+    }
+}
+
+/// Blends every component, alpha included, so fades work.
+impl Lerp for Color {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        Color::new(
+            self.r.lerp(to.r, t),
+            self.g.lerp(to.g, t),
+            self.b.lerp(to.b, t),
+            self.a.lerp(to.a, t),
+        )
+    }
+}
+
+/// How a `Tween` moves from its start to its end value over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+
+    /// Overshoots the end value a little and settles back, e.g. for popping in buttons.
+    EaseOutBack,
+
+    /// Bounces on the end value like a dropped ball.
+    EaseOutBounce,
+}
+
+impl Easing {
+    /// Maps `t` going from 0.0 to 1.0 to how far along the value is.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => 0.5 - (PI * t).cos() / 2.0,
+            Easing::EaseOutBack => {
+                const OVERSHOOT: f32 = 1.70158;
+                let t = t - 1.0;
+                1.0 + (OVERSHOOT + 1.0) * t * t * t + OVERSHOOT * t * t
+            },
+            Easing::EaseOutBounce => {
+                const N: f32 = 7.5625;
+                const D: f32 = 2.75;
+
+                if t < 1.0 / D {
+                    N * t * t
+                } else if t < 2.0 / D {
+                    let t = t - 1.5 / D;
+                    N * t * t + 0.75
+                } else if t < 2.5 / D {
+                    let t = t - 2.25 / D;
+                    N * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D;
+                    N * t * t + 0.984375
+                }
+            },
+        }
+    }
+}
+
+/// A value animating from `from` to `to` over `duration`, like a fade-in
+/// alpha or a sliding panel's position. Call `update` once per frame with
+/// `FrameClock::delta` and read `value`.
+#[derive(Debug, Clone)]
+pub struct Tween<T: Lerp> {
+    from: T,
+    to: T,
+    duration: Duration,
+    easing: Easing,
+    elapsed: Duration,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: Duration, easing: Easing) -> Self {
+        Self { from, to, duration, easing, elapsed: Duration::from_secs(0) }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(self.to, self.easing.apply(self.progress()))
+    }
+
+    /// How much of the duration has passed, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            return 1.0;
+        }
+
+        self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Starts over from `from`.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+    }
+
+    /// Starts a new tween from the current value to `to`, so e.g. a hover
+    /// scale turning around halfway doesn't jump.
+    pub fn retarget(&mut self, to: T, duration: Duration) {
+        self.from = self.value();
+        self.to = to;
+        self.duration = duration;
+        self.elapsed = Duration::from_secs(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 6] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+        Easing::EaseOutBack,
+        Easing::EaseOutBounce,
+    ];
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        for easing in &EASINGS {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{:?} at 0.0", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{:?} at 1.0", easing);
+        }
+    }
+
+    #[test]
+    fn easings_clamp_t() {
+        for easing in &EASINGS {
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(2.0), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn tween_reaches_its_end_value() {
+        let mut tween = Tween::new(10.0, 20.0, Duration::from_millis(100), Easing::Linear);
+
+        tween.update(Duration::from_millis(50));
+        assert!((tween.value() - 15.0).abs() < 1e-4);
+        assert!(!tween.is_done());
+
+        tween.update(Duration::from_millis(80));
+        assert_eq!(tween.value(), 20.0);
+        assert!(tween.is_done());
+    }
+
+    #[test]
+    fn zero_duration_tween_is_done_right_away() {
+        let tween = Tween::new(0.0, 1.0, Duration::from_secs(0), Easing::EaseIn);
+
+        assert!(tween.is_done());
+        assert_eq!(tween.value(), 1.0);
+    }
+
+    #[test]
+    fn retarget_starts_from_the_current_value() {
+        let mut tween = Tween::new(0.0, 10.0, Duration::from_millis(100), Easing::Linear);
+        tween.update(Duration::from_millis(50));
+        tween.retarget(0.0, Duration::from_millis(100));
+
+        assert!((tween.value() - 5.0).abs() < 1e-4);
+    }
+}
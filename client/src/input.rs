@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use winit::{
+    dpi::{LogicalPosition, PhysicalPosition},
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent,
+    },
+};
+
+pub use winit::event::VirtualKeyCode as KeyCode;
+
+/// Roughly how many pixels one line of mouse wheel scrolling is,
+/// for converting touchpad pixel scrolling into lines.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+/// Keyboard and mouse state, built up from winit events. Call `update` with
+/// every event, read the state during the frame, and call `end_frame` once
+/// gameplay code has seen it so the per-frame state starts over.
+pub struct InputState {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+
+    mouse_buttons_down: HashSet<MouseButton>,
+    mouse_buttons_pressed: HashSet<MouseButton>,
+    mouse_buttons_released: HashSet<MouseButton>,
+
+    mouse_position: PhysicalPosition<f64>,
+    mouse_motion: [f64; 2],
+    scroll_delta: [f32; 2],
+    scale_factor: f64,
+}
+
+impl InputState {
+    /// `scale_factor` is the window's current scale factor, used for logical positions.
+    pub fn new(scale_factor: f64) -> Self {
+        Self {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            mouse_buttons_down: HashSet::new(),
+            mouse_buttons_pressed: HashSet::new(),
+            mouse_buttons_released: HashSet::new(),
+            mouse_position: PhysicalPosition::new(0.0, 0.0),
+            mouse_motion: [0.0, 0.0],
+            scroll_delta: [0.0, 0.0],
+            scale_factor,
+        }
+    }
+
+    pub fn update<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { event, .. } => self.handle_window_event(event),
+            Event::DeviceEvent { event, .. } => self.handle_device_event(event),
+            _ => {},
+        }
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { virtual_keycode: Some(key), state, .. },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    // Held keys repeat their pressed events.
+                    if self.keys_down.insert(*key) {
+                        self.keys_pressed.insert(*key);
+                    }
+                },
+                ElementState::Released => {
+                    self.keys_down.remove(key);
+                    self.keys_released.insert(*key);
+                },
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.mouse_buttons_down.insert(*button);
+                    self.mouse_buttons_pressed.insert(*button);
+                },
+                ElementState::Released => {
+                    self.mouse_buttons_down.remove(button);
+                    self.mouse_buttons_released.insert(*button);
+                },
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_position = *position;
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(delta) => (
+                        delta.x as f32 / PIXELS_PER_SCROLL_LINE,
+                        delta.y as f32 / PIXELS_PER_SCROLL_LINE,
+                    ),
+                };
+
+                self.scroll_delta[0] += x;
+                self.scroll_delta[1] += y;
+            },
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor;
+            },
+            WindowEvent::Focused(false) => {
+                // Release events for keys held while losing focus never arrive.
+                self.keys_released.extend(self.keys_down.drain());
+                self.mouse_buttons_released.extend(self.mouse_buttons_down.drain());
+            },
+            _ => {},
+        }
+    }
+
+    fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            self.mouse_motion[0] += dx;
+            self.mouse_motion[1] += dy;
+        }
+    }
+
+    /// Clears the state which only lasts for one frame.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.mouse_buttons_pressed.clear();
+        self.mouse_buttons_released.clear();
+        self.mouse_motion = [0.0, 0.0];
+        self.scroll_delta = [0.0, 0.0];
+    }
+
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Whether `key` went down this frame.
+    pub fn was_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Whether `key` went up this frame.
+    pub fn was_key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_down.contains(&button)
+    }
+
+    pub fn was_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_pressed.contains(&button)
+    }
+
+    pub fn was_mouse_button_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_released.contains(&button)
+    }
+
+    /// The cursor position in physical pixels, relative to the top left of the window.
+    pub fn mouse_position(&self) -> PhysicalPosition<f64> {
+        self.mouse_position
+    }
+
+    /// The cursor position in logical pixels (physical pixels divided by the scale factor).
+    pub fn logical_mouse_position(&self) -> LogicalPosition<f64> {
+        self.mouse_position.to_logical(self.scale_factor)
+    }
+
+    /// Raw mouse movement this frame, not limited by the window edges.
+    pub fn mouse_motion(&self) -> [f64; 2] {
+        self.mouse_motion
+    }
+
+    /// Scrolling this frame, in lines. Positive Y scrolls up.
+    pub fn scroll_delta(&self) -> [f32; 2] {
+        self.scroll_delta
+    }
+}
@@ -1,226 +1,245 @@
-use crate::graphics::{
-    text::{AxisAlign, Color, Font, StyledText, TextAlignment, TextSystem},
-    GraphicsDevice, TexturedQuad,
+use crate::{
+    app::{run_app, App},
+    graphics::{
+        text::{AxisAlign, Font, Overflow, StyledText, TextAlignment, TextSystem},
+        Color, DebugOverlay, FrameEncoder, GraphicsDevice, GraphicsDeviceConfig, RenderLayer,
+        ResourceTracker, TexturedQuad,
+    },
 };
+use input::{InputState, KeyCode};
+use sus_common::PlayerInput;
+
+#[cfg(not(target_arch = "wasm32"))]
+use instant::Instant;
+#[cfg(not(target_arch = "wasm32"))]
 use laminar::{Config as NetworkConfig, Packet, Socket, SocketEvent};
-use std::time::{Duration, Instant};
-use sus_common::{
-    network::{ClientToServer, ConnectPacket, PlayerInputPacket, ServerToClient},
-    PlayerInput,
-};
-use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{net::SocketAddr, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use sus_common::network::{ClientToServer, ConnectPacket, PlayerInputPacket, ServerToClient};
 
+mod app;
 mod graphics;
+mod input;
+mod text_input;
 
-const TARGET_FPS: usize = 60;
-const FRAME_DT: Duration = Duration::from_micros((1000000.0 / TARGET_FPS as f64) as u64);
+const TARGET_FPS: u32 = 60;
+#[cfg(not(target_arch = "wasm32"))]
 const SERVER_ADDR: &str = "127.0.0.1:7600";
 
-async fn run() {
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().with_title("sus").build(&event_loop).unwrap();
-
-    let mut graphics_device = GraphicsDevice::new(&window).await;
-    let textured_quad = TexturedQuad::new(&graphics_device);
-    let mut text_system = TextSystem::new(&graphics_device);
-
-    let mut last_frame_time = Instant::now();
-
-    // Connect to the server
-    let net_config = NetworkConfig {
-        idle_connection_timeout: Duration::from_secs(5),
-        heartbeat_interval: Some(Duration::from_secs(4)),
-        ..NetworkConfig::default()
-    };
-    let mut socket =
-        Socket::bind_with_config("127.0.0.1:0", net_config).expect("Could not connect to server");
-    let server_addr = SERVER_ADDR.parse().unwrap();
-    let connect_packet = ClientToServer::Connect(ConnectPacket::new("Brian"));
-    socket
-        .send(Packet::reliable_ordered(
-            server_addr,
-            bincode::serialize(&connect_packet).unwrap(),
-            None,
-        ))
-        .expect("Could not send packet to server");
-
-    let mut connected = false;
+struct Client {
+    textured_quad: TexturedQuad,
+    text_system: TextSystem,
+    debug_overlay: DebugOverlay,
+    fps: f32,
+    exit: bool,
+
+    // Browsers can't open UDP sockets, so the web client doesn't connect yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    socket: Socket,
+    #[cfg(not(target_arch = "wasm32"))]
+    server_addr: SocketAddr,
+    #[cfg(not(target_arch = "wasm32"))]
+    connected: bool,
 
     // Game state
-    let mut player_input = PlayerInput::new();
-
-    event_loop.run(move |event, _, control_flow| {
-        match event {
-            Event::MainEventsCleared => {
-                if last_frame_time.elapsed() >= FRAME_DT {
-                    let now = Instant::now();
-                    last_frame_time = now;
-
-                    // Game logic here
-                    // Consider using this: https://github.com/tuzz/game-loop
-                    let input_packet: PlayerInputPacket = (&player_input).into();
-                    let msg = ClientToServer::PlayerInput(input_packet);
-                    socket
-                        .send(Packet::unreliable_sequenced(
-                            server_addr,
-                            bincode::serialize(&msg).unwrap(),
-                            Some(sus_common::network::INPUT_STREAM),
-                        ))
-                        .expect("Could not send packet to server");
-
-                    socket.manual_poll(now);
-                    match socket.recv() {
-                        Some(SocketEvent::Packet(packet)) => {
-                            let msg = packet.payload();
-
-                            if packet.addr() == server_addr {
-                                if let Ok(decoded) = bincode::deserialize::<ServerToClient>(msg) {
-                                    match decoded {
-                                        ServerToClient::ConnectAck => {
-                                            println!("Server accepted us, yay!");
-                                            connected = true;
-                                        },
-                                        ServerToClient::NewPlayer(new_player_packet) => {
-                                            println!("New player: {:?}", new_player_packet);
-                                        },
-                                        ServerToClient::FullGameState(full_game_state) => {
-                                            println!("Full game state: {:?}", full_game_state);
-                                        },
-                                        ServerToClient::PlayerMovement => {
-                                            println!("Player moved!");
-                                        },
-                                    }
-                                }
-                            } else {
-                                println!("Unknown sender.");
-                            }
-                        },
-                        Some(SocketEvent::Timeout(addr)) => {
-                            println!("Server timed out: {}", addr);
-                        },
-                        Some(SocketEvent::Connect(addr)) => {
-                            println!("Server connected: {}", addr);
-                        },
-                        Some(SocketEvent::Disconnect(addr)) => {
-                            println!("Server disconnected: {}", addr);
-                        },
-                        None => {},
-                    }
+    player_input: PlayerInput,
+}
 
-                    window.request_redraw();
+impl Client {
+    fn new(graphics_device: &mut GraphicsDevice) -> Self {
+        graphics_device.set_target_fps(Some(TARGET_FPS));
+
+        Self {
+            textured_quad: TexturedQuad::new(graphics_device),
+            text_system: TextSystem::new(graphics_device),
+            debug_overlay: DebugOverlay::new(graphics_device),
+            fps: 0.0,
+            exit: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            socket: Self::connect(),
+            #[cfg(not(target_arch = "wasm32"))]
+            server_addr: SERVER_ADDR.parse().unwrap(),
+            #[cfg(not(target_arch = "wasm32"))]
+            connected: false,
+            player_input: PlayerInput::new(),
+        }
+    }
+
+    /// Binds a socket and sends the server a connect packet.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn connect() -> Socket {
+        let net_config = NetworkConfig {
+            idle_connection_timeout: Duration::from_secs(5),
+            heartbeat_interval: Some(Duration::from_secs(4)),
+            ..NetworkConfig::default()
+        };
+        let mut socket = Socket::bind_with_config("127.0.0.1:0", net_config)
+            .expect("Could not connect to server");
+        let server_addr = SERVER_ADDR.parse().unwrap();
+        let connect_packet = ClientToServer::Connect(ConnectPacket::new("Brian"));
+        socket
+            .send(Packet::reliable_ordered(
+                server_addr,
+                bincode::serialize(&connect_packet).unwrap(),
+                None,
+            ))
+            .expect("Could not send packet to server");
+
+        socket
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_network(&mut self) {
+        let input_packet: PlayerInputPacket = (&self.player_input).into();
+        let msg = ClientToServer::PlayerInput(input_packet);
+        self.socket
+            .send(Packet::unreliable_sequenced(
+                self.server_addr,
+                bincode::serialize(&msg).unwrap(),
+                Some(sus_common::network::INPUT_STREAM),
+            ))
+            .expect("Could not send packet to server");
+
+        self.socket.manual_poll(Instant::now());
+        match self.socket.recv() {
+            Some(SocketEvent::Packet(packet)) => {
+                let msg = packet.payload();
+
+                if packet.addr() == self.server_addr {
+                    if let Ok(decoded) = bincode::deserialize::<ServerToClient>(msg) {
+                        match decoded {
+                            ServerToClient::ConnectAck => {
+                                println!("Server accepted us, yay!");
+                                self.connected = true;
+                            },
+                            ServerToClient::NewPlayer(new_player_packet) => {
+                                println!("New player: {:?}", new_player_packet);
+                            },
+                            ServerToClient::FullGameState(full_game_state) => {
+                                println!("Full game state: {:?}", full_game_state);
+                            },
+                            ServerToClient::PlayerMovement => {
+                                println!("Player moved!");
+                            },
+                        }
+                    }
+                } else {
+                    println!("Unknown sender.");
                 }
             },
-            Event::WindowEvent { event: WindowEvent::Resized(new_size), .. } => {
-                println!("Resizing to {}x{}", new_size.width, new_size.height);
-                graphics_device.resize(new_size);
-
-                window.request_redraw();
+            Some(SocketEvent::Timeout(addr)) => {
+                println!("Server timed out: {}", addr);
             },
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                },
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            virtual_keycode: Some(virtual_code),
-                            state: ElementState::Pressed,
-                            ..
-                        },
-                    ..
-                } => {
-                    if let VirtualKeyCode::Escape = virtual_code {
-                        *control_flow = ControlFlow::Exit;
-                    }
-
-                    match virtual_code {
-                        VirtualKeyCode::Escape => *control_flow = ControlFlow::Exit,
-                        VirtualKeyCode::W => player_input.up = true,
-                        VirtualKeyCode::A => player_input.left = true,
-                        VirtualKeyCode::S => player_input.down = true,
-                        VirtualKeyCode::D => player_input.right = true,
-                        _ => {},
-                    }
-                },
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            virtual_keycode: Some(virtual_code),
-                            state: ElementState::Released,
-                            ..
-                        },
-                    ..
-                } => {
-                    if let VirtualKeyCode::Escape = virtual_code {
-                        *control_flow = ControlFlow::Exit;
-                    }
-
-                    match virtual_code {
-                        VirtualKeyCode::W => player_input.up = false,
-                        VirtualKeyCode::A => player_input.left = false,
-                        VirtualKeyCode::S => player_input.down = false,
-                        VirtualKeyCode::D => player_input.right = false,
-                        _ => {},
-                    }
-                },
-                _ => (),
+            Some(SocketEvent::Connect(addr)) => {
+                println!("Server connected: {}", addr);
             },
-            Event::RedrawRequested(_window_id) => {
-                // Draw the scene
-                let mut frame_encoder = graphics_device.begin_frame();
-                textured_quad.render(&text_system.glyph_painter.bind_group, &mut frame_encoder);
-                text_system.render_horizontal(
-                    TextAlignment {
-                        x: AxisAlign::Start(10),
-                        y: AxisAlign::WindowCenter,
-                        max_width: None,
-                        max_height: None,
-                    },
-                    &[
-                        StyledText::default_styling("hello"),
-                        // StyledText {
-                        //     text: "Another test, blue this time",
-                        //     font: Font::SpaceMono400(40),
-                        //     color: Color::new(0, 0, 255, 255),
-                        // },
-                        // StyledText {
-                        //     text: "\nTest with a line break, green.",
-                        //     font: Font::SpaceMono400(40),
-                        //     color: Color::new(0, 255, 0, 255),
-                        // },
-                        // StyledText {
-                        //     text: "Red test\nHere are some numbers:\n0123456789!@#$%^&*(){}[].",
-                        //     font: Font::SpaceMono400(40),
-                        //     color: Color::new(255, 0, 0, 255),
-                        // },
-                        // StyledText {
-                        //     text: "\nOpacity test, this should be half-faded white",
-                        //     font: Font::SpaceMono400(40),
-                        //     color: Color::new(255, 255, 255, 128),
-                        // },
-                        // StyledText {
-                        //     text: &format!(
-                        //         "\nServer addr: {}\nConnected: {}",
-                        //         server_addr, connected
-                        //     ),
-                        //     font: Font::SpaceMono400(40),
-                        //     color: Color::new(255, 255, 255, 255),
-                        // },
-                    ],
-                    &mut frame_encoder,
-                    window.inner_size(),
-                );
-                frame_encoder.finish();
+            Some(SocketEvent::Disconnect(addr)) => {
+                println!("Server disconnected: {}", addr);
             },
-            _ => (),
+            None => {},
         }
-    });
+    }
+}
+
+impl App for Client {
+    fn update(&mut self, input: &InputState, dt: f32) {
+        // Game logic here
+        // Consider using this: https://github.com/tuzz/game-loop
+        if input.was_key_pressed(KeyCode::Escape) {
+            self.exit = true;
+        }
+
+        if dt > 0.0 {
+            self.fps = 1.0 / dt;
+        }
+
+        self.player_input.up = input.is_key_down(KeyCode::W);
+        self.player_input.left = input.is_key_down(KeyCode::A);
+        self.player_input.down = input.is_key_down(KeyCode::S);
+        self.player_input.right = input.is_key_down(KeyCode::D);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_network();
+    }
+
+    fn render<'a>(&'a mut self, frame_encoder: &mut FrameEncoder<'a>) {
+        let window_size = frame_encoder.design_size();
+        self.debug_overlay.debug_text(10, 10, &format!("FPS: {:.0}", self.fps));
+
+        if ResourceTracker::is_enabled() {
+            let stats = ResourceTracker::stats();
+            self.debug_overlay.debug_text(
+                10,
+                30,
+                &format!(
+                    "Textures: {} Buffers: {} Bind groups: {} Pipelines: {} Swap chains: {}",
+                    stats.textures,
+                    stats.buffers,
+                    stats.bind_groups,
+                    stats.pipelines,
+                    stats.swap_chains
+                ),
+            );
+        }
+
+        self.textured_quad.render(&self.text_system.glyph_painter.bind_group, frame_encoder);
+        self.text_system.render_horizontal(
+            TextAlignment {
+                x: AxisAlign::Start(10),
+                y: AxisAlign::WindowCenter,
+                max_width: None,
+                max_height: None,
+                overflow: Overflow::Wrap,
+                subpixel: false,
+            },
+            &[
+                StyledText::default_styling("hello"),
+                // StyledText {
+                //     text: "Another test, blue this time",
+                //     font: Font::SpaceMono400(40),
+                //     color: Color::rgba8(0, 0, 255, 255),
+                // },
+                // StyledText {
+                //     text: "\nTest with a line break, green.",
+                //     font: Font::SpaceMono400(40),
+                //     color: Color::rgba8(0, 255, 0, 255),
+                // },
+                // StyledText {
+                //     text: "Red test\nHere are some numbers:\n0123456789!@#$%^&*(){}[].",
+                //     font: Font::SpaceMono400(40),
+                //     color: Color::rgba8(255, 0, 0, 255),
+                // },
+                // StyledText {
+                //     text: "\nOpacity test, this should be half-faded white",
+                //     font: Font::SpaceMono400(40),
+                //     color: Color::rgba8(255, 255, 255, 128),
+                // },
+                // StyledText {
+                //     text: &format!(
+                //         "\nServer addr: {}\nConnected: {}",
+                //         server_addr, connected
+                //     ),
+                //     font: Font::SpaceMono400(40),
+                //     color: Color::rgba8(255, 255, 255, 255),
+                // },
+            ],
+            frame_encoder,
+            window_size,
+        );
+        frame_encoder.submit(RenderLayer::UI, &mut self.debug_overlay);
+    }
+
+    fn should_exit(&self) -> bool {
+        self.exit
+    }
 }
 
 fn main() {
-    futures::executor::block_on(run());
+    #[cfg(target_arch = "wasm32")]
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    run_app("sus", GraphicsDeviceConfig::default(), |graphics_device, _window| {
+        Client::new(graphics_device)
+    });
 }
@@ -0,0 +1,142 @@
+use crate::input::KeyCode;
+use unicode_segmentation::UnicodeSegmentation;
+use winit::event::{ElementState, Event, KeyboardInput, WindowEvent};
+
+/// A single line of text being typed, e.g. into a chat box. Characters come
+/// from winit's `ReceivedCharacter`, so they follow the keyboard layout and
+/// whatever the platform's input method produced, rather than key codes.
+/// Editing and cursor movement work in grapheme clusters, so backspace removes
+/// a whole emoji or accented letter even when it's several codepoints.
+///
+/// Feed it every event with `update` (e.g. from `App::handle_event`) while the
+/// chat box is focused. Return submits the line, which `take_committed` hands out.
+#[derive(Debug, Default)]
+pub struct TextInput {
+    text: String,
+
+    /// Byte offset into `text`, always on a grapheme boundary.
+    cursor: usize,
+
+    /// Lines submitted with return which haven't been taken yet.
+    committed: String,
+
+    /// The input method's composition in progress. winit 0.24 doesn't report
+    /// composition events, input methods deliver their committed text as
+    /// characters, so this stays empty until winit does.
+    preview: String,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update<T>(&mut self, event: &Event<T>) {
+        let event = match event {
+            Event::WindowEvent { event, .. } => event,
+            _ => return,
+        };
+
+        match event {
+            WindowEvent::ReceivedCharacter(c) => {
+                // Backspace, delete, return, etc. arrive as control characters which
+                // differ between platforms, they're handled as keys below instead.
+                if !c.is_control() {
+                    self.insert(&c.to_string());
+                }
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput { virtual_keycode: Some(key), state: ElementState::Pressed, .. },
+                ..
+            } => match key {
+                KeyCode::Back => self.backspace(),
+                KeyCode::Delete => self.delete(),
+                KeyCode::Left => self.cursor = self.previous_boundary(),
+                KeyCode::Right => self.cursor = self.next_boundary(),
+                KeyCode::Home => self.cursor = 0,
+                KeyCode::End => self.cursor = self.text.len(),
+                KeyCode::Return | KeyCode::NumpadEnter => self.commit(),
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
+    /// Inserts `text` at the cursor and moves the cursor after it.
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Removes the grapheme before the cursor.
+    pub fn backspace(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Removes the grapheme after the cursor.
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// The line being typed, without the composition preview.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The cursor position as a byte offset into `text`.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The input method's composition in progress, to draw at the cursor.
+    pub fn preview(&self) -> &str {
+        &self.preview
+    }
+
+    /// The line with the composition preview inserted at the cursor, for drawing
+    /// the text field with `TextSystem`.
+    pub fn display_text(&self) -> String {
+        let mut display_text = self.text.clone();
+        display_text.insert_str(self.cursor, &self.preview);
+        display_text
+    }
+
+    /// Returns the lines submitted since the last call, separated by
+    /// line breaks, and clears them.
+    pub fn take_committed(&mut self) -> String {
+        std::mem::take(&mut self.committed)
+    }
+
+    /// Clears the line being typed, e.g. when the chat box is closed.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.preview.clear();
+        self.cursor = 0;
+    }
+
+    fn commit(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+
+        if !self.committed.is_empty() {
+            self.committed.push('\n');
+        }
+
+        self.committed.push_str(&self.text);
+        self.clear();
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        let next = self.text[self.cursor..].graphemes(true).next();
+        self.cursor + next.map_or(0, str::len)
+    }
+}
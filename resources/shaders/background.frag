@@ -0,0 +1,26 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    vec4 inner_color;
+    vec4 outer_color;
+    uint mode; // 0 = vertical gradient, 1 = radial
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    float t;
+
+    if (mode == 1) {
+        // 0.0 at the center, 1.0 at the corners.
+        t = length(vert_uv - 0.5) / length(vec2(0.5));
+    } else {
+        t = vert_uv.y;
+    }
+
+    color_out = mix(inner_color, outer_color, clamp(t, 0.0, 1.0));
+}
@@ -0,0 +1,24 @@
+[[block]]
+struct Locals {
+    inner_color: vec4<f32>;
+    outer_color: vec4<f32>;
+    // 0 = vertical gradient, 1 = radial
+    mode: u32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    var t: f32 = vert_uv.y;
+
+    if (locals.mode == 1u) {
+        // 0.0 at the center, 1.0 at the corners.
+        t = length(vert_uv - vec2<f32>(0.5, 0.5)) / length(vec2<f32>(0.5, 0.5));
+    }
+
+    let blend = clamp(t, 0.0, 1.0);
+    return mix(locals.inner_color, locals.outer_color, vec4<f32>(blend, blend, blend, blend));
+}
@@ -0,0 +1,33 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D bloom_texture;
+layout(set = 0, binding = 1) uniform sampler bloom_sampler;
+
+layout(set = 0, binding = 2) uniform Params {
+    vec2 direction;
+    float threshold;
+    float intensity;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+// A 9 tap gaussian, folded into 5 linearly filtered samples
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main() {
+    vec2 texel = direction / vec2(textureSize(sampler2D(bloom_texture, bloom_sampler), 0));
+
+    vec3 color = texture(sampler2D(bloom_texture, bloom_sampler), vert_uv).rgb * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+        vec2 offset = texel * OFFSETS[i];
+        color += texture(sampler2D(bloom_texture, bloom_sampler), vert_uv + offset).rgb * WEIGHTS[i];
+        color += texture(sampler2D(bloom_texture, bloom_sampler), vert_uv - offset).rgb * WEIGHTS[i];
+    }
+
+    color_out = vec4(color, 1.0);
+}
@@ -0,0 +1,33 @@
+[[group(0), binding(0)]]
+var bloom_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var bloom_sampler: sampler;
+
+[[block]]
+struct Params {
+    direction: vec2<f32>;
+    threshold: f32;
+    intensity: f32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+fn sample_pair(uv: vec2<f32>, offset: vec2<f32>) -> vec3<f32> {
+    return textureSample(bloom_texture, bloom_sampler, uv + offset).rgb
+        + textureSample(bloom_texture, bloom_sampler, uv - offset).rgb;
+}
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let size = textureDimensions(bloom_texture);
+    let texel = params.direction / vec2<f32>(f32(size.x), f32(size.y));
+
+    // A 9 tap gaussian, folded into 5 linearly filtered samples
+    var color: vec3<f32> = textureSample(bloom_texture, bloom_sampler, vert_uv).rgb * 0.2270270270;
+    color = color + sample_pair(vert_uv, texel * 1.3846153846) * 0.3162162162;
+    color = color + sample_pair(vert_uv, texel * 3.2307692308) * 0.0702702703;
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D bloom_texture;
+layout(set = 0, binding = 1) uniform sampler bloom_sampler;
+
+layout(set = 0, binding = 2) uniform Params {
+    vec2 direction;
+    float threshold;
+    float intensity;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    vec3 color = texture(sampler2D(bloom_texture, bloom_sampler), vert_uv).rgb;
+
+    // Added onto the frame target by the blend state
+    color_out = vec4(color * intensity, 0.0);
+}
@@ -0,0 +1,23 @@
+[[group(0), binding(0)]]
+var bloom_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var bloom_sampler: sampler;
+
+[[block]]
+struct Params {
+    direction: vec2<f32>;
+    threshold: f32;
+    intensity: f32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(bloom_texture, bloom_sampler, vert_uv).rgb;
+
+    // Added onto the frame target by the blend state
+    return vec4<f32>(color * params.intensity, 0.0);
+}
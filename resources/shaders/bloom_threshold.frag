@@ -0,0 +1,27 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler scene_sampler;
+
+layout(set = 0, binding = 2) uniform Params {
+    vec2 direction;
+    float threshold;
+    float intensity;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    vec3 color = texture(sampler2D(scene_texture, scene_sampler), vert_uv).rgb;
+
+    // Keep only what's above the threshold, scaling the whole color
+    // so bright pixels keep their hue.
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+
+    color_out = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,27 @@
+[[group(0), binding(0)]]
+var scene_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var scene_sampler: sampler;
+
+[[block]]
+struct Params {
+    direction: vec2<f32>;
+    threshold: f32;
+    intensity: f32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(scene_texture, scene_sampler, vert_uv).rgb;
+
+    // Keep only what's above the threshold, scaling the whole color
+    // so bright pixels keep their hue.
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - params.threshold, 0.0) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * contribution, 1.0);
+}
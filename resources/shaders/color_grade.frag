@@ -0,0 +1,33 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler scene_sampler;
+
+layout(set = 0, binding = 2) uniform Params {
+    float brightness;
+    float contrast;
+    float saturation;
+    float gamma;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+// Rec. 709 luma weights
+const vec3 LUMA = vec3(0.2126, 0.7152, 0.0722);
+
+void main() {
+    vec4 scene = texture(sampler2D(scene_texture, scene_sampler), vert_uv);
+    vec3 color = scene.rgb;
+
+    // Applied in this order: brightness, contrast, saturation, gamma
+    color += brightness;
+    color = (color - 0.5) * contrast + 0.5;
+    color = mix(vec3(dot(color, LUMA)), color, saturation);
+    color = pow(clamp(color, 0.0, 1.0), vec3(1.0 / gamma));
+
+    color_out = vec4(color, scene.a);
+}
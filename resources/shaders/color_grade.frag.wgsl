@@ -0,0 +1,36 @@
+[[group(0), binding(0)]]
+var scene_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var scene_sampler: sampler;
+
+[[block]]
+struct Params {
+    brightness: f32;
+    contrast: f32;
+    saturation: f32;
+    gamma: f32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+fn splat(value: f32) -> vec3<f32> {
+    return vec3<f32>(value, value, value);
+}
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let scene = textureSample(scene_texture, scene_sampler, vert_uv);
+    var color: vec3<f32> = scene.rgb;
+
+    // Applied in this order: brightness, contrast, saturation, gamma
+    color = color + splat(params.brightness);
+    color = (color - splat(0.5)) * params.contrast + splat(0.5);
+    // Rec. 709 luma weights
+    let luma = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    color = mix(splat(luma), color, splat(params.saturation));
+    color = pow(clamp(color, splat(0.0), splat(1.0)), splat(1.0 / params.gamma));
+
+    return vec4<f32>(color, scene.a);
+}
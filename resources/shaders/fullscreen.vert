@@ -0,0 +1,15 @@
+#version 450
+
+// A single triangle covering the whole screen, generated from the vertex index
+// so no vertex buffer is needed.
+
+// Shader output
+layout(location = 0) out vec2 vert_uv;
+
+void main() {
+    vec2 pos = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2)) * 2.0 - 1.0;
+
+    // Texture coordinates have Y pointing down
+    vert_uv = vec2(pos.x + 1.0, 1.0 - pos.y) * 0.5;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
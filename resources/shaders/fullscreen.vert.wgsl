@@ -0,0 +1,20 @@
+// A single triangle covering the whole screen, generated from the vertex index
+// so no vertex buffer is needed.
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_uv: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    let pos = corner * 2.0 - vec2<f32>(1.0, 1.0);
+
+    var out: VertexOutput;
+    // Texture coordinates have Y pointing down
+    out.vert_uv = vec2<f32>(pos.x + 1.0, 1.0 - pos.y) * 0.5;
+    out.position = vec4<f32>(pos, 0.0, 1.0);
+    return out;
+}
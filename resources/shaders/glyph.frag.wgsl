@@ -0,0 +1,14 @@
+[[group(0), binding(1)]]
+var glyph_texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var glyph_texture_sampler: sampler;
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main(
+    [[location(0)]] glyph_uv: vec2<f32>,
+    [[location(1)]] glyph_color: vec4<f32>
+) -> [[location(0)]] vec4<f32> {
+    let glyph_alpha = textureSample(glyph_texture, glyph_texture_sampler, glyph_uv).r;
+    return vec4<f32>(glyph_color.rgb, glyph_alpha * glyph_color.a);
+}
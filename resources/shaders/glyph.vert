@@ -13,13 +13,19 @@ layout(location = 1) in vec2 pos;
 layout(location = 2) in vec2 size; // (width, height)
 layout(location = 3) in vec4 uv_extents; // (u, v, width, height), texture space
 layout(location = 4) in vec4 color;
+layout(location = 5) in vec4 outline_color;
+layout(location = 6) in float outline_width; // 0.0 uses the SdfParams outline
 
 layout(location = 0) out vec2 glyph_uv;
 layout(location = 1) out vec4 glyph_color;
+layout(location = 2) flat out vec4 glyph_outline_color;
+layout(location = 3) flat out float glyph_outline_width;
 
 void main() {
     glyph_uv = uv_extents.xy + (uv_extents.zw * uv);
     glyph_color = color;
+    glyph_outline_color = outline_color;
+    glyph_outline_width = outline_width;
 
     vec4 output_pos = vec4(pos + (size * uv), 0.0, 1.0);
     gl_Position = proj * output_pos;
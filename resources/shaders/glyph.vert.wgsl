@@ -0,0 +1,40 @@
+[[block]]
+struct Locals {
+    proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] glyph_uv: vec2<f32>;
+    [[location(1)]] glyph_color: vec4<f32>;
+    [[location(2), interpolate(flat)]] glyph_outline_color: vec4<f32>;
+    [[location(3), interpolate(flat)]] glyph_outline_width: f32;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// `uv` is the normalized, default UV coordinates from the glyph quad,
+// the rest are attributes from the instance array.
+[[stage(vertex)]]
+fn main(
+    [[location(0)]] uv: vec2<f32>,
+    [[location(1)]] pos: vec2<f32>,
+    // (width, height)
+    [[location(2)]] size: vec2<f32>,
+    // (u, v, width, height), texture space
+    [[location(3)]] uv_extents: vec4<f32>,
+    [[location(4)]] color: vec4<f32>,
+    [[location(5)]] outline_color: vec4<f32>,
+    // 0.0 uses the SdfParams outline
+    [[location(6)]] outline_width: f32
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.glyph_uv = uv_extents.xy + (uv_extents.zw * uv);
+    out.glyph_color = color;
+    out.glyph_outline_color = outline_color;
+    out.glyph_outline_width = outline_width;
+    out.position = locals.proj * vec4<f32>(pos + (size * uv), 0.0, 1.0);
+    return out;
+}
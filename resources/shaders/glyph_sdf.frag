@@ -0,0 +1,46 @@
+#version 450
+
+layout(set = 0, binding = 1) uniform texture2D glyph_texture;
+layout(set = 0, binding = 2) uniform sampler glyph_texture_sampler;
+
+// Widths are in distance field units, the glyph edge is at 0.5.
+layout(set = 0, binding = 3) uniform SdfParams {
+    vec4 outline_color;
+    vec4 glow_color;
+    float outline_width;
+    float glow_width;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 glyph_uv;
+layout(location = 1) in vec4 glyph_color;
+layout(location = 2) flat in vec4 glyph_outline_color;
+layout(location = 3) flat in float glyph_outline_width;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    float dist = texture(sampler2D(glyph_texture, glyph_texture_sampler), glyph_uv).r;
+
+    // Antialias over roughly one screen pixel, whatever the scale.
+    float smoothing = 0.7 * fwidth(dist);
+
+    // Per string outlines from a TextStyle take over from the uniform one.
+    bool styled = glyph_outline_width > 0.0;
+    vec4 edge_color = styled ? glyph_outline_color : outline_color;
+    float edge_width = styled ? glyph_outline_width : outline_width;
+
+    float fill_edge = 0.5;
+    float outline_edge = fill_edge - edge_width;
+    float glow_edge = outline_edge - glow_width;
+
+    float fill = smoothstep(fill_edge - smoothing, fill_edge + smoothing, dist);
+    float outline = smoothstep(outline_edge - smoothing, outline_edge + smoothing, dist);
+    float glow = smoothstep(glow_edge, outline_edge, dist);
+
+    vec4 body = mix(edge_color, glyph_color, fill);
+    vec4 halo = vec4(glow_color.rgb, glow_color.a * glow);
+
+    color_out = mix(halo, vec4(body.rgb, body.a * outline), outline);
+}
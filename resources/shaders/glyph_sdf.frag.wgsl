@@ -0,0 +1,55 @@
+[[group(0), binding(1)]]
+var glyph_texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var glyph_texture_sampler: sampler;
+
+// Widths are in distance field units, the glyph edge is at 0.5.
+[[block]]
+struct SdfParams {
+    outline_color: vec4<f32>;
+    glow_color: vec4<f32>;
+    outline_width: f32;
+    glow_width: f32;
+};
+
+[[group(0), binding(3)]]
+var<uniform> params: SdfParams;
+
+fn splat(value: f32) -> vec4<f32> {
+    return vec4<f32>(value, value, value, value);
+}
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main(
+    [[location(0)]] glyph_uv: vec2<f32>,
+    [[location(1)]] glyph_color: vec4<f32>,
+    [[location(2), interpolate(flat)]] glyph_outline_color: vec4<f32>,
+    [[location(3), interpolate(flat)]] glyph_outline_width: f32
+) -> [[location(0)]] vec4<f32> {
+    let dist = textureSample(glyph_texture, glyph_texture_sampler, glyph_uv).r;
+
+    // Antialias over roughly one screen pixel, whatever the scale.
+    let smoothing = 0.7 * fwidth(dist);
+
+    // Per string outlines from a TextStyle take over from the uniform one.
+    var edge_color: vec4<f32> = params.outline_color;
+    var edge_width: f32 = params.outline_width;
+    if (glyph_outline_width > 0.0) {
+        edge_color = glyph_outline_color;
+        edge_width = glyph_outline_width;
+    }
+
+    let fill_edge = 0.5;
+    let outline_edge = fill_edge - edge_width;
+    let glow_edge = outline_edge - params.glow_width;
+
+    let fill = smoothstep(fill_edge - smoothing, fill_edge + smoothing, dist);
+    let outline = smoothstep(outline_edge - smoothing, outline_edge + smoothing, dist);
+    let glow = smoothstep(glow_edge, outline_edge, dist);
+
+    let body = mix(edge_color, glyph_color, splat(fill));
+    let halo = vec4<f32>(params.glow_color.rgb, params.glow_color.a * glow);
+
+    return mix(halo, vec4<f32>(body.rgb, body.a * outline), splat(outline));
+}
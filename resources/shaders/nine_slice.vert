@@ -0,0 +1,17 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 proj;
+};
+
+// Vertex attributes, position is in screen space pixels
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec2 uv;
+
+// Shader output
+layout(location = 0) out vec2 vert_uv;
+
+void main() {
+    vert_uv = uv;
+    gl_Position = proj * vec4(pos, 0.0, 1.0);
+}
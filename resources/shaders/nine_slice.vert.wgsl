@@ -0,0 +1,22 @@
+[[block]]
+struct Locals {
+    proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_uv: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex attributes, position is in screen space pixels
+[[stage(vertex)]]
+fn main([[location(0)]] pos: vec2<f32>, [[location(1)]] uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.vert_uv = uv;
+    out.position = locals.proj * vec4<f32>(pos, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec2 vert_offset;
+layout(location = 1) in vec4 vert_color;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Soft round particles, fading out towards the edge of the quad.
+    float falloff = 1.0 - smoothstep(0.5, 1.0, length(vert_offset));
+    out_color = vec4(vert_color.rgb, vert_color.a * falloff);
+}
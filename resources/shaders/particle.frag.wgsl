@@ -0,0 +1,10 @@
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main(
+    [[location(0)]] vert_offset: vec2<f32>,
+    [[location(1)]] vert_color: vec4<f32>
+) -> [[location(0)]] vec4<f32> {
+    // Soft round particles, fading out towards the edge of the quad.
+    let falloff = 1.0 - smoothstep(0.5, 1.0, length(vert_offset));
+    return vec4<f32>(vert_color.rgb, vert_color.a * falloff);
+}
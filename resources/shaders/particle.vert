@@ -0,0 +1,28 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 view_proj;
+};
+
+// Instance attributes, straight from the particle storage buffer
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+layout(location = 2) in float life;
+layout(location = 3) in float lifetime;
+layout(location = 4) in float size;
+
+// Shader output
+layout(location = 0) out vec2 vert_offset;
+layout(location = 1) out vec4 vert_color;
+
+void main() {
+    // Triangle strip corners: top left, top right, bottom left, bottom right
+    vec2 corner = vec2(gl_VertexIndex & 1, gl_VertexIndex >> 1) * 2.0 - 1.0;
+
+    // Dead particles collapse to a point, which draws nothing.
+    float alive = life > 0.0 ? 1.0 : 0.0;
+
+    vert_offset = corner;
+    vert_color = vec4(color.rgb, color.a * clamp(life / lifetime, 0.0, 1.0));
+    gl_Position = view_proj * vec4(position + corner * size * 0.5 * alive, 0.0, 1.0);
+}
@@ -0,0 +1,38 @@
+[[block]]
+struct Locals {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_offset: vec2<f32>;
+    [[location(1)]] vert_color: vec4<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Instance attributes, straight from the particle storage buffer
+[[stage(vertex)]]
+fn main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[location(0)]] position: vec2<f32>,
+    [[location(1)]] color: vec4<f32>,
+    [[location(2)]] life: f32,
+    [[location(3)]] lifetime: f32,
+    [[location(4)]] size: f32
+) -> VertexOutput {
+    // Triangle strip corners: top left, top right, bottom left, bottom right
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u)) * 2.0
+        - vec2<f32>(1.0, 1.0);
+
+    // Dead particles collapse to a point, which draws nothing.
+    let alive = select(0.0, 1.0, life > 0.0);
+
+    var out: VertexOutput;
+    out.vert_offset = corner;
+    out.vert_color = vec4<f32>(color.rgb, color.a * clamp(life / lifetime, 0.0, 1.0));
+    out.position = locals.view_proj * vec4<f32>(position + corner * size * 0.5 * alive, 0.0, 1.0);
+    return out;
+}
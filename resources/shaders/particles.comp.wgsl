@@ -0,0 +1,45 @@
+struct Particle {
+    position: vec2<f32>;
+    velocity: vec2<f32>;
+    color: vec4<f32>;
+    life: f32;
+    lifetime: f32;
+    size: f32;
+    padding: f32;
+};
+
+[[block]]
+struct Params {
+    gravity: vec2<f32>;
+    dt: f32;
+    particle_count: u32;
+};
+
+[[block]]
+struct Particles {
+    particles: [[stride(48)]] array<Particle>;
+};
+
+[[group(0), binding(0)]]
+var<storage> params: [[access(read)]] Params;
+
+[[group(0), binding(1)]]
+var<storage> particles: [[access(read_write)]] Particles;
+
+[[stage(compute), workgroup_size(64)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    let index = global_id.x;
+    if (index >= params.particle_count) {
+        return;
+    }
+
+    var particle: Particle = particles.particles[index];
+    if (particle.life <= 0.0) {
+        return;
+    }
+
+    particle.velocity = particle.velocity + params.gravity * params.dt;
+    particle.position = particle.position + particle.velocity * params.dt;
+    particle.life = particle.life - params.dt;
+    particles.particles[index] = particle;
+}
@@ -0,0 +1,11 @@
+#version 450
+
+// Input from vertex shader
+layout(location = 0) flat in uint vert_id;
+
+// Fragment shader output, into an R32Uint target
+layout(location = 0) out uint id_out;
+
+void main() {
+    id_out = vert_id;
+}
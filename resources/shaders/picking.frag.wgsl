@@ -0,0 +1,5 @@
+// Input from vertex shader, output into an R32Uint target
+[[stage(fragment)]]
+fn main([[location(0), interpolate(flat)]] vert_id: u32) -> [[location(0)]] u32 {
+    return vert_id;
+}
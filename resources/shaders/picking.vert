@@ -0,0 +1,17 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 view_proj;
+};
+
+// Vertex attributes, position is in world space
+layout(location = 0) in vec2 pos;
+layout(location = 1) in uint id;
+
+// Shader output
+layout(location = 0) flat out uint vert_id;
+
+void main() {
+    vert_id = id;
+    gl_Position = view_proj * vec4(pos, 0.0, 1.0);
+}
@@ -0,0 +1,22 @@
+[[block]]
+struct Locals {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0), interpolate(flat)]] vert_id: u32;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex attributes, position is in world space
+[[stage(vertex)]]
+fn main([[location(0)]] pos: vec2<f32>, [[location(1)]] id: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.vert_id = id;
+    out.position = locals.view_proj * vec4<f32>(pos, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,14 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler scene_sampler;
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    color_out = texture(sampler2D(scene_texture, scene_sampler), vert_uv);
+}
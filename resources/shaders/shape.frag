@@ -0,0 +1,37 @@
+#version 450
+
+const float TAU = 6.28318530718;
+
+// Input from vertex shader
+layout(location = 0) in vec4 vert_color;
+
+// Offset from the ring's center, only used by rings.
+layout(location = 1) in vec2 vert_local;
+
+// Radius, half thickness, start angle and sweep of the ring, a half
+// thickness of 0 for solid shapes.
+layout(location = 2) flat in vec4 vert_ring;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    float coverage = 1.0;
+
+    if (vert_ring.y > 0.0) {
+        float radius = length(vert_local);
+        float ring_dist = abs(radius - vert_ring.x) - vert_ring.y;
+
+        // Mask out the part of the ring outside the sweep, by the distance
+        // along the ring to the nearest end of the arc.
+        float angle = mod(atan(vert_local.y, vert_local.x) - vert_ring.z, TAU);
+        float outside = min(angle - vert_ring.w, TAU - angle) * radius;
+        float dist = angle > vert_ring.w ? max(ring_dist, outside) : ring_dist;
+
+        // Antialias over one screen pixel, whatever the camera zoom.
+        float pixel = max(fwidth(radius), 0.0001);
+        coverage = clamp(0.5 - dist / pixel, 0.0, 1.0);
+    }
+
+    color_out = vec4(vert_color.rgb, vert_color.a * coverage);
+}
@@ -0,0 +1,34 @@
+// Input from vertex shader, output to the render target. `vert_local` is the
+// offset from the ring's center, only used by rings. `vert_ring` holds the
+// radius, half thickness, start angle and sweep of the ring, a half
+// thickness of 0 for solid shapes.
+[[stage(fragment)]]
+fn main(
+    [[location(0)]] vert_color: vec4<f32>,
+    [[location(1)]] vert_local: vec2<f32>,
+    [[location(2), interpolate(flat)]] vert_ring: vec4<f32>
+) -> [[location(0)]] vec4<f32> {
+    let tau = 6.28318530718;
+
+    // Derivatives are taken outside of the branch, antialiasing
+    // over one screen pixel, whatever the camera zoom.
+    let radius = length(vert_local);
+    let pixel = max(fwidth(radius), 0.0001);
+
+    var coverage: f32 = 1.0;
+    if (vert_ring.y > 0.0) {
+        let ring_dist = abs(radius - vert_ring.x) - vert_ring.y;
+
+        // Mask out the part of the ring outside the sweep, by the distance
+        // along the ring to the nearest end of the arc. Wrapped like GLSL's
+        // mod, as % truncates.
+        let turn = atan2(vert_local.y, vert_local.x) - vert_ring.z;
+        let angle = turn - tau * floor(turn / tau);
+        let outside = min(angle - vert_ring.w, tau - angle) * radius;
+        let dist = select(ring_dist, max(ring_dist, outside), angle > vert_ring.w);
+
+        coverage = clamp(0.5 - dist / pixel, 0.0, 1.0);
+    }
+
+    return vec4<f32>(vert_color.rgb, vert_color.a * coverage);
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 view_proj;
+};
+
+// Vertex attributes, position is in world space
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 local;
+layout(location = 3) in vec4 ring;
+
+// Shader output
+layout(location = 0) out vec4 vert_color;
+layout(location = 1) out vec2 vert_local;
+layout(location = 2) flat out vec4 vert_ring;
+
+void main() {
+    vert_color = color;
+    vert_local = local;
+    vert_ring = ring;
+    gl_Position = view_proj * vec4(pos, 0.0, 1.0);
+}
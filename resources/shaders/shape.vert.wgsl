@@ -0,0 +1,31 @@
+[[block]]
+struct Locals {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_color: vec4<f32>;
+    [[location(1)]] vert_local: vec2<f32>;
+    [[location(2), interpolate(flat)]] vert_ring: vec4<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex attributes, position is in world space
+[[stage(vertex)]]
+fn main(
+    [[location(0)]] pos: vec2<f32>,
+    [[location(1)]] color: vec4<f32>,
+    [[location(2)]] local: vec2<f32>,
+    [[location(3)]] ring: vec4<f32>
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.vert_color = color;
+    out.vert_local = local;
+    out.vert_ring = ring;
+    out.position = locals.view_proj * vec4<f32>(pos, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    vec4 color;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    color_out = color;
+}
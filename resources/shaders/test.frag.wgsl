@@ -0,0 +1,5 @@
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(vert_uv.x, vert_uv.y, 1.0, 1.0);
+}
@@ -4,6 +4,11 @@
 layout(location = 0) in vec2 pos;
 layout(location = 1) in vec2 uv;
 
+// Maps the quad's corners from -1.0 to 1.0 into clip space.
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 transform;
+};
+
 // Shader output
 layout(location = 0) out vec2 vert_uv;
 
@@ -13,5 +18,6 @@ out gl_PerVertex {
 
 void main() {
     vert_uv = uv;
-    gl_Position = vec4(pos, 0.0, 1.0);
+    // Halfway into the depth range, so a depth bias can move quads either way.
+    gl_Position = transform * vec4(pos, 0.5, 1.0);
 }
@@ -0,0 +1,24 @@
+// Maps the quad's corners from -1.0 to 1.0 into clip space.
+[[block]]
+struct Transform {
+    transform: mat4x4<f32>;
+};
+
+[[group(1), binding(0)]]
+var<uniform> transform: Transform;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_uv: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Vertex attributes
+[[stage(vertex)]]
+fn main([[location(0)]] pos: vec2<f32>, [[location(1)]] uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.vert_uv = uv;
+    // Halfway into the depth range, so a depth bias can move quads either way.
+    out.position = transform.transform * vec4<f32>(pos, 0.5, 1.0);
+    return out;
+}
@@ -0,0 +1,23 @@
+#version 450
+
+// Vertex attributes
+layout(location = 0) in vec2 pos;
+layout(location = 1) in vec2 uv;
+
+// Maps the quad's corners from -1.0 to 1.0 into clip space.
+layout(push_constant) uniform Transform {
+    mat4 transform;
+};
+
+// Shader output
+layout(location = 0) out vec2 vert_uv;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+void main() {
+    vert_uv = uv;
+    // Halfway into the depth range, so a depth bias can move quads either way.
+    gl_Position = transform * vec4(pos, 0.5, 1.0);
+}
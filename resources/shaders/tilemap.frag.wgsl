@@ -0,0 +1,10 @@
+[[group(0), binding(1)]]
+var tile_texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var tile_sampler: sampler;
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    return textureSample(tile_texture, tile_sampler, vert_uv);
+}
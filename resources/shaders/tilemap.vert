@@ -0,0 +1,20 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 view_proj;
+};
+
+// Instance attributes, the tile's rect is in world space
+layout(location = 0) in vec4 rect;
+layout(location = 1) in vec4 uv_rect;
+
+// Shader output
+layout(location = 0) out vec2 vert_uv;
+
+void main() {
+    // Triangle strip corners: top left, top right, bottom left, bottom right
+    vec2 corner = vec2(gl_VertexIndex & 1, gl_VertexIndex >> 1);
+
+    vert_uv = mix(uv_rect.xy, uv_rect.zw, corner);
+    gl_Position = view_proj * vec4(rect.xy + corner * rect.zw, 0.0, 1.0);
+}
@@ -0,0 +1,29 @@
+[[block]]
+struct Locals {
+    view_proj: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> locals: Locals;
+
+// Shader output
+struct VertexOutput {
+    [[location(0)]] vert_uv: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// Instance attributes, the tile's rect is in world space
+[[stage(vertex)]]
+fn main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[location(0)]] rect: vec4<f32>,
+    [[location(1)]] uv_rect: vec4<f32>
+) -> VertexOutput {
+    // Triangle strip corners: top left, top right, bottom left, bottom right
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u));
+
+    var out: VertexOutput;
+    out.vert_uv = mix(uv_rect.xy, uv_rect.zw, corner);
+    out.position = locals.view_proj * vec4<f32>(rect.xy + corner * rect.zw, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,39 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D scene_texture;
+layout(set = 0, binding = 1) uniform sampler scene_sampler;
+
+layout(set = 0, binding = 2) uniform Params {
+    float exposure;
+    // 0 = Reinhard, 1 = ACES
+    uint tone_operator;
+};
+
+// Input from vertex shader
+layout(location = 0) in vec2 vert_uv;
+
+// Fragment shader output
+layout(location = 0) out vec4 color_out;
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main() {
+    vec4 scene = texture(sampler2D(scene_texture, scene_sampler), vert_uv);
+    vec3 color = max(scene.rgb, vec3(0.0)) * exposure;
+
+    if (tone_operator == 0) {
+        color = color / (1.0 + color);
+    } else {
+        color = aces(color);
+    }
+
+    color_out = vec4(color, clamp(scene.a, 0.0, 1.0));
+}
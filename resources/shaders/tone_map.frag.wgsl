@@ -0,0 +1,43 @@
+[[group(0), binding(0)]]
+var scene_texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var scene_sampler: sampler;
+
+[[block]]
+struct Params {
+    exposure: f32;
+    // 0 = Reinhard, 1 = ACES
+    tone_operator: u32;
+};
+
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+fn splat(value: f32) -> vec3<f32> {
+    return vec3<f32>(value, value, value);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = splat(0.03);
+    let c = 2.43;
+    let d = splat(0.59);
+    let e = splat(0.14);
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), splat(0.0), splat(1.0));
+}
+
+// Input from vertex shader, output to the render target
+[[stage(fragment)]]
+fn main([[location(0)]] vert_uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let scene = textureSample(scene_texture, scene_sampler, vert_uv);
+    var color: vec3<f32> = max(scene.rgb, splat(0.0)) * params.exposure;
+
+    if (params.tone_operator == 0u) {
+        color = color / (splat(1.0) + color);
+    } else {
+        color = aces(color);
+    }
+
+    return vec4<f32>(color, clamp(scene.a, 0.0, 1.0));
+}